@@ -4,8 +4,8 @@
 use anyhow::{anyhow, Context, Error, Result};
 use clap::Parser;
 use crafty::{
-    data, Action, CraftContext, CraftOptions, CraftResult, CraftState, Player, Recipe,
-    SearchOptions, Simulator,
+    data, macros, macros::MacroOptions, Action, CraftContext, CraftOptions, CraftResult,
+    CraftState, Player, Recipe, SearchOptions, Simulator, MAX_STEPS_RANGE,
 };
 use dialoguer::{
     console::{Style, StyledObject},
@@ -39,6 +39,26 @@ struct Args {
     #[clap(short = 's', long, default_value_t = 25_u8, display_order = 1000)]
     steps: u8,
 
+    /// The quality to aim for, if lower than the recipe's max quality
+    #[clap(long, conflicts_with = "collectability", display_order = 1001)]
+    quality_target: Option<u32>,
+
+    /// The collectability to aim for, instead of a quality target
+    #[clap(long, display_order = 1002)]
+    collectability: Option<u32>,
+
+    /// The player is a specialist of this job
+    #[clap(long, display_order = 1010)]
+    specialist: bool,
+
+    /// Don't use Manipulation, e.g. if it hasn't been learned yet
+    #[clap(long, display_order = 1011)]
+    no_manipulation: bool,
+
+    /// Allow using a delineation for Quick Innovation. Only specialists can use delineations
+    #[clap(long, requires = "specialist", display_order = 1012)]
+    delineation: bool,
+
     /// The number of craft simulations to run per search
     #[clap(short = 'i', long, default_value_t = 500_000_u32, display_order = 1101)]
     search_iterations: u32,
@@ -47,7 +67,8 @@ struct Args {
     #[clap(short = 'p', long, default_value_t = 1_u16, display_order = 1102)]
     search_pool_size: u16,
 
-    /// Search mode (stepwise or oneshot)
+    /// Search mode (stepwise, oneshot, or parallel). Parallel runs a oneshot search for each tree in
+    /// the search pool, sharing statistics between them
     #[clap(short = 'm', long, default_value_t = SearchMode::Stepwise, display_order = 1103)]
     search_mode: SearchMode,
 
@@ -57,20 +78,22 @@ struct Args {
 
     /// A constant used for search. The higher the weight, the more a node's potential max score
     /// is valued over its average score. A weight of 1.0 means only max scores will be used; 0.0
-    /// means only average scores will be used.
-    #[clap(short = 'w', default_value_t = 0.1_f32, display_order = 2000)]
-    max_score_weighting_constant: f32,
+    /// means only average scores will be used. Defaults to 0.2 for stepwise searches, and 0.1
+    /// otherwise.
+    #[clap(short = 'w', display_order = 2000)]
+    max_score_weighting_constant: Option<f32>,
 
     /// A constant used for search. Higher values will cause nodes with more uncertain scores to be
-    /// explored.
-    #[clap(short = 'c', default_value_t = 1.5_f32, display_order = 2000)]
-    exploration_constant: f32,
+    /// explored. Defaults to 1.0 for stepwise searches, and 1.5 otherwise.
+    #[clap(short = 'c', display_order = 2000)]
+    exploration_constant: Option<f32>,
 }
 
 #[derive(Debug, Clone, Copy)]
 enum SearchMode {
     Stepwise,
     Oneshot,
+    Parallel,
 }
 
 impl std::fmt::Display for SearchMode {
@@ -86,7 +109,10 @@ impl std::str::FromStr for SearchMode {
         match s {
             "stepwise" => Ok(Self::Stepwise),
             "oneshot" => Ok(Self::Oneshot),
-            _ => Err(anyhow!("expected \"stepwise\" or \"oneshot\"")),
+            "parallel" => Ok(Self::Parallel),
+            _ => Err(anyhow!(
+                "expected \"stepwise\", \"oneshot\", or \"parallel\""
+            )),
         }
     }
 }
@@ -108,15 +134,20 @@ fn main() -> Result<()> {
         iterations: args.search_iterations,
         rng_seed: args.seed,
         score_storage_threshold: Some(0.75),
-        max_score_weighting_constant: Some(args.max_score_weighting_constant),
-        exploration_constant: Some(args.exploration_constant),
+        max_score_weighting_constant: args.max_score_weighting_constant,
+        exploration_constant: args.exploration_constant,
+        ..Default::default()
     };
 
     let craft_options = CraftOptions {
         max_steps: args.steps,
+        quality_target: quality_target(&args, recipe)?,
+        player_is_specialist: args.specialist,
+        use_manipulation: !args.no_manipulation,
+        use_delineation: args.delineation,
         ..Default::default()
     };
-    let context = CraftContext::new(player, recipe, craft_options);
+    let context = CraftContext::try_new(player, recipe, craft_options)?;
     let mut action_history: Vec<Action> = vec![];
     loop {
         let (state, result) = Simulator::simulate(&context, action_history.clone());
@@ -124,9 +155,16 @@ fn main() -> Result<()> {
             None => {
                 print_state(&state);
             }
-            Some(CraftResult::Finished(_)) => {
-                println!("{}", green("\nThe craft is complete."),);
+            Some(CraftResult::Finished(metrics)) => {
+                println!(
+                    "{}",
+                    green(&format!(
+                        "\nThe craft is complete in {} steps ({}% HQ).",
+                        metrics.steps, metrics.hq_percent
+                    ))
+                );
                 print_state(&state);
+                print_info(&format!("  {}", state.explain_score()));
                 break;
             }
             _ => {
@@ -154,22 +192,33 @@ fn main() -> Result<()> {
 
             let instant = time::Instant::now();
 
-            // Run multiple simulations in parallel, and take the one with the max score
-            let (actions, result_state) = (0..args.search_pool_size)
-                .into_par_iter()
-                .map(|_| match args.search_mode {
-                    SearchMode::Stepwise => Simulator::search_stepwise(
-                        &context,
-                        action_history.clone(),
-                        search_options,
-                        None,
-                    ),
-                    SearchMode::Oneshot => {
-                        Simulator::search_oneshot(&context, action_history.clone(), search_options)
-                    }
-                })
-                .max_by(|(_, a), (_, b)| a.max_score.partial_cmp(&b.max_score).unwrap())
-                .unwrap();
+            let (actions, result_state) = if let SearchMode::Parallel = args.search_mode {
+                Simulator::search_root_parallel(
+                    &context,
+                    action_history.clone(),
+                    search_options,
+                    u32::from(args.search_pool_size),
+                )
+            } else {
+                // Run multiple simulations in parallel, and take the one with the max score
+                (0..args.search_pool_size)
+                    .into_par_iter()
+                    .map(|_| match args.search_mode {
+                        SearchMode::Stepwise => Simulator::search_stepwise(
+                            &context,
+                            action_history.clone(),
+                            search_options,
+                            None,
+                        ),
+                        SearchMode::Oneshot | SearchMode::Parallel => Simulator::search_oneshot(
+                            &context,
+                            action_history.clone(),
+                            search_options,
+                        ),
+                    })
+                    .max_by(|(_, a), (_, b)| a.max_score.partial_cmp(&b.max_score).unwrap())
+                    .unwrap()
+            };
 
             let elapsed = instant.elapsed().as_secs_f64();
             print_info(&format!("  completed in {elapsed} seconds."));
@@ -178,9 +227,7 @@ fn main() -> Result<()> {
 
             let action_count = actions.len();
             print_info(&format!("\n  {action_count} actions taken:\n"));
-            for action in actions {
-                println!("{}", action.macro_text());
-            }
+            print_macros(&actions)?;
 
             break;
         }
@@ -209,22 +256,38 @@ fn validate_args(args: &Args) -> Result<()> {
     is_between(args.cp, 1, 2000, "cp")?;
     is_between(args.search_iterations, 100, 10_000_000, "iteration count")?;
     is_between(args.search_pool_size, 1, 10_000, "search pool")?;
-    is_between(args.steps, 5, 50, "max steps")?;
     is_between(
-        args.max_score_weighting_constant,
-        0.0,
-        1.0,
-        "max score weighting constant",
-    )?;
-    is_between(
-        args.exploration_constant,
-        0.0,
-        1000.0,
-        "exploration constant",
+        args.steps,
+        *MAX_STEPS_RANGE.start(),
+        *MAX_STEPS_RANGE.end(),
+        "max steps",
     )?;
+    if let Some(weighting) = args.max_score_weighting_constant {
+        is_between(weighting, 0.0, 1.0, "max score weighting constant")?;
+    }
+    if let Some(exploration) = args.exploration_constant {
+        is_between(exploration, 0.0, 1000.0, "exploration constant")?;
+    }
     Ok(())
 }
 
+/// The quality target requested by `--quality-target` or `--collectability`,
+/// checked against the recipe. Prints the target that will be searched for.
+fn quality_target(args: &Args, recipe: &Recipe) -> Result<Option<u32>> {
+    let quality_target = args
+        .quality_target
+        .or(args.collectability.map(Recipe::quality_from_collectability));
+
+    if let Some(quality_target) = quality_target {
+        is_between(quality_target, 0, recipe.quality, "quality target")?;
+    }
+    print_info(&format!(
+        "\n  quality target: {}",
+        quality_target.unwrap_or(recipe.quality)
+    ));
+    Ok(quality_target)
+}
+
 fn prompt_selection<'a, T>(prompt: &str, items: &'a [T], is_fuzzy: bool) -> Result<&'a T>
 where
     T: std::fmt::Display,
@@ -256,7 +319,14 @@ fn prompt_recipe() -> Result<&'static Recipe> {
     let recipe_job_level: u32 = Input::with_theme(&ColorfulTheme::default())
         .with_prompt("recipe level? (1-100)")
         .with_initial_text("100")
-        .validate_with(|input: &u32| is_between(*input, 1, 100, "recipe level"))
+        .validate_with(|input: &u32| {
+            is_between(*input, 1, 100, "recipe level")?;
+            if data::available_recipe_levels().contains(input) {
+                Ok(())
+            } else {
+                Err(anyhow!("there are no recipes at level {}", input))
+            }
+        })
         .interact_text()?;
 
     let recipe_options = data::recipes(recipe_job_level);
@@ -265,13 +335,41 @@ fn prompt_recipe() -> Result<&'static Recipe> {
 }
 
 fn print_state(state: &CraftState) {
+    let percent = |value: u32, target: u32| {
+        if target == 0 {
+            100
+        } else {
+            u64::from(value) * 100 / u64::from(target)
+        }
+    };
+    let summary = format!(
+        "{:>4}% progress | {:>4}% quality | {:>3}% HQ",
+        percent(state.progress, state.context.progress_target),
+        percent(state.quality, state.context.quality_target),
+        state.hq_percent()
+    );
+
     println!(
-        "\n  step {:>2}: {}",
+        "\n  step {:>2}: {}\n           {}",
         state.step,
-        green(state.to_string().as_str())
+        green(state.to_string().as_str()),
+        green(&summary)
     );
 }
 
+fn print_macros(actions: &[Action]) -> Result<()> {
+    let macros = macros::generate_macros(actions, &MacroOptions::default())?;
+    for (i, lines) in macros.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        for line in lines {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
 fn print_info(info: &str) {
     println!("{}", cyan(info));
 }
@@ -18,6 +18,10 @@ fn setup_sim(rng_seed: Option<u32>) -> (CraftContext, SearchOptions) {
         quality_mod: 80,
         is_expert: false,
         conditions_flag: 15,
+        material_quality_factor: 0,
+        required_craftsmanship: 0,
+        required_control: 0,
+        item_id: 0,
     };
     let player = Player::new(90, 3304, 3374, 575);
     let craft_options = CraftOptions {
@@ -60,6 +64,25 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         });
     }
     group.finish();
+
+    // the web app searches stepwise, so it gets its own group
+    let mut group = c.benchmark_group("search_stepwise");
+    group
+        .sample_size(10)
+        .warm_up_time(Duration::new(5, 0))
+        .measurement_time(Duration::new(60, 0));
+    for seed in 0..5_u32 {
+        group.bench_function(seed.to_string().as_str(), |b| {
+            b.iter_batched(
+                || setup_sim(Some(seed)),
+                |(context, options)| {
+                    Simulator::search_stepwise(&context, black_box(vec![]), options, None);
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
 }
 
 criterion_group!(
@@ -1,6 +1,6 @@
 use recipe::Recipe;
 use serde::{de, Deserialize};
-use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::collections::{hash_map::Entry, HashMap};
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{BufWriter, Write};
@@ -33,19 +33,22 @@ fn process_recipe_tables() -> Result<(), Box<dyn std::error::Error>> {
         recipe_levels.insert(recipe_level.recipe_level, recipe_level);
     }
 
-    // Convert base values from RecipeLevelTable to actual PQD requirements for
-    // a recipe
-    fn apply_factor(base: u32, factor: u32) -> u32 {
-        ((base * factor) as f64 / 100.0).floor() as u32
-    }
+    check_known_recipes(&recipe_levels)?;
 
-    // Process the recipe table, and keep track of distinct recipe variants
-    let mut distinct_recipe_variants = HashSet::new();
+    // Process the recipe table, and keep track of distinct recipe variants.
+    // Variants are only distinguished by their attributes, so the item id is
+    // left out of the key and the lowest item id is kept for each variant.
+    let mut distinct_recipe_variants: HashMap<Recipe, u32> = HashMap::new();
 
     for record in recipes_csv.deserialize::<RecipeRecord>() {
         let recipe = record?;
         if recipe.can_hq {
-            let base = recipe_levels.get(&recipe.recipe_level).unwrap();
+            let base = recipe_levels.get(&recipe.recipe_level).ok_or_else(|| {
+                format!(
+                    "recipe level {} is missing from RecipeLevelTable.csv",
+                    recipe.recipe_level
+                )
+            })?;
             let variant = Recipe {
                 recipe_level: recipe.recipe_level,
                 job_level: base.job_level,
@@ -61,13 +64,23 @@ fn process_recipe_tables() -> Result<(), Box<dyn std::error::Error>> {
                 quality_mod: base.quality_modifier,
                 is_expert: recipe.is_expert,
                 conditions_flag: base.conditions_flag,
+                material_quality_factor: recipe.material_quality_factor,
+                required_craftsmanship: recipe.required_craftsmanship,
+                required_control: recipe.required_control,
+                item_id: 0,
             };
-            distinct_recipe_variants.insert(variant);
+            let item_id = distinct_recipe_variants
+                .entry(variant)
+                .or_insert(recipe.item_id);
+            *item_id = (*item_id).min(recipe.item_id);
         }
     }
 
     // Sort and group recipes by job level for ease of selection
-    let mut recipe_variants: Vec<_> = distinct_recipe_variants.into_iter().collect();
+    let mut recipe_variants: Vec<_> = distinct_recipe_variants
+        .into_iter()
+        .map(|(variant, item_id)| Recipe { item_id, ..variant })
+        .collect();
     recipe_variants.sort_by(|a, b| {
         (a.job_level.cmp(&b.job_level))
             .then(a.stars.cmp(&b.stars))
@@ -87,12 +100,36 @@ fn process_recipe_tables() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    // Prepare phf map
+    // Index each recipe level's variants by their job level group and their
+    // range within it. A recipe level has a single job level and star count,
+    // so its variants are next to each other after sorting.
+    let mut recipe_level_ranges: HashMap<u32, (u32, usize, usize)> = HashMap::new();
+    for (&job_level, group) in &recipes_by_level {
+        for (i, variant) in group.iter().enumerate() {
+            let range = recipe_level_ranges
+                .entry(variant.recipe_level)
+                .or_insert((job_level, i, i));
+            if range.0 != job_level || range.2 != i {
+                return Err(format!(
+                    "the variants of recipe level {} aren't next to each other",
+                    variant.recipe_level
+                )
+                .into());
+            }
+            range.2 = i + 1;
+        }
+    }
+
+    // Prepare phf maps
     let mut recipes = phf_codegen::Map::new();
     for (key, val) in recipes_by_level {
         let static_array = &format!("&{:?}", val);
         recipes.entry(key, static_array);
     }
+    let mut recipe_level_index = phf_codegen::Map::new();
+    for (key, val) in recipe_level_ranges {
+        recipe_level_index.entry(key, &format!("{:?}", val));
+    }
 
     // Generate source files
     let out_dir = env::var("OUT_DIR")?;
@@ -103,16 +140,83 @@ fn process_recipe_tables() -> Result<(), Box<dyn std::error::Error>> {
         "static RECIPES: phf::Map<u32, &'static [Recipe]> = {};\n",
         recipes.build()
     )?;
+    writeln!(
+        recipes_writer,
+        "static RECIPE_LEVELS: phf::Map<u32, (u32, usize, usize)> = {};\n",
+        recipe_level_index.build()
+    )?;
 
     Ok(())
 }
 
-#[allow(dead_code)]
+/// Convert base values from RecipeLevelTable to actual PQD requirements for a
+/// recipe. The game scales each base value by a percentage and rounds down.
+fn apply_factor(base: u32, factor: u32) -> u32 {
+    base * factor / 100
+}
+
+/// A recipe with PQD verified in game
+struct KnownRecipe {
+    recipe_level: u32,
+    /// Progress, quality, and durability factors from Recipe.csv
+    factors: [u32; 3],
+    /// Progress, quality, and durability shown in game
+    expected: [u32; 3],
+}
+
+const KNOWN_RECIPES: &[KnownRecipe] = &[
+    KnownRecipe {
+        recipe_level: 580,
+        factors: [100, 140, 100],
+        expected: [3900, 10920, 70],
+    },
+    KnownRecipe {
+        recipe_level: 690,
+        factors: [100, 100, 100],
+        expected: [6600, 12000, 80],
+    },
+];
+
+/// Fails the build if the derived PQD of a known recipe doesn't match the
+/// game, so a change to the data or to `apply_factor` can't slip through.
+fn check_known_recipes(
+    recipe_levels: &HashMap<u32, RecipeLevelRecord>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for known in KNOWN_RECIPES {
+        let base = recipe_levels.get(&known.recipe_level).ok_or_else(|| {
+            format!(
+                "recipe level {} is missing from RecipeLevelTable.csv",
+                known.recipe_level
+            )
+        })?;
+        let [progress_factor, quality_factor, durability_factor] = known.factors;
+        let derived = [
+            apply_factor(base.progress, progress_factor),
+            apply_factor(base.quality, quality_factor),
+            apply_factor(base.durability, durability_factor),
+        ];
+        if derived != known.expected {
+            return Err(format!(
+                "recipe level {} derives PQD {:?}, but the game has {:?}",
+                known.recipe_level, derived, known.expected
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 struct RecipeRecord {
     #[serde(rename = "RecipeLevelTable")]
     recipe_level: u32,
 
+    #[serde(rename = "Item{Result}")]
+    item_id: u32,
+
+    #[serde(rename = "MaterialQualityFactor")]
+    material_quality_factor: u32,
+
     #[serde(rename = "DifficultyFactor")]
     progress_factor: u32,
 
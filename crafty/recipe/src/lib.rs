@@ -4,7 +4,7 @@ use ts_type::{wasm_bindgen, TsType};
 
 // Must be separate from the `crafty` crate so it can be used in the build script
 
-#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, TsType)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, TsType)]
 pub struct Recipe {
     pub recipe_level: u32,
     pub job_level: u32,
@@ -18,6 +18,114 @@ pub struct Recipe {
     pub quality_mod: u32,
     pub is_expert: bool,
     pub conditions_flag: u32,
+    /// The percentage of `quality` that can be reached by using only HQ materials
+    pub material_quality_factor: u32,
+    /// Craftsmanship needed to start the craft, or 0 if there's no requirement
+    pub required_craftsmanship: u32,
+    /// Control needed to start the craft, or 0 if there's no requirement
+    pub required_control: u32,
+    /// Id of the item crafted by this recipe. Many items share identical
+    /// recipe attributes, in which case this is one representative item.
+    pub item_id: u32,
+}
+
+/// Collectable items show collectability instead of quality, which is quality
+/// divided by this amount, rounded down
+pub const COLLECTABILITY_DIVISOR: u32 = 10;
+
+impl Recipe {
+    /// The collectability of a craft with the given quality
+    pub fn collectability_from_quality(quality: u32) -> u32 {
+        quality / COLLECTABILITY_DIVISOR
+    }
+
+    /// The minimum quality needed to reach the given collectability
+    pub fn quality_from_collectability(collectability: u32) -> u32 {
+        collectability.saturating_mul(COLLECTABILITY_DIVISOR)
+    }
+}
+
+impl Recipe {
+    /// A hash of the recipe's attributes that stays the same across builds
+    /// and platforms, e.g. for keying cached search results. Unlike the
+    /// derived `Hash`, it doesn't depend on field order. `item_id` is left
+    /// out, since recipes with identical attributes craft the same way.
+    pub fn fingerprint(&self) -> u64 {
+        Fingerprinter::new()
+            .write_u64(self.recipe_level.into())
+            .write_u64(self.job_level.into())
+            .write_u64(self.stars.into())
+            .write_u64(self.progress.into())
+            .write_u64(self.quality.into())
+            .write_i64(self.durability.into())
+            .write_u64(self.progress_div.into())
+            .write_u64(self.progress_mod.into())
+            .write_u64(self.quality_div.into())
+            .write_u64(self.quality_mod.into())
+            .write_bool(self.is_expert)
+            .write_u64(self.conditions_flag.into())
+            .write_u64(self.material_quality_factor.into())
+            .write_u64(self.required_craftsmanship.into())
+            .write_u64(self.required_control.into())
+            .finish()
+    }
+}
+
+/// Builds a stable 64-bit FNV-1a hash from values written in a fixed order.
+/// Changing what's written, or the order, changes every fingerprint.
+#[derive(Debug, Clone, Copy)]
+pub struct Fingerprinter(u64);
+
+impl Fingerprinter {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    pub fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+        self
+    }
+
+    pub fn write_u64(&mut self, value: u64) -> &mut Self {
+        self.write(&value.to_le_bytes())
+    }
+
+    pub fn write_i64(&mut self, value: i64) -> &mut Self {
+        self.write(&value.to_le_bytes())
+    }
+
+    pub fn write_bool(&mut self, value: bool) -> &mut Self {
+        self.write(&[u8::from(value)])
+    }
+
+    /// Writes whether there's a value first, so that None and Some(0) differ
+    pub fn write_option_u64(&mut self, value: Option<u64>) -> &mut Self {
+        match value {
+            Some(value) => self.write_bool(true).write_u64(value),
+            None => self.write_bool(false),
+        }
+    }
+
+    /// Writes the length first, so that consecutive strings can't run together
+    pub fn write_str(&mut self, value: &str) -> &mut Self {
+        self.write_u64(value.len() as u64).write(value.as_bytes())
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fingerprinter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl fmt::Display for Recipe {
@@ -1,16 +1,37 @@
-use crate::CraftState;
+use crate::{BuffKind, Condition, CraftState};
 use enum_indexing::EnumIndexing;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{cmp, fmt};
 use ts_type::{wasm_bindgen, TsType};
 
+// Buff durations, in steps
+pub const WASTE_NOT_DURATION: u8 = 4;
+pub const WASTE_NOT_II_DURATION: u8 = 8;
+pub const VENERATION_DURATION: u8 = 4;
+pub const GREAT_STRIDES_DURATION: u8 = 3;
+pub const INNOVATION_DURATION: u8 = 4;
+pub const MUSCLE_MEMORY_DURATION: u8 = 5;
+pub const MANIPULATION_DURATION: u8 = 8;
+
+/// The most Inner Quiet stacks there can be
+pub const MAX_INNER_QUIET: u8 = 10;
+
+/// How many times a specialist can use Careful Observation in a craft
+pub const CAREFUL_OBSERVATION_USES: u8 = 3;
+
 pub struct Attributes {
     pub level: u32,
     pub progress_efficiency: Option<u32>,
     pub quality_efficiency: Option<u32>,
     pub durability_cost: Option<i8>,
     pub cp_cost: Option<u32>,
+    /// Actions that this action continues a combo from
+    pub combo_from: &'static [Action],
+    /// CP cost when continuing a combo, if it differs from `cp_cost`
+    pub combo_cp_cost: Option<u32>,
     pub effect: Option<fn(&mut CraftState)>,
+    /// Whether this action only applies a buff, which has a shorter animation
+    pub is_buff: bool,
 }
 
 macro_rules! optional {
@@ -22,6 +43,15 @@ macro_rules! optional {
     };
 }
 
+macro_rules! flag {
+    () => {
+        false
+    };
+    ($e:expr) => {
+        $e
+    };
+}
+
 macro_rules! create_actions {
     (
         $(
@@ -31,10 +61,15 @@ macro_rules! create_actions {
                 $(quality $quality:expr,)?
                 $(durability $durability:expr,)?
                 $(cp $cp:expr,)?
+                $(combo_from [$($combo_from:ident),+],)?
+                $(combo_cp $combo_cp:expr,)?
                 $(effect $effect:expr,)?
+                $(buff $buff:expr,)?
         )+ $(,)?
     ) => {
-        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, EnumIndexing, TsType)]
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIndexing, TsType,
+        )]
         pub enum Action {
             $($action_name,)*
         }
@@ -44,6 +79,11 @@ macro_rules! create_actions {
                 $(Action::$action_name,)*
             ];
 
+            /// Actions that another action can continue a combo from
+            pub const COMBO_STARTERS: &'static [Action] = &[
+                $($($(Action::$combo_from,)+)?)*
+            ];
+
             pub fn attributes(&self) -> Attributes {
                 match *self {
                     $(
@@ -53,7 +93,10 @@ macro_rules! create_actions {
                             quality_efficiency: optional!($( $quality )?),
                             durability_cost: optional!($( $durability )?),
                             cp_cost: optional!($( $cp )?),
+                            combo_from: &[$($(Action::$combo_from,)+)?],
+                            combo_cp_cost: optional!($( $combo_cp )?),
                             effect: optional!($( $effect )?),
+                            is_buff: flag!($( $buff )?),
                         },
                     )*
                 }
@@ -101,7 +144,7 @@ create_actions!(
         cp 18,
     [MastersMend, "Master's Mend"]
         level 7,
-        durability 0,  // indicates that this move is not a buff
+        durability 0,
         cp 88,
         effect |state| {
             state.durability = cmp::min(state.durability + 30, state.context.durability_max);
@@ -110,39 +153,47 @@ create_actions!(
     // RapidSynthesis
     [Observe, "Observe"]
         level 13,
-        durability 0,  // indicates that this move is not a buff
+        durability 0,
         cp 7,
     // TricksOfTheTrade
     [WasteNot, "Waste Not"]
         level 15,
         cp 56,
         effect |state| {
-            state.buffs.waste_not = 4;
+            state.buffs.waste_not = WASTE_NOT_DURATION;
             state.buffs.waste_not_ii = 0;
         },
+        buff true,
     [Veneration, "Veneration"]
         level 15,
         cp 18,
+        // recasting refreshes the remaining duration instead of stacking
         effect |state| {
-            state.buffs.veneration = 4;
+            state.buffs.veneration = VENERATION_DURATION;
         },
+        buff true,
     [StandardTouch, "Standard Touch"]
         level 18,
         quality 125,
         durability 10,
         cp 32,
+        combo_from [BasicTouch],
+        combo_cp 18,
     [GreatStrides, "Great Strides"]
         level 21,
         cp 32,
         effect |state| {
-            state.buffs.great_strides = 3;
+            state.buffs.great_strides = GREAT_STRIDES_DURATION;
         },
+        buff true,
     [Innovation, "Innovation"]
         level 26,
         cp 18,
+        // recasting refreshes the remaining duration instead of stacking
         effect |state| {
-            state.buffs.innovation = 4;
+            state.buffs.innovation = INNOVATION_DURATION;
         },
+        buff true,
     [BasicSynthesisTraited, "Basic Synthesis"]
         level 31,
         progress 120,
@@ -153,8 +204,9 @@ create_actions!(
         cp 98,
         effect |state| {
             state.buffs.waste_not = 0;
-            state.buffs.waste_not_ii = 8;
+            state.buffs.waste_not_ii = WASTE_NOT_II_DURATION;
         },
+        buff true,
     [ByregotsBlessing, "Byregot's Blessing"]
         level 50,
         quality 0,  // a placeholder to indicate this action *does* affect quality
@@ -167,8 +219,12 @@ create_actions!(
         durability 10,
         cp 6,
         effect |state| {
-            state.buffs.muscle_memory = 5;
+            state.buffs.muscle_memory = MUSCLE_MEMORY_DURATION;
         },
+    // Careful Observation doesn't take a step, see `CraftState::_execute_in_place`
+    [CarefulObservation, "Careful Observation"]
+        level 55,
+        durability 0,
     [CarefulSynthesis, "Careful Synthesis"]
         level 62,
         progress 150,
@@ -178,8 +234,9 @@ create_actions!(
         level 65,
         cp 96,
         effect |state| {
-            state.buffs.manipulation = 8;
+            state.buffs.manipulation = MANIPULATION_DURATION;
         },
+        buff true,
     [PrudentTouch, "Prudent Touch"]
         level 66,
         quality 100,
@@ -190,6 +247,8 @@ create_actions!(
         quality 150,
         durability 10,
         cp 46,
+        combo_from [StandardTouch, Observe],
+        combo_cp 18,
     [Reflect, "Reflect"]
         level 69,
         quality 300,
@@ -241,6 +300,7 @@ create_actions!(
         quality 100,
         durability 10,
         cp 24,
+        combo_from [BasicTouch],
     [DelicateSynthesisTraited, "Delicate Synthesis"]
         level 94,
         progress 150,
@@ -250,24 +310,26 @@ create_actions!(
     // DaringTouch
     [QuickInnovation, "Quick Innovation"]
         level 96,
-        durability 0,  // indicates that this move is not a buff
+        durability 0,
         effect |state| {
             state.buffs.innovation = 1;
             state.quick_innovation_available = false;
         },
+        buff true,
     [ImmaculateMend, "Immaculate Mend"]
         level 98,
-        durability 0,  // indicates that this move is not a buff
+        durability 0,
         cp 112,
         effect |state| {
             state.durability = state.context.durability_max;
         },
     [TrainedPerfection, "Trained Perfection"]
         level 100,
-        durability 0,  // indicates that this move is not a buff
+        durability 0,
         effect |state| {
             state.trained_perfection_active = Some(true);
         },
+        buff true,
 
 );
 
@@ -276,7 +338,12 @@ impl Action {
     #[allow(clippy::cast_sign_loss)]
     #[allow(clippy::cast_precision_loss)]
     pub fn calc_progress_increase(state: &CraftState, efficiency: u32) -> u32 {
-        let base = state.context.base_progress_factor as u64;
+        // conditions scale the base value, before any buffs are applied
+        let condition_multiplier: u64 = match state.condition {
+            Condition::Malleable => 150,
+            _ => 100,
+        };
+        let base = u64::from(state.context.base_progress_factor) * condition_multiplier / 100;
 
         let mut multiplier: u64 = 100;
         if state.buffs.veneration > 0 {
@@ -286,7 +353,8 @@ impl Action {
             multiplier += 100;
         }
 
-        (base * efficiency as u64 * multiplier / (100 * 100)) as u32
+        let increase = base * u64::from(efficiency) * multiplier / (100 * 100);
+        u32::try_from(increase).unwrap_or(u32::MAX)
     }
 
     #[allow(clippy::cast_possible_truncation)]
@@ -294,15 +362,22 @@ impl Action {
     #[allow(clippy::cast_precision_loss)]
     pub fn calc_quality_increase(state: &CraftState, efficiency: u32) -> u32 {
         if state.action == Some(Action::TrainedEye) {
-            return state.context.quality_target - state.quality;
+            return state.context.quality_target.saturating_sub(state.quality);
         }
 
-        let base = state.context.base_quality_factor as u64;
+        // conditions scale the base value, before any buffs are applied
+        let condition_multiplier: u64 = match state.condition {
+            Condition::Good => 150,
+            Condition::Excellent => 400,
+            Condition::Poor => 50,
+            _ => 100,
+        };
+        let base = u64::from(state.context.base_quality_factor) * condition_multiplier / 100;
 
         let efficiency = if state.action == Some(Action::ByregotsBlessing) {
             100 + u64::from(state.buffs.inner_quiet) * 20
         } else {
-            efficiency as u64
+            u64::from(efficiency)
         };
 
         let iq_multiplier = 100 + u64::from(state.buffs.inner_quiet) * 10;
@@ -315,42 +390,307 @@ impl Action {
             multiplier += 100;
         }
 
-        (base * efficiency * iq_multiplier * multiplier / (100 * 100 * 100)) as u32
+        let increase = base * efficiency * iq_multiplier * multiplier / (100 * 100 * 100);
+        u32::try_from(increase).unwrap_or(u32::MAX)
     }
 
     pub fn calc_durability_cost(state: &CraftState, base_cost: i8) -> i8 {
         if state.trained_perfection_active == Some(true) {
             return 0;
         }
-        if state.buffs.waste_not > 0 || state.buffs.waste_not_ii > 0 {
+        if state.buffs.waste_not_remaining() > 0 {
             return base_cost / 2;
         }
         base_cost
     }
 
-    pub fn calc_cp_cost(state: &CraftState, base_cost: u32) -> u32 {
+    /// Whether using this action after `previous_combo_action` continues a combo
+    pub fn continues_combo(self, previous_combo_action: Option<Action>) -> bool {
+        previous_combo_action
+            .is_some_and(|previous| self.attributes().combo_from.contains(&previous))
+    }
+
+    /// Whether this action can be followed up by a combo when it's used after
+    /// `previous_combo_action`. It has to start a combo, and if it's only part
+    /// of one, like Standard Touch, it has to continue that combo too.
+    pub fn can_be_followed_up(self, previous_combo_action: Option<Action>) -> bool {
+        Action::COMBO_STARTERS.contains(&self)
+            && (self.attributes().combo_from.is_empty()
+                || self.continues_combo(previous_combo_action))
+    }
+
+    /// Actions that continue a combo from this one, e.g. Standard Touch and
+    /// Refined Touch after Basic Touch
+    pub fn combo_follow_ups(self) -> Vec<Action> {
+        Action::ACTIONS
+            .iter()
+            .copied()
+            .filter(|action| action.continues_combo(Some(self)))
+            .collect()
+    }
+
+    /// The buffs this action uses up and the ones it applies, for describing
+    /// it without simulating. See `BuffEffects`.
+    pub fn buff_effects(self) -> BuffEffects {
         use Action::*;
 
-        match (state.previous_combo_action, state.action) {
-            (Some(BasicTouch), Some(StandardTouch))
-            | (Some(StandardTouch) | Some(Observe), Some(AdvancedTouch)) => 18,
+        let attributes = self.attributes();
+        let mut consumed = vec![];
+        let mut granted = vec![];
+
+        if attributes.progress_efficiency.is_some() {
+            consumed.push(BuffKind::MuscleMemory);
+        }
+        if attributes.quality_efficiency.is_some() {
+            consumed.push(BuffKind::GreatStrides);
+            match self {
+                ByregotsBlessing => consumed.insert(0, BuffKind::InnerQuiet),
+                TrainedEye => {}
+                Reflect | PreparatoryTouch => granted.push((BuffKind::InnerQuiet, 2)),
+                _ => granted.push((BuffKind::InnerQuiet, 1)),
+            }
+        }
+
+        granted.extend(match self {
+            WasteNot => Some((BuffKind::WasteNot, WASTE_NOT_DURATION)),
+            WasteNotII => Some((BuffKind::WasteNot, WASTE_NOT_II_DURATION)),
+            Veneration => Some((BuffKind::Veneration, VENERATION_DURATION)),
+            GreatStrides => Some((BuffKind::GreatStrides, GREAT_STRIDES_DURATION)),
+            Innovation => Some((BuffKind::Innovation, INNOVATION_DURATION)),
+            QuickInnovation => Some((BuffKind::Innovation, 1)),
+            MuscleMemory => Some((BuffKind::MuscleMemory, MUSCLE_MEMORY_DURATION)),
+            Manipulation => Some((BuffKind::Manipulation, MANIPULATION_DURATION)),
+            _ => None,
+        });
+
+        BuffEffects { consumed, granted }
+    }
+
+    /// The CP cost of using `action` from `state`, taking combos into account
+    pub fn calc_cp_cost(state: &CraftState, action: Action, base_cost: u32) -> u32 {
+        match action.attributes().combo_cp_cost {
+            Some(combo_cost) if action.continues_combo(state.previous_combo_action) => combo_cost,
             _ => base_cost,
         }
     }
 
     pub fn macro_text(&self) -> String {
-        let mut label = self.label().to_string();
-        if label.contains(' ') {
-            label = format!("\"{label}\"");
+        Self::format_macro_line(self.label(), Some(self.macro_wait_time()))
+    }
+
+    /// The macro line for this action in the client's `language`, optionally
+    /// with a trailing `<wait>`. None if the action's name in `language`
+    /// isn't known, see `label_for`.
+    pub fn macro_line(&self, language: Language, wait: bool) -> Option<String> {
+        let label = self.label_for(language)?;
+        Some(Self::format_macro_line(
+            label,
+            wait.then(|| self.macro_wait_time()),
+        ))
+    }
+
+    fn format_macro_line(label: &str, wait: Option<u8>) -> String {
+        let label = if label.contains(' ') {
+            format!("\"{label}\"")
+        } else {
+            label.to_string()
+        };
+
+        match wait {
+            Some(wait) => format!("/ac {label} <wait.{wait}>"),
+            None => format!("/ac {label}"),
         }
+    }
+
+    /// Whether this action only applies a buff, which has a shorter animation
+    pub fn is_buff(&self) -> bool {
+        self.attributes().is_buff
+    }
+
+    /// Seconds to wait after using this action in a macro
+    pub fn macro_wait_time(&self) -> u8 {
+        if self.is_buff() {
+            2
+        } else {
+            3
+        }
+    }
+}
+
+/// A broad grouping of actions by what they're used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub enum ActionCategory {
+    /// Increases progress, and possibly quality
+    Synthesis,
+    /// Increases quality only
+    Touch,
+    /// Restores durability
+    Repair,
+    /// Applies an effect to later steps
+    Buff,
+    Other,
+}
+
+/// How an action changes buffs, from `Action::buff_effects`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TsType)]
+pub struct BuffEffects {
+    /// Buffs that are removed when the action is used
+    pub consumed: Vec<BuffKind>,
+    /// Buffs that are applied, paired with their duration. Inner Quiet is
+    /// paired with the stacks gained instead, which doesn't include the extra
+    /// stack from Refined Touch continuing a combo. Inner Quiet isn't gained
+    /// below level 11.
+    pub granted: Vec<(BuffKind, u8)>,
+}
+
+/// Static information about an action, for displaying it
+#[derive(Debug, Clone, Serialize, TsType)]
+pub struct ActionMetadata {
+    pub action: Action,
+    pub game_id: u32,
+    pub label: &'static str,
+    pub category: ActionCategory,
+    pub level: u32,
+    /// Actions that continue a combo from this one
+    pub combo_follow_ups: Vec<Action>,
+    pub buff_effects: BuffEffects,
+}
+
+impl Action {
+    /// The in-game action id, as used by Carpenter. Each crafting job has its
+    /// own id for an action, but they share icons.
+    pub fn game_id(&self) -> u32 {
+        use Action::*;
+
+        match self {
+            BasicSynthesis | BasicSynthesisTraited => 100_001,
+            BasicTouch => 100_002,
+            MastersMend => 100_003,
+            Observe => 100_010,
+            WasteNot => 4631,
+            Veneration => 19297,
+            StandardTouch => 100_004,
+            GreatStrides => 260,
+            Innovation => 19004,
+            WasteNotII => 4639,
+            ByregotsBlessing => 100_339,
+            MuscleMemory => 100_379,
+            CarefulObservation => 100_395,
+            CarefulSynthesis | CarefulSynthesisTraited => 100_203,
+            Manipulation => 4574,
+            PrudentTouch => 100_227,
+            AdvancedTouch => 100_411,
+            Reflect => 100_387,
+            PreparatoryTouch => 100_299,
+            Groundwork | GroundworkTraited => 100_403,
+            DelicateSynthesis | DelicateSynthesisTraited => 100_323,
+            TrainedEye => 100_283,
+            PrudentSynthesis => 100_427,
+            TrainedFinesse => 100_435,
+            RefinedTouch => 100_443,
+            QuickInnovation => 100_459,
+            ImmaculateMend => 100_467,
+            TrainedPerfection => 100_475,
+        }
+    }
+
+    pub fn category(&self) -> ActionCategory {
+        use Action::*;
 
         let attrs = self.attributes();
-        let is_buff = attrs.progress_efficiency.is_none()
-            && attrs.quality_efficiency.is_none()
-            && attrs.durability_cost.is_none();
-        let wait_time = if is_buff { 2 } else { 3 };
+        match self {
+            MastersMend | ImmaculateMend => ActionCategory::Repair,
+            Observe | CarefulObservation | TrainedPerfection => ActionCategory::Other,
+            QuickInnovation => ActionCategory::Buff,
+            _ if attrs.progress_efficiency.is_some() => ActionCategory::Synthesis,
+            _ if attrs.quality_efficiency.is_some() => ActionCategory::Touch,
+            _ => ActionCategory::Buff,
+        }
+    }
+
+    pub fn metadata(&self) -> ActionMetadata {
+        ActionMetadata {
+            action: *self,
+            game_id: self.game_id(),
+            label: self.label(),
+            category: self.category(),
+            level: self.attributes().level,
+            combo_follow_ups: self.combo_follow_ups(),
+            buff_effects: self.buff_effects(),
+        }
+    }
+}
+
+/// A game client language, which determines the action names that macros need
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TsType)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Japanese,
+}
+
+impl Action {
+    /// The action's name as shown in a client using `language`. None if the
+    /// name isn't known: a macro with the wrong name fails in game, so it's
+    /// better to not have one than to fall back to the English name.
+    pub fn label_for(&self, language: Language) -> Option<&'static str> {
+        use Action::*;
+
+        // German and French names for Careful Observation and the level 94+
+        // actions couldn't be checked against the game's data, so they're
+        // left out rather than guessed
+        let unverified = |japanese| match language {
+            Language::English => Some(self.label()),
+            Language::Japanese => Some(japanese),
+            Language::German | Language::French => None,
+        };
+        let (german, french, japanese) = match self {
+            BasicSynthesis | BasicSynthesisTraited => ("Bearbeiten", "Ouvrage", "作業"),
+            BasicTouch => ("Veredelung", "Retouche", "加工"),
+            MastersMend => (
+                "Wiederherstellung",
+                "Réparation de maître",
+                "マスターズメンド",
+            ),
+            Observe => ("Beobachten", "Observation", "経過観察"),
+            WasteNot => ("Nachhaltigkeit", "Parcimonie", "倹約"),
+            Veneration => ("Ehrfurcht", "Vénération", "ヴェネレーション"),
+            StandardTouch => ("Solide Veredelung", "Retouche standard", "中級加工"),
+            GreatStrides => ("Große Schritte", "Grands progrès", "グレートストライド"),
+            Innovation => ("Innovation", "Innovation", "イノベーション"),
+            WasteNotII => ("Nachhaltigkeit II", "Parcimonie pérenne", "長期倹約"),
+            ByregotsBlessing => ("Byregots Segen", "Bénédiction de Byregot", "ビエルゴの祝福"),
+            MuscleMemory => ("Motorisches Gedächtnis", "Mémoire musculaire", "確信"),
+            CarefulObservation => return unverified("設計変更"),
+            CarefulSynthesis | CarefulSynthesisTraited => {
+                ("Sorgfältige Bearbeitung", "Ouvrage prudent", "模範作業")
+            }
+            Manipulation => ("Manipulation", "Manipulation", "マニピュレーション"),
+            PrudentTouch => ("Sparsame Veredelung", "Retouche parcimonieuse", "倹約加工"),
+            AdvancedTouch => ("Höhere Veredelung", "Retouche avancée", "上級加工"),
+            Reflect => ("Einkehr", "Véritable valeur", "真価"),
+            PreparatoryTouch => ("Basisveredelung", "Retouche préparatoire", "下地加工"),
+            Groundwork | GroundworkTraited => ("Vorarbeit", "Travail préparatoire", "下地作業"),
+            DelicateSynthesis | DelicateSynthesisTraited => {
+                ("Präzise Bearbeitung", "Travail minutieux", "精密作業")
+            }
+            TrainedEye => ("Flinke Hand", "Main preste", "匠の早業"),
+            PrudentSynthesis => ("Rationelle Bearbeitung", "Ouvrage parcimonieux", "倹約作業"),
+            TrainedFinesse => ("Göttliche Hand", "Main divine", "匠の神業"),
+            RefinedTouch => ("Raffinierte Veredelung", "Retouche raffinée", "洗練加工"),
+            QuickInnovation => return unverified("クイックイノベーション"),
+            ImmaculateMend => return unverified("パーフェクトメンド"),
+            TrainedPerfection => return unverified("匠の絶技"),
+        };
 
-        format!("/ac {label} <wait.{wait_time}>")
+        Some(match language {
+            Language::English => self.label(),
+            Language::German => german,
+            Language::French => french,
+            Language::Japanese => japanese,
+        })
     }
 }
 
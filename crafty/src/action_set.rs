@@ -1,11 +1,19 @@
 use enum_indexing::EnumIndexing;
 use rand::{rngs::SmallRng, Rng};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use ts_type::wasm_bindgen;
 
 use crate::Action;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct ActionSet(u32);
 
+// serialized as a list of actions, see the `Serialize` impl below
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_ACTION_SET: &'static str = r#"
+export type ActionSet = Action[];
+"#;
+
 impl ActionSet {
     #[allow(clippy::cast_possible_truncation)]
     fn bit_from_action(action: Action) -> u32 {
@@ -42,8 +50,8 @@ impl ActionSet {
         instance
     }
 
-    pub fn contains(&self, action: Action) -> bool {
-        (self.0 & Self::bit_from_action(action)).count_ones() == 1
+    pub fn contains(self, action: Action) -> bool {
+        self.0 & Self::bit_from_action(action) != 0
     }
 
     /// Iterates through Actions in the set and keeps or removes them based on
@@ -69,7 +77,7 @@ impl ActionSet {
         }
     }
 
-    fn random_index(&self, rng: &mut SmallRng) -> usize {
+    fn random_index(self, rng: &mut SmallRng) -> usize {
         // inspired by https://stackoverflow.com/a/37460774
         let mut nth = rng.gen_range(0..self.len());
         let mut remaining_bits = self.0;
@@ -90,8 +98,55 @@ impl ActionSet {
         panic!("called `random` on empty ActionSet");
     }
 
+    /// Picks an index from the set proportionally to `weights`, which is
+    /// indexed by `Action::index`. Negative, NaN, and infinite weights count
+    /// as 0. Falls back to a uniform pick if none of the actions in the set
+    /// have a positive weight, or the weights are too large to add up.
+    fn weighted_random_index(self, weights: &[f32], rng: &mut SmallRng) -> usize {
+        let weight = |index: usize| {
+            weights
+                .get(index)
+                .copied()
+                .filter(|weight| weight.is_finite())
+                .unwrap_or(0.0)
+                .max(0.0)
+        };
+
+        let mut total = 0.0;
+        let mut remaining_bits = self.0;
+        while remaining_bits != 0 {
+            let index = remaining_bits.trailing_zeros() as usize;
+            total += weight(index);
+            remaining_bits &= remaining_bits - 1;
+        }
+
+        // an empty or infinite range would make `gen_range` panic
+        if total <= 0.0 || !total.is_finite() {
+            return self.random_index(rng);
+        }
+
+        let mut roll = rng.gen_range(0.0..total);
+        let mut last_index = None;
+        let mut remaining_bits = self.0;
+        while remaining_bits != 0 {
+            let index = remaining_bits.trailing_zeros() as usize;
+            let w = weight(index);
+            if w > 0.0 {
+                if roll < w {
+                    return index;
+                }
+                roll -= w;
+                last_index = Some(index);
+            }
+            remaining_bits &= remaining_bits - 1;
+        }
+
+        // floating point error can leave a sliver of the roll unaccounted for
+        last_index.unwrap()
+    }
+
     /// Returns a random Action from the set
-    pub fn sample(&self, rng: &mut SmallRng) -> Action {
+    pub fn sample(self, rng: &mut SmallRng) -> Action {
         let random_index = self.random_index(rng);
         Action::from_index(random_index).unwrap()
     }
@@ -103,18 +158,33 @@ impl ActionSet {
         Action::from_index(random_index).unwrap()
     }
 
-    pub fn len(&self) -> u32 {
+    /// Returns a random Action from the set, chosen proportionally to
+    /// `weights` (indexed by `Action::index`)
+    pub fn sample_weighted(self, weights: &[f32], rng: &mut SmallRng) -> Action {
+        let random_index = self.weighted_random_index(weights, rng);
+        Action::from_index(random_index).unwrap()
+    }
+
+    /// Removes and returns a random Action from the set, chosen proportionally
+    /// to `weights` (indexed by `Action::index`)
+    pub fn pick_weighted(&mut self, weights: &[f32], rng: &mut SmallRng) -> Action {
+        let random_index = self.weighted_random_index(weights, rng);
+        self.unset_bit(1u32 << random_index);
+        Action::from_index(random_index).unwrap()
+    }
+
+    pub fn len(self) -> u32 {
         self.0.count_ones()
     }
 
-    pub fn is_empty(&self) -> bool {
+    pub fn is_empty(self) -> bool {
         self.0 == 0
     }
 
-    pub fn to_vec(&self) -> Vec<Action> {
+    pub fn to_vec(self) -> Vec<Action> {
         let mut actions = vec![];
 
-        for action in Action::ACTIONS.iter() {
+        for action in Action::ACTIONS {
             if self.contains(*action) {
                 actions.push(*action);
             }
@@ -124,12 +194,93 @@ impl ActionSet {
     }
 }
 
+impl Serialize for ActionSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.to_vec())
+    }
+}
+
+impl<'de> Deserialize<'de> for ActionSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Action>::deserialize(deserializer).map(|actions| Self::from_vec(&actions))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rand::SeedableRng;
     use Action::*;
 
+    /// A random subset of every action, in `Action::ACTIONS` order
+    fn random_subset(rng: &mut SmallRng) -> Vec<Action> {
+        Action::ACTIONS
+            .iter()
+            .copied()
+            .filter(|_| rng.gen_bool(0.5))
+            .collect()
+    }
+
+    #[test]
+    fn every_action_fits_in_the_bitfield() {
+        assert!(Action::ACTIONS.len() <= u32::BITS as usize);
+        let set = ActionSet::from_vec(&Action::ACTIONS.to_vec());
+        assert_eq!(set.len() as usize, Action::ACTIONS.len());
+        assert_eq!(set.to_vec(), Action::ACTIONS);
+    }
+
+    #[test]
+    fn random_subsets_round_trip() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            let actions = random_subset(&mut rng);
+            let set = ActionSet::from_vec(&actions);
+
+            assert_eq!(set.to_vec(), actions);
+            assert_eq!(set.len() as usize, actions.len());
+            assert_eq!(set.is_empty(), actions.is_empty());
+            for action in Action::ACTIONS {
+                assert_eq!(set.contains(*action), actions.contains(action));
+            }
+        }
+    }
+
+    #[test]
+    fn random_keeps_match_retain() {
+        let mut rng = SmallRng::seed_from_u64(2);
+        for _ in 0..1000 {
+            let mut actions = random_subset(&mut rng);
+            let kept = random_subset(&mut rng);
+            let mut set = ActionSet::from_vec(&actions);
+
+            set.keep(|action| kept.contains(action));
+            actions.retain(|action| kept.contains(action));
+            assert_eq!(set.to_vec(), actions);
+        }
+    }
+
+    #[test]
+    fn pick_removes_exactly_the_returned_action() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        for _ in 0..1000 {
+            let mut actions = random_subset(&mut rng);
+            let mut set = ActionSet::from_vec(&actions);
+
+            while !set.is_empty() {
+                let sampled = set.sample(&mut rng);
+                assert!(actions.contains(&sampled));
+                assert_eq!(set.to_vec(), actions);
+
+                let picked = set.pick(&mut rng);
+                assert!(actions.contains(&picked));
+                actions.retain(|&action| action != picked);
+                assert_eq!(set.to_vec(), actions);
+                assert_eq!(set.len() as usize, actions.len());
+            }
+            assert!(actions.is_empty());
+        }
+    }
+
     #[test]
     fn set_and_unset_works() {
         let mut set = ActionSet::new();
@@ -186,4 +337,72 @@ mod tests {
         assert!(counts[GreatStrides.index()] > 0);
         assert!(counts[TrainedFinesse.index()] > 0);
     }
+
+    #[test]
+    fn weighted_random_index_works() {
+        let mut set = ActionSet::new();
+        set.set(BasicTouch);
+        set.set(BasicSynthesis);
+        set.set(GreatStrides);
+
+        let mut weights = vec![0.0; Action::ACTIONS.len()];
+        weights[BasicTouch.index()] = 3.0;
+        weights[BasicSynthesis.index()] = 1.0;
+        // not in the set, so it should never be chosen
+        weights[MuscleMemory.index()] = 100.0;
+
+        let mut counts = vec![0; Action::ACTIONS.len()];
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            counts[set.weighted_random_index(&weights, &mut rng)] += 1;
+        }
+
+        assert_eq!(counts[GreatStrides.index()], 0);
+        assert_eq!(counts[MuscleMemory.index()], 0);
+        assert!(counts[BasicTouch.index()] > 2 * counts[BasicSynthesis.index()]);
+        assert!(counts[BasicSynthesis.index()] > 0);
+    }
+
+    #[test]
+    fn weighted_random_index_ignores_invalid_weights() {
+        let mut set = ActionSet::new();
+        set.set(BasicTouch);
+        set.set(BasicSynthesis);
+        set.set(GreatStrides);
+
+        let mut weights = vec![0.0; Action::ACTIONS.len()];
+        weights[BasicTouch.index()] = f32::NAN;
+        weights[BasicSynthesis.index()] = f32::INFINITY;
+        weights[GreatStrides.index()] = 1.0;
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..100 {
+            assert_eq!(set.sample_weighted(&weights, &mut rng), GreatStrides);
+        }
+
+        // weights that overflow when added up fall back to a uniform pick
+        weights[BasicTouch.index()] = f32::MAX;
+        weights[BasicSynthesis.index()] = f32::MAX;
+        let mut counts = vec![0; Action::ACTIONS.len()];
+        for _ in 0..300 {
+            counts[set.weighted_random_index(&weights, &mut rng)] += 1;
+        }
+        assert!(counts[GreatStrides.index()] > 0);
+
+        let first = set.pick_weighted(&[f32::NEG_INFINITY; 64], &mut rng);
+        assert!(!set.contains(first));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn pick_weighted_falls_back_to_uniform() {
+        let mut set = ActionSet::new();
+        set.set(BasicTouch);
+        set.set(BasicSynthesis);
+
+        let mut rng = SmallRng::seed_from_u64(1);
+        let first = set.pick_weighted(&[], &mut rng);
+        let second = set.pick_weighted(&[], &mut rng);
+        assert_ne!(first, second);
+        assert!(set.is_empty());
+    }
 }
@@ -0,0 +1,193 @@
+use rand::{rngs::SmallRng, Rng};
+use serde::{Deserialize, Serialize};
+use ts_type::{wasm_bindgen, TsType};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TsType)]
+pub enum Condition {
+    #[default]
+    Normal,
+    Good,
+    Excellent,
+    Poor,
+    Centered,
+    Sturdy,
+    Pliant,
+    Malleable,
+    Primed,
+    GoodOmen,
+}
+
+impl Condition {
+    pub const CONDITIONS: &'static [Condition] = &[
+        Condition::Normal,
+        Condition::Good,
+        Condition::Excellent,
+        Condition::Poor,
+        Condition::Centered,
+        Condition::Sturdy,
+        Condition::Pliant,
+        Condition::Malleable,
+        Condition::Primed,
+        Condition::GoodOmen,
+    ];
+
+    /// The bit used to indicate this condition in a recipe's `conditions_flag`
+    fn flag_bit(self) -> u32 {
+        match self {
+            Condition::Normal => 1,
+            Condition::Good => 1 << 1,
+            Condition::Excellent => 1 << 2,
+            Condition::Poor => 1 << 3,
+            Condition::Centered => 1 << 4,
+            Condition::Sturdy => 1 << 5,
+            Condition::Pliant => 1 << 6,
+            Condition::Malleable => 1 << 7,
+            Condition::Primed => 1 << 8,
+            Condition::GoodOmen => 1 << 9,
+        }
+    }
+
+    /// Expert recipes are the only ones with conditions beyond the first four bits
+    fn is_expert_flag(conditions_flag: u32) -> bool {
+        conditions_flag & !0b1111 != 0
+    }
+
+    /// The chance of this condition being rolled on any given step.
+    fn chance(self, conditions_flag: u32) -> f32 {
+        if conditions_flag & self.flag_bit() == 0 {
+            return 0.0;
+        }
+
+        let is_expert = Self::is_expert_flag(conditions_flag);
+        match self {
+            // Normal covers whatever chance remains
+            Condition::Normal => {
+                1.0 - Self::CONDITIONS[1..]
+                    .iter()
+                    .map(|c| c.chance(conditions_flag))
+                    .sum::<f32>()
+            }
+            Condition::Good if is_expert => 0.12,
+            Condition::Good => 0.25,
+            Condition::Excellent => 0.04,
+            // Poor is never rolled, it only follows Excellent
+            Condition::Poor => 0.0,
+            Condition::Centered | Condition::Sturdy => 0.15,
+            Condition::Pliant | Condition::Malleable | Condition::Primed | Condition::GoodOmen => {
+                0.12
+            }
+        }
+    }
+
+    /// Decodes a recipe's `conditions_flag` into the conditions that may occur
+    /// during the craft, paired with the chance of each being rolled on a step.
+    pub fn probabilities(conditions_flag: u32) -> Vec<(Condition, f32)> {
+        Self::CONDITIONS
+            .iter()
+            .filter(|c| conditions_flag & c.flag_bit() != 0)
+            .map(|&c| (c, c.chance(conditions_flag)))
+            .collect()
+    }
+
+    /// Whether any condition other than Normal can be rolled
+    pub fn is_variable(conditions_flag: u32) -> bool {
+        Self::CONDITIONS[1..]
+            .iter()
+            .any(|c| c.chance(conditions_flag) > 0.0)
+    }
+
+    /// Rolls the condition for the next step of a craft. Doesn't consume any
+    /// randomness if the recipe's conditions can't vary.
+    pub fn roll(conditions_flag: u32, rng: &mut SmallRng) -> Condition {
+        if !Self::is_variable(conditions_flag) {
+            return Condition::Normal;
+        }
+
+        let mut roll: f32 = rng.gen();
+        for &condition in &Self::CONDITIONS[1..] {
+            let chance = condition.chance(conditions_flag);
+            if roll < chance {
+                return condition;
+            }
+            roll -= chance;
+        }
+        Condition::Normal
+    }
+
+    /// Rolls the condition for the step after one with this condition. The
+    /// game always follows Excellent with Poor, whatever the recipe's chances
+    /// are, and that doesn't consume any randomness either.
+    pub fn roll_after(self, conditions_flag: u32, rng: &mut SmallRng) -> Condition {
+        if self == Condition::Excellent {
+            return Condition::Poor;
+        }
+        Self::roll(conditions_flag, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use Condition::*;
+
+    fn assert_probabilities(conditions_flag: u32, expected: &[(Condition, f32)]) {
+        let probabilities = Condition::probabilities(conditions_flag);
+        assert_eq!(probabilities.len(), expected.len());
+        for ((condition, chance), (expected_condition, expected_chance)) in
+            probabilities.iter().zip(expected)
+        {
+            assert_eq!(condition, expected_condition);
+            assert!((chance - expected_chance).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn decodes_standard_flag() {
+        assert_probabilities(
+            15,
+            &[(Normal, 0.71), (Good, 0.25), (Excellent, 0.04), (Poor, 0.0)],
+        );
+        assert!(Condition::is_variable(15));
+    }
+
+    #[test]
+    fn decodes_expert_flag() {
+        assert_probabilities(
+            115,
+            &[
+                (Normal, 0.46),
+                (Good, 0.12),
+                (Centered, 0.15),
+                (Sturdy, 0.15),
+                (Pliant, 0.12),
+            ],
+        );
+        assert_probabilities(
+            483,
+            &[
+                (Normal, 0.37),
+                (Good, 0.12),
+                (Sturdy, 0.15),
+                (Pliant, 0.12),
+                (Malleable, 0.12),
+                (Primed, 0.12),
+            ],
+        );
+    }
+
+    #[test]
+    fn excellent_is_followed_by_poor() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(Excellent.roll_after(15, &mut rng), Poor);
+        assert_eq!(Excellent.roll_after(1, &mut rng), Poor);
+        // normal rolls resume after Poor
+        assert!((0..100).all(|_| Poor.roll_after(15, &mut rng) != Poor));
+    }
+
+    #[test]
+    fn normal_only_flag_doesnt_vary() {
+        assert!(!Condition::is_variable(1));
+        assert_probabilities(1, &[(Normal, 1.0)]);
+    }
+}
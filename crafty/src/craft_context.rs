@@ -1,11 +1,96 @@
 use crate::{Action, ActionSet, Player, Recipe};
-use serde::Deserialize;
+use recipe::Fingerprinter;
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::RangeInclusive};
 use ts_type::{wasm_bindgen, TsType};
 
+/// The values of `CraftOptions::max_steps` that a craft can be created with
+pub const MAX_STEPS_RANGE: RangeInclusive<u8> = 5..=50;
+
+/// How many levels above a recipe's job level the player has to be to use
+/// Trained Eye on it
+const TRAINED_EYE_LEVEL_DIFFERENCE: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CraftContextError {
+    /// `max_steps` is outside of `MAX_STEPS_RANGE`
+    MaxStepsOutOfRange(u8),
+    /// `collectability_tiers` aren't in ascending order
+    CollectabilityTiersUnordered,
+    /// `target_tier` isn't an index into `collectability_tiers`
+    TargetTierOutOfRange(u8),
+    /// `quality_weight` is outside of 0 to 1
+    QualityWeightOutOfRange,
+    /// `finishing_quality_percent` is over 100
+    FinishingQualityOutOfRange(u8),
+    /// The player's craftsmanship is below the recipe's requirement, which is
+    /// included
+    CraftsmanshipTooLow(u32),
+    /// The player's control is below the recipe's requirement, which is
+    /// included
+    ControlTooLow(u32),
+}
+
+impl fmt::Display for CraftContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CraftContextError::MaxStepsOutOfRange(max_steps) => write!(
+                f,
+                "max steps must be between {} and {}, got {max_steps}",
+                MAX_STEPS_RANGE.start(),
+                MAX_STEPS_RANGE.end()
+            ),
+            CraftContextError::CollectabilityTiersUnordered => {
+                write!(f, "collectability tiers must be in ascending order")
+            }
+            CraftContextError::TargetTierOutOfRange(tier) => {
+                write!(f, "target tier must be 0, 1, or 2, got {tier}")
+            }
+            CraftContextError::QualityWeightOutOfRange => {
+                write!(f, "quality weight must be between 0 and 1")
+            }
+            CraftContextError::FinishingQualityOutOfRange(percent) => {
+                write!(
+                    f,
+                    "finishing quality percent must be at most 100, got {percent}"
+                )
+            }
+            CraftContextError::CraftsmanshipTooLow(required) => {
+                write!(f, "craftsmanship too low, need {required}")
+            }
+            CraftContextError::ControlTooLow(required) => {
+                write!(f, "control too low, need {required}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CraftContextError {}
+
+/// The player's stats as they apply to a craft. These are the values that
+/// progress and quality are calculated from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub struct EffectiveStats {
+    pub craftsmanship: u32,
+    pub control: u32,
+    pub cp: u32,
+}
+
+impl EffectiveStats {
+    pub fn new(player: &Player) -> Self {
+        Self {
+            craftsmanship: player.craftsmanship,
+            control: player.control,
+            cp: player.cp,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CraftContext {
     pub player_job_level: u32,
     pub recipe_job_level: u32,
+    pub effective_stats: EffectiveStats,
     /// Multiply by synthesis action efficiency for increase in progress
     pub base_progress_factor: u32,
     /// Multiply by touch action efficiency for increase in quality
@@ -14,16 +99,31 @@ pub struct CraftContext {
     pub progress_target: u32,
     pub starting_quality: u32,
     pub quality_target: u32,
+    /// Whether crafts are scored on quality. If not, finishing in fewer
+    /// steps is all that matters.
+    pub quality_relevant: bool,
     pub durability_max: i8,
     pub cp_max: u32,
     pub is_expert: bool,
+    /// Bitfield of the conditions that can occur during the craft
+    pub conditions_flag: u32,
     pub action_pool: ActionSet,
     pub player_is_specialist: bool,
     pub use_manipulation: bool,
     pub use_delineation: bool,
+    pub condition_seed: Option<u64>,
+    pub collectability_tiers: Option<[u32; 3]>,
+    pub skip_trained_eye_opener: bool,
+    pub allow_quality_under_muscle_memory: bool,
+    pub quality_weight: Option<f32>,
+    /// Strict search doesn't finish the craft while quality is below this
+    pub finishing_quality_floor: u32,
 }
 
-#[derive(Debug, Clone, Copy, Default, Deserialize, TsType)]
+/// Serialized field names are kept stable so that saved options can be
+/// loaded again. Missing fields take their default values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, TsType)]
+#[serde(default)]
 pub struct CraftOptions {
     pub max_steps: u8,
     pub starting_quality: Option<u32>,
@@ -31,18 +131,151 @@ pub struct CraftOptions {
     pub player_is_specialist: bool,
     pub use_manipulation: bool,
     pub use_delineation: bool,
+    /// Seed used to roll a reproducible sequence of conditions when simulating.
+    /// Every step is Normal if None.
+    pub condition_seed: Option<u64>,
+    /// Minimum collectability of each reward tier of a collectable, lowest
+    /// first. If `quality_target` is None, it defaults to the `target_tier`.
+    /// Like any quality target, quality past it isn't rewarded.
+    pub collectability_tiers: Option<[u32; 3]>,
+    /// The index into `collectability_tiers` of the tier to aim for, e.g. 1
+    /// for the middle tier when the top one isn't worth the CP. Defaults to
+    /// the highest tier if None.
+    pub target_tier: Option<u8>,
+    /// Strict search always opens with Trained Eye when it's available. Set
+    /// this to also explore openers without it.
+    pub skip_trained_eye_opener: bool,
+    /// Strict search doesn't use quality actions under Muscle Memory when the
+    /// recipe is at the player's level, since progress is usually worth more
+    /// there. Set this to also explore them, e.g. Delicate Synthesis.
+    pub allow_quality_under_muscle_memory: bool,
+    /// Actions to leave out of the action pool, e.g. ones the player hasn't
+    /// unlocked. Traited versions of an action, like `GroundworkTraited`, are
+    /// separate actions and have to be excluded separately.
+    pub excluded_actions: ActionSet,
+    /// Trades speed for quality when scoring a craft, from 0 to 1. At 0 only
+    /// finishing in fewer steps is rewarded, and quality not at all; at 1 it's
+    /// the other way around. The bonuses move linearly in between. Uses the
+    /// default weights, which strongly favor quality, if None.
+    pub quality_weight: Option<f32>,
+    /// Strict search doesn't finish the craft while quality is below this
+    /// percent of the quality target, so that it doesn't settle for rotations
+    /// that skip quality. Defaults to 20 if None. Set it to 0 to let speed or
+    /// low quality crafts finish at any time.
+    pub finishing_quality_percent: Option<u8>,
+    /// Uses exactly these actions instead of the ones the player's level and
+    /// CP allow, e.g. to try out actions from a future patch. Traited versions
+    /// aren't swapped in, but `excluded_actions` still applies.
+    pub action_pool_override: Option<ActionSet>,
+}
+
+impl CraftOptions {
+    /// A hash of the options that stays the same across builds, like
+    /// `Recipe::fingerprint`. Together with the recipe's and player's, it
+    /// identifies a craft, e.g. for caching search results. Excluded actions
+    /// are hashed by name, in any order.
+    pub fn fingerprint(&self) -> u64 {
+        let sorted_names = |actions: ActionSet| {
+            let mut names: Vec<&str> = actions.to_vec().iter().map(Action::name).collect();
+            names.sort_unstable();
+            names.dedup();
+            names
+        };
+        let excluded_actions = sorted_names(self.excluded_actions);
+
+        let mut fingerprinter = Fingerprinter::new();
+        fingerprinter
+            .write_u64(self.max_steps.into())
+            .write_option_u64(self.starting_quality.map(u64::from))
+            .write_option_u64(self.quality_target.map(u64::from))
+            .write_bool(self.player_is_specialist)
+            .write_bool(self.use_manipulation)
+            .write_bool(self.use_delineation)
+            .write_option_u64(self.condition_seed)
+            .write_bool(self.collectability_tiers.is_some());
+        for tier in self.collectability_tiers.unwrap_or_default() {
+            fingerprinter.write_u64(tier.into());
+        }
+        fingerprinter
+            .write_bool(self.skip_trained_eye_opener)
+            .write_u64(excluded_actions.len() as u64);
+        for name in excluded_actions {
+            fingerprinter.write_str(name);
+        }
+        fingerprinter.write_option_u64(self.quality_weight.map(|weight| weight.to_bits().into()));
+
+        // options added since are only written, with their name, when they
+        // aren't the default, so adding one doesn't change every fingerprint
+        if self.allow_quality_under_muscle_memory {
+            fingerprinter.write_str("allow_quality_under_muscle_memory");
+        }
+        if let Some(percent) = self.finishing_quality_percent {
+            fingerprinter
+                .write_str("finishing_quality_percent")
+                .write_u64(percent.into());
+        }
+        if let Some(tier) = self.target_tier {
+            fingerprinter
+                .write_str("target_tier")
+                .write_u64(tier.into());
+        }
+        if let Some(action_pool) = self.action_pool_override {
+            let names = sorted_names(action_pool);
+            fingerprinter
+                .write_str("action_pool_override")
+                .write_u64(names.len() as u64);
+            for name in names {
+                fingerprinter.write_str(name);
+            }
+        }
+        fingerprinter.finish()
+    }
+
+    /// Checks that the options are within reasonable bounds for a craft
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first invalid option.
+    pub fn validate(&self) -> Result<(), CraftContextError> {
+        if !MAX_STEPS_RANGE.contains(&self.max_steps) {
+            return Err(CraftContextError::MaxStepsOutOfRange(self.max_steps));
+        }
+        if let Some(tiers) = self.collectability_tiers {
+            if tiers.windows(2).any(|pair| pair[0] > pair[1]) {
+                return Err(CraftContextError::CollectabilityTiersUnordered);
+            }
+        }
+        if let Some(tier) = self.target_tier.filter(|&tier| tier > 2) {
+            return Err(CraftContextError::TargetTierOutOfRange(tier));
+        }
+        if self
+            .quality_weight
+            .is_some_and(|weight| !(0.0..=1.0).contains(&weight))
+        {
+            return Err(CraftContextError::QualityWeightOutOfRange);
+        }
+        if let Some(percent) = self
+            .finishing_quality_percent
+            .filter(|&percent| percent > 100)
+        {
+            return Err(CraftContextError::FinishingQualityOutOfRange(percent));
+        }
+        Ok(())
+    }
 }
 
 impl CraftContext {
     #[allow(clippy::cast_precision_loss)]
-    fn base_factors(player: &Player, recipe: &Recipe) -> (u32, u32) {
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    fn base_factors(player: &Player, stats: EffectiveStats, recipe: &Recipe) -> (u32, u32) {
         // https://github.com/ffxiv-teamcraft/simulator/blob/72f4a6037baa3cd7cd78dfe34207283b824881a2/src/model/actions/crafting-action.ts#L176
 
         let progress_div = recipe.progress_div as f32;
-        let mut base_progress_factor: f32 = (player.craftsmanship * 10) as f32 / progress_div + 2.0;
+        let mut base_progress_factor: f32 = (stats.craftsmanship * 10) as f32 / progress_div + 2.0;
 
         let quality_div = recipe.quality_div as f32;
-        let mut base_quality_factor: f32 = (player.control * 10) as f32 / quality_div + 35.0;
+        let mut base_quality_factor: f32 = (stats.control * 10) as f32 / quality_div + 35.0;
 
         if player.job_level <= recipe.job_level {
             base_progress_factor *= recipe.progress_mod as f32 / 100.0;
@@ -52,14 +285,27 @@ impl CraftContext {
         (base_progress_factor as u32, base_quality_factor as u32)
     }
 
-    fn determine_action_pool(player: &Player, recipe: &Recipe) -> ActionSet {
+    fn determine_action_pool(
+        player: &Player,
+        stats: EffectiveStats,
+        recipe: &Recipe,
+        options: &CraftOptions,
+    ) -> ActionSet {
+        if let Some(mut pool) = options.action_pool_override {
+            for action in options.excluded_actions.to_vec() {
+                pool.unset(action);
+            }
+            return pool;
+        }
+
         let mut pool = ActionSet::new();
 
         for action in Action::ACTIONS {
             let attrs = action.attributes();
-            if player.job_level >= attrs.level && player.cp >= attrs.cp_cost.unwrap_or(0) {
+            if player.job_level >= attrs.level && stats.cp >= attrs.cp_cost.unwrap_or(0) {
                 if action == &Action::TrainedEye
-                    && player.job_level.saturating_sub(recipe.job_level) < 10
+                    && player.job_level.saturating_sub(recipe.job_level)
+                        < TRAINED_EYE_LEVEL_DIFFERENCE
                 {
                     continue;
                 }
@@ -84,27 +330,450 @@ impl CraftContext {
             }
         }
 
+        // only after replacing actions with their traited versions, so that
+        // excluding a traited action doesn't bring the original back
+        for action in options.excluded_actions.to_vec() {
+            pool.unset(action);
+        }
+
         pool
     }
 
+    /// Like `new`, but checks that `options` are within reasonable bounds, and
+    /// that the player meets the recipe's stat requirements, first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first invalid option or unmet
+    /// requirement.
+    pub fn try_new(
+        player: &Player,
+        recipe: &Recipe,
+        options: CraftOptions,
+    ) -> Result<Self, CraftContextError> {
+        options.validate()?;
+        if player.craftsmanship < recipe.required_craftsmanship {
+            return Err(CraftContextError::CraftsmanshipTooLow(
+                recipe.required_craftsmanship,
+            ));
+        }
+        if player.control < recipe.required_control {
+            return Err(CraftContextError::ControlTooLow(recipe.required_control));
+        }
+        Ok(Self::new(player, recipe, options))
+    }
+
     pub fn new(player: &Player, recipe: &Recipe, options: CraftOptions) -> Self {
-        let (base_progress_factor, base_quality_factor) = Self::base_factors(player, recipe);
+        let effective_stats = EffectiveStats::new(player);
+        let (base_progress_factor, base_quality_factor) =
+            Self::base_factors(player, effective_stats, recipe);
+        let target_tier = usize::from(options.target_tier.unwrap_or(2).min(2));
+        let quality_target = options
+            .quality_target
+            .or(options
+                .collectability_tiers
+                .map(|tiers| Recipe::quality_from_collectability(tiers[target_tier])))
+            .unwrap_or(recipe.quality);
         Self {
             player_job_level: player.job_level,
             recipe_job_level: recipe.job_level,
+            effective_stats,
             base_progress_factor,
             base_quality_factor,
             step_max: options.max_steps,
             progress_target: recipe.progress,
             starting_quality: options.starting_quality.unwrap_or(0),
-            quality_target: options.quality_target.unwrap_or(recipe.quality),
+            quality_target,
+            // a collectability tier is still a quality target, so only recipes
+            // without quality or crafts that don't aim for any are excluded
+            quality_relevant: recipe.quality > 0 && quality_target > 0,
             durability_max: recipe.durability,
-            cp_max: player.cp,
+            cp_max: effective_stats.cp,
             is_expert: recipe.is_expert,
-            action_pool: Self::determine_action_pool(player, recipe),
+            conditions_flag: recipe.conditions_flag,
+            action_pool: Self::determine_action_pool(player, effective_stats, recipe, &options),
             player_is_specialist: options.player_is_specialist,
             use_manipulation: options.use_manipulation,
             use_delineation: options.use_delineation,
+            condition_seed: options.condition_seed,
+            collectability_tiers: options.collectability_tiers,
+            skip_trained_eye_opener: options.skip_trained_eye_opener,
+            allow_quality_under_muscle_memory: options.allow_quality_under_muscle_memory,
+            quality_weight: options.quality_weight,
+            // the floor is never above the target, so it fits unless the
+            // percent is invalid
+            finishing_quality_floor: u32::try_from(
+                u64::from(quality_target)
+                    * u64::from(options.finishing_quality_percent.unwrap_or(20))
+                    / 100,
+            )
+            .unwrap_or(quality_target),
         }
     }
+
+    /// The index into `collectability_tiers` of the highest tier that `quality`
+    /// reaches. None if there are no tiers, or the lowest isn't reached.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn collectability_tier(&self, quality: u32) -> Option<u8> {
+        let collectability = Recipe::collectability_from_quality(quality);
+        let tiers = self.collectability_tiers?;
+        tiers
+            .iter()
+            .rposition(|&tier| collectability >= tier)
+            .map(|index| index as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_new_validates_max_steps() {
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = |max_steps| CraftOptions {
+            max_steps,
+            ..Default::default()
+        };
+
+        assert!(CraftContext::try_new(&player, &recipe, options(25)).is_ok());
+        assert!(CraftContext::try_new(&player, &recipe, options(5)).is_ok());
+        assert_eq!(
+            CraftContext::try_new(&player, &recipe, options(0)).unwrap_err(),
+            CraftContextError::MaxStepsOutOfRange(0)
+        );
+        assert!(CraftContext::try_new(&player, &recipe, options(51)).is_err());
+    }
+
+    #[test]
+    fn try_new_validates_quality_weight() {
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = |quality_weight| CraftOptions {
+            max_steps: 25,
+            quality_weight,
+            ..Default::default()
+        };
+
+        assert!(CraftContext::try_new(&player, &recipe, options(None)).is_ok());
+        assert!(CraftContext::try_new(&player, &recipe, options(Some(0.0))).is_ok());
+        assert!(CraftContext::try_new(&player, &recipe, options(Some(1.0))).is_ok());
+        assert_eq!(
+            CraftContext::try_new(&player, &recipe, options(Some(1.5))).unwrap_err(),
+            CraftContextError::QualityWeightOutOfRange
+        );
+        assert!(CraftContext::try_new(&player, &recipe, options(Some(f32::NAN))).is_err());
+    }
+
+    #[test]
+    fn try_new_checks_stat_requirements() {
+        let recipe = Recipe {
+            required_craftsmanship: 3000,
+            required_control: 3500,
+            ..crate::data::recipes(90)[0]
+        };
+        let options = CraftOptions {
+            max_steps: 25,
+            ..Default::default()
+        };
+        let try_new = |craftsmanship, control| {
+            let player = Player::new(90, craftsmanship, control, 649);
+            CraftContext::try_new(&player, &recipe, options)
+        };
+
+        assert!(try_new(3000, 3500).is_ok());
+        assert!(try_new(3290, 3541).is_ok());
+        assert_eq!(
+            try_new(2999, 3541).unwrap_err(),
+            CraftContextError::CraftsmanshipTooLow(3000)
+        );
+        assert_eq!(
+            try_new(3290, 3499).unwrap_err(),
+            CraftContextError::ControlTooLow(3500)
+        );
+        assert_eq!(
+            CraftContextError::CraftsmanshipTooLow(3000).to_string(),
+            "craftsmanship too low, need 3000"
+        );
+    }
+
+    #[test]
+    fn option_fingerprints_are_pinned() {
+        let options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            excluded_actions: ActionSet::from_vec(&vec![Action::Reflect, Action::MuscleMemory]),
+            ..Default::default()
+        };
+        assert_eq!(options.fingerprint(), 17_858_785_887_711_721_481);
+
+        let reordered = CraftOptions {
+            excluded_actions: ActionSet::from_vec(&vec![Action::MuscleMemory, Action::Reflect]),
+            ..options
+        };
+        assert_eq!(reordered.fingerprint(), options.fingerprint());
+
+        let changed = [
+            CraftOptions {
+                starting_quality: Some(0),
+                ..options
+            },
+            CraftOptions {
+                collectability_tiers: Some([0, 0, 0]),
+                ..options
+            },
+            CraftOptions {
+                quality_weight: Some(0.5),
+                ..options
+            },
+            CraftOptions {
+                allow_quality_under_muscle_memory: true,
+                ..options
+            },
+            CraftOptions {
+                finishing_quality_percent: Some(20),
+                ..options
+            },
+            CraftOptions {
+                action_pool_override: Some(ActionSet::new()),
+                ..options
+            },
+        ];
+        for changed in changed {
+            assert_ne!(changed.fingerprint(), options.fingerprint(), "{changed:?}");
+        }
+    }
+
+    #[test]
+    fn traited_actions_replace_originals_at_their_level() {
+        use Action::*;
+
+        let recipe = crate::data::recipes(90)[0];
+        let options = CraftOptions {
+            max_steps: 25,
+            ..Default::default()
+        };
+        let pool = |job_level| {
+            let player = Player::new(job_level, 4000, 4000, 600);
+            CraftContext::new(&player, &recipe, options).action_pool
+        };
+
+        for (original, traited) in [
+            (BasicSynthesis, BasicSynthesisTraited),
+            (CarefulSynthesis, CarefulSynthesisTraited),
+            (Groundwork, GroundworkTraited),
+            (DelicateSynthesis, DelicateSynthesisTraited),
+        ] {
+            let trait_level = traited.attributes().level;
+
+            let before = pool(trait_level - 1);
+            assert!(before.contains(original), "{original:?}");
+            assert!(!before.contains(traited), "{traited:?}");
+
+            for level in [trait_level, trait_level + 1] {
+                let after = pool(level);
+                assert!(!after.contains(original), "{original:?} at {level}");
+                assert!(after.contains(traited), "{traited:?} at {level}");
+            }
+        }
+    }
+
+    #[test]
+    fn trained_eye_needs_level_difference() {
+        let recipe = crate::data::recipes(90)[0];
+        let options = CraftOptions {
+            max_steps: 25,
+            ..Default::default()
+        };
+        let pool = |job_level| {
+            let player = Player::new(job_level, 4000, 4000, 600);
+            CraftContext::new(&player, &recipe, options).action_pool
+        };
+
+        assert!(!pool(90 + TRAINED_EYE_LEVEL_DIFFERENCE - 1).contains(Action::TrainedEye));
+        assert!(pool(90 + TRAINED_EYE_LEVEL_DIFFERENCE).contains(Action::TrainedEye));
+
+        // still in the pool for expert recipes, but never usable
+        let expert = Recipe {
+            is_expert: true,
+            ..recipe
+        };
+        let context = CraftContext::new(&Player::new(100, 4000, 4000, 600), &expert, options);
+        assert_eq!(
+            crate::CraftState::new(&context).can_use(Action::TrainedEye),
+            Err(crate::InvalidActionReason::ExpertRecipe)
+        );
+    }
+
+    #[test]
+    fn excluded_actions_leave_the_pool() {
+        use Action::*;
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(100, 4000, 4000, 600);
+        let options = CraftOptions {
+            max_steps: 25,
+            excluded_actions: ActionSet::from_vec(&vec![Observe, GroundworkTraited]),
+            ..Default::default()
+        };
+
+        let pool = CraftContext::new(&player, &recipe, options).action_pool;
+        assert!(!pool.contains(Observe));
+        assert!(!pool.contains(GroundworkTraited));
+        // the untraited version stays replaced
+        assert!(!pool.contains(Groundwork));
+        assert!(pool.contains(BasicTouch));
+    }
+
+    #[test]
+    fn action_pool_override_replaces_the_pool() {
+        use Action::*;
+        let recipe = crate::data::recipes(90)[0];
+        // too low to use Basic Touch or Veneration normally
+        let player = Player::new(1, 4000, 4000, 600);
+        let options = CraftOptions {
+            max_steps: 25,
+            action_pool_override: Some(ActionSet::from_vec(&vec![
+                BasicSynthesis,
+                BasicTouch,
+                Veneration,
+            ])),
+            excluded_actions: ActionSet::from_vec(&vec![Veneration]),
+            ..Default::default()
+        };
+
+        let pool = CraftContext::new(&player, &recipe, options).action_pool;
+        assert_eq!(pool.to_vec(), vec![BasicSynthesis, BasicTouch]);
+    }
+
+    #[test]
+    fn collectability_round_trips() {
+        assert_eq!(Recipe::collectability_from_quality(5_999), 599);
+        assert_eq!(Recipe::quality_from_collectability(600), 6_000);
+        assert_eq!(
+            Recipe::collectability_from_quality(Recipe::quality_from_collectability(600)),
+            600
+        );
+    }
+
+    #[test]
+    fn collectability_tiers_set_the_target() {
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = CraftOptions {
+            max_steps: 25,
+            collectability_tiers: Some([300, 450, 600]),
+            ..Default::default()
+        };
+
+        let context = CraftContext::try_new(&player, &recipe, options).unwrap();
+        assert_eq!(context.quality_target, 6000);
+        assert_eq!(context.collectability_tier(2999), None);
+        assert_eq!(context.collectability_tier(3000), Some(0));
+        assert_eq!(context.collectability_tier(5999), Some(1));
+        assert_eq!(context.collectability_tier(9000), Some(2));
+
+        let context = CraftContext::new(
+            &player,
+            &recipe,
+            CraftOptions {
+                quality_target: Some(4500),
+                ..options
+            },
+        );
+        assert_eq!(context.quality_target, 4500);
+
+        // a lower tier can be aimed for instead
+        let middle_tier = CraftOptions {
+            target_tier: Some(1),
+            ..options
+        };
+        let context = CraftContext::try_new(&player, &recipe, middle_tier).unwrap();
+        assert_eq!(context.quality_target, 4500);
+        assert_eq!(context.collectability_tier(context.quality_target), Some(1));
+        assert_ne!(middle_tier.fingerprint(), options.fingerprint());
+        assert_eq!(
+            CraftOptions {
+                target_tier: Some(3),
+                ..options
+            }
+            .validate(),
+            Err(CraftContextError::TargetTierOutOfRange(3))
+        );
+
+        let unordered = CraftOptions {
+            collectability_tiers: Some([450, 300, 600]),
+            ..options
+        };
+        assert_eq!(
+            unordered.validate(),
+            Err(CraftContextError::CollectabilityTiersUnordered)
+        );
+    }
+
+    #[test]
+    fn quality_relevance_is_explicit() {
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = |quality_target| CraftOptions {
+            max_steps: 25,
+            quality_target,
+            ..Default::default()
+        };
+
+        assert!(CraftContext::new(&player, &recipe, options(None)).quality_relevant);
+        // a collectability tier below max quality is still scored on quality
+        assert!(CraftContext::new(&player, &recipe, options(Some(1000))).quality_relevant);
+        assert!(!CraftContext::new(&player, &recipe, options(Some(0))).quality_relevant);
+
+        let no_quality = Recipe {
+            quality: 0,
+            ..recipe
+        };
+        assert!(!CraftContext::new(&player, &no_quality, options(None)).quality_relevant);
+    }
+
+    #[test]
+    fn craft_options_round_trip() {
+        let options = CraftOptions {
+            max_steps: 30,
+            quality_target: Some(8000),
+            player_is_specialist: true,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"max_steps":30,"starting_quality":null,"quality_target":8000,"#,
+                r#""player_is_specialist":true,"use_manipulation":true,"#,
+                r#""use_delineation":false,"condition_seed":null,"collectability_tiers":null,"#,
+                r#""target_tier":null,"skip_trained_eye_opener":false,"allow_quality_under_muscle_memory":false,"#,
+                r#""excluded_actions":[],"quality_weight":null,"#,
+                r#""finishing_quality_percent":null,"action_pool_override":null}"#
+            )
+        );
+        assert_eq!(
+            serde_json::from_str::<CraftOptions>(&json).unwrap(),
+            options
+        );
+
+        // presets saved before an option existed still load
+        let partial: CraftOptions = serde_json::from_str(r#"{"max_steps":30}"#).unwrap();
+        assert_eq!(partial.max_steps, 30);
+        assert!(!partial.use_manipulation);
+
+        // action sets are read and written as lists of actions, in the order
+        // of `Action::ACTIONS`
+        let excluded: CraftOptions =
+            serde_json::from_str(r#"{"excluded_actions":["Reflect","Observe"]}"#).unwrap();
+        assert_eq!(
+            excluded.excluded_actions.to_vec(),
+            vec![Action::Observe, Action::Reflect]
+        );
+        assert!(serde_json::to_string(&excluded)
+            .unwrap()
+            .contains(r#""excluded_actions":["Observe","Reflect"]"#));
+    }
 }
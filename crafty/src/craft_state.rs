@@ -1,12 +1,17 @@
-use crate::{action::Attributes, Action, ActionSet, CraftContext};
-use serde::Serialize;
+use crate::{
+    action::Attributes, Action, ActionSet, Condition, CraftContext, CAREFUL_OBSERVATION_USES,
+    GREAT_STRIDES_DURATION, INNOVATION_DURATION, MANIPULATION_DURATION, MAX_INNER_QUIET,
+    MUSCLE_MEMORY_DURATION, VENERATION_DURATION, WASTE_NOT_II_DURATION,
+};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use std::{cmp, fmt};
 use ts_type::{wasm_bindgen, TsType};
 
 #[derive(Debug)]
 pub enum CraftResult {
-    /// The craft reached 100% progress. Includes the score of the `CraftState`.
-    Finished(f32),
+    /// The craft reached 100% progress. Includes the score and final values of
+    /// the `CraftState`.
+    Finished(CraftMetrics),
     /// No durability remains.
     DurabilityFailure,
     /// The step limit was reached.
@@ -15,7 +20,89 @@ pub enum CraftResult {
     InvalidActionFailure,
 }
 
-#[derive(Default, Debug, Clone, Serialize, TsType)]
+/// Final values of a finished craft
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TsType)]
+pub struct CraftMetrics {
+    pub score: f32,
+    pub progress: u32,
+    pub quality: u32,
+    pub durability: i8,
+    pub cp: u32,
+    /// Number of steps used to finish the craft
+    pub steps: u8,
+    pub hq_percent: u8,
+    /// Index of the highest collectability tier reached, if the craft has tiers
+    pub collectability_tier: Option<u8>,
+}
+
+/// The part of `CraftState::score` that each metric contributes
+struct ScoreComponents {
+    progress: f32,
+    quality: f32,
+    durability: f32,
+    cp: f32,
+    fewer_steps: f32,
+}
+
+/// The reason an action can't be used, from `CraftState::can_use`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub enum InvalidActionReason {
+    /// The craft has already finished or failed
+    CraftOver,
+    /// The player's level or CP is too low, or the action was replaced by a
+    /// traited version
+    Unavailable,
+    NotEnoughCp,
+    /// Quality can't be increased any further
+    QualityMaxed,
+    FirstStepOnly,
+    ExpertRecipe,
+    NotEnoughInnerQuiet,
+    /// Can only be used once per craft
+    AlreadyUsed,
+    WasteNotActive,
+    AlreadyObserving,
+    ComboRequired,
+    ManipulationDisabled,
+    InnovationActive,
+    /// Careful Observation has been used three times, or the player isn't a
+    /// specialist
+    NoUsesRemaining,
+}
+
+/// The heuristic that keeps a usable action out of the strict moveset, from
+/// `CraftState::pruning_rule`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruningRule {
+    /// Trained Eye is always used first when it's available
+    TrainedEyeOpener,
+    /// Quality actions aren't used under Muscle Memory at the recipe's level
+    QualityUnderMuscleMemory,
+    /// Pure quality actions aren't used under Veneration
+    QualityUnderVeneration,
+    /// Quality buffs aren't used once the quality target is reached
+    QualityBuffAtTarget,
+    /// Only Advanced Touch follows Observe
+    ObserveFollowUp,
+    /// The craft isn't finished while quality is below
+    /// `CraftOptions::finishing_quality_percent` of the target
+    EarlyFinish,
+    /// Pure progress actions aren't used under Innovation
+    ProgressUnderInnovation,
+    /// A rule for that specific action, e.g. Waste Not only when its buff
+    /// isn't active
+    ActionHeuristic,
+}
+
+/// Maps a quality percentage (0-100) to the resulting HQ chance
+const HQ_TABLE: [u8; 101] = [
+    1, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8,
+    9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 12, 13, 13, 13, 14, 14, 14, 15, 15, 15, 16, 16, 17,
+    17, 17, 18, 18, 18, 19, 19, 20, 20, 21, 22, 23, 24, 26, 28, 31, 34, 38, 42, 47, 52, 58, 64, 68,
+    71, 74, 76, 78, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 94, 96, 98, 100,
+];
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, TsType)]
 pub struct Buffs {
     pub inner_quiet: u8,
     pub waste_not: u8,
@@ -43,6 +130,195 @@ impl Buffs {
         self.veneration = self.veneration.saturating_sub(1);
         self.muscle_memory = self.muscle_memory.saturating_sub(1);
     }
+
+    /// Steps left of either Waste Not or Waste Not II, which have the same
+    /// effect. Only one should be active at a time, but the longer is used.
+    pub fn waste_not_remaining(&self) -> u8 {
+        self.waste_not.max(self.waste_not_ii)
+    }
+}
+
+/// One of the buffs tracked by `Buffs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub enum BuffKind {
+    InnerQuiet,
+    /// Either Waste Not or Waste Not II
+    WasteNot,
+    Manipulation,
+    GreatStrides,
+    Innovation,
+    Veneration,
+    MuscleMemory,
+}
+
+impl BuffKind {
+    pub const BUFFS: &'static [BuffKind] = &[
+        BuffKind::InnerQuiet,
+        BuffKind::WasteNot,
+        BuffKind::Manipulation,
+        BuffKind::GreatStrides,
+        BuffKind::Innovation,
+        BuffKind::Veneration,
+        BuffKind::MuscleMemory,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BuffKind::InnerQuiet => "Inner Quiet",
+            BuffKind::WasteNot => "Waste Not",
+            BuffKind::Manipulation => "Manipulation",
+            BuffKind::GreatStrides => "Great Strides",
+            BuffKind::Innovation => "Innovation",
+            BuffKind::Veneration => "Veneration",
+            BuffKind::MuscleMemory => "Muscle Memory",
+        }
+    }
+
+    /// Whether the buff's value counts stacks, rather than steps remaining
+    pub fn is_stack(self) -> bool {
+        self == BuffKind::InnerQuiet
+    }
+
+    /// The highest value the buff can have, in steps or stacks. Waste Not
+    /// covers Waste Not II, which lasts longer.
+    pub fn max_value(self) -> u8 {
+        match self {
+            BuffKind::InnerQuiet => MAX_INNER_QUIET,
+            BuffKind::WasteNot => WASTE_NOT_II_DURATION,
+            BuffKind::Manipulation => MANIPULATION_DURATION,
+            BuffKind::GreatStrides => GREAT_STRIDES_DURATION,
+            BuffKind::Innovation => INNOVATION_DURATION,
+            BuffKind::Veneration => VENERATION_DURATION,
+            BuffKind::MuscleMemory => MUSCLE_MEMORY_DURATION,
+        }
+    }
+
+    pub fn metadata(self) -> BuffMetadata {
+        BuffMetadata {
+            kind: self,
+            label: self.label(),
+            max_value: self.max_value(),
+            is_stack: self.is_stack(),
+        }
+    }
+}
+
+/// Static information about a buff, for displaying it
+#[derive(Debug, Clone, Copy, Serialize, TsType)]
+pub struct BuffMetadata {
+    pub kind: BuffKind,
+    pub label: &'static str,
+    pub max_value: u8,
+    pub is_stack: bool,
+}
+
+impl Buffs {
+    /// Buffs that are currently active, paired with their remaining steps.
+    /// Inner Quiet is paired with its stack count instead, since it doesn't
+    /// expire, and Waste Not II is reported as Waste Not.
+    pub fn active(&self) -> Vec<(BuffKind, u8)> {
+        [
+            (BuffKind::InnerQuiet, self.inner_quiet),
+            (BuffKind::WasteNot, self.waste_not_remaining()),
+            (BuffKind::Manipulation, self.manipulation),
+            (BuffKind::GreatStrides, self.great_strides),
+            (BuffKind::Innovation, self.innovation),
+            (BuffKind::Veneration, self.veneration),
+            (BuffKind::MuscleMemory, self.muscle_memory),
+        ]
+        .into_iter()
+        .filter(|&(_, value)| value > 0)
+        .collect()
+    }
+}
+
+/// Signed change in each buff's value between two `Buffs`
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub struct BuffsDiff {
+    pub inner_quiet: i16,
+    pub waste_not: i16,
+    pub waste_not_ii: i16,
+    pub manipulation: i16,
+    pub great_strides: i16,
+    pub innovation: i16,
+    pub veneration: i16,
+    pub muscle_memory: i16,
+}
+
+impl Buffs {
+    /// The change in each buff going from `self` to `other`
+    pub fn diff(&self, other: &Buffs) -> BuffsDiff {
+        let delta = |from: u8, to: u8| i16::from(to) - i16::from(from);
+        BuffsDiff {
+            inner_quiet: delta(self.inner_quiet, other.inner_quiet),
+            waste_not: delta(self.waste_not, other.waste_not),
+            waste_not_ii: delta(self.waste_not_ii, other.waste_not_ii),
+            manipulation: delta(self.manipulation, other.manipulation),
+            great_strides: delta(self.great_strides, other.great_strides),
+            innovation: delta(self.innovation, other.innovation),
+            veneration: delta(self.veneration, other.veneration),
+            muscle_memory: delta(self.muscle_memory, other.muscle_memory),
+        }
+    }
+}
+
+/// Signed change in each value between two `CraftState`s
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub struct StateDiff {
+    pub step: i16,
+    pub progress: i32,
+    pub quality: i32,
+    pub durability: i16,
+    pub cp: i32,
+    pub buffs: BuffsDiff,
+}
+
+/// A craft in progress as the game shows it, e.g. read by a plugin, for use
+/// with `CraftState::from_in_game`. Each buff is the number of steps it has
+/// left, or 0 if it isn't active. Missing fields take their default values.
+#[derive(Debug, Clone, Copy, Default, Deserialize, TsType)]
+#[serde(default)]
+pub struct InGameState {
+    /// The step about to be taken, starting from 1. Steps already taken count
+    /// toward the step limit and the fewer steps score.
+    pub step: u8,
+    pub progress: u32,
+    pub quality: u32,
+    pub durability: i8,
+    pub cp: u32,
+    pub condition: Condition,
+    /// The last action used, so combos can be continued
+    pub last_action: Option<Action>,
+    /// The action used before `last_action`. Standard Touch only continues a
+    /// combo into Advanced Touch if it followed Basic Touch.
+    pub action_before_last: Option<Action>,
+    pub inner_quiet: u8,
+    pub waste_not: u8,
+    pub waste_not_ii: u8,
+    pub manipulation: u8,
+    pub great_strides: u8,
+    pub innovation: u8,
+    pub veneration: u8,
+    pub muscle_memory: u8,
+}
+
+/// A `CraftState` as it's serialized, e.g. the `craft_state` that the web
+/// layer hands back, for use with `CraftState::from_serialized`. Search
+/// statistics and available moves aren't needed to continue a craft.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SerializedCraftState {
+    pub step: u8,
+    pub progress: u32,
+    pub quality: u32,
+    pub durability: i8,
+    pub cp: u32,
+    pub condition: Condition,
+    pub previous_combo_action: Option<Action>,
+    pub quick_innovation_available: bool,
+    pub careful_observations_remaining: u8,
+    pub trained_perfection_active: Option<bool>,
+    pub buffs: Buffs,
+    pub action: Option<Action>,
 }
 
 #[derive(Debug, Clone)]
@@ -58,8 +334,11 @@ pub struct CraftState<'a> {
     pub durability: i8,
     pub cp: u32,
 
+    pub condition: Condition,
     pub previous_combo_action: Option<Action>,
     pub quick_innovation_available: bool,
+    /// Careful Observation uses left, separate from delineations
+    pub careful_observations_remaining: u8,
     pub trained_perfection_active: Option<bool>,
     pub buffs: Buffs,
 
@@ -74,7 +353,67 @@ pub struct CraftState<'a> {
     pub available_moves: ActionSet,
 }
 
-impl<'a> fmt::Display for CraftState<'a> {
+// serialized with the limits from its context alongside its own values, see
+// the `Serialize` impl below. Search statistics aren't meaningful outside of
+// the search tree, so they're left out.
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_CRAFT_STATE: &'static str = r#"
+export type CraftState = {
+    step: number;
+    step_max: number;
+    progress: number;
+    progress_target: number;
+    quality: number;
+    quality_target: number;
+    durability: number;
+    durability_max: number;
+    cp: number;
+    cp_max: number;
+    effective_stats: EffectiveStats;
+    condition: Condition;
+    previous_combo_action: Action | undefined;
+    quick_innovation_available: boolean;
+    careful_observations_remaining: number;
+    trained_perfection_active: boolean | undefined;
+    buffs: Buffs;
+    action: Action | undefined;
+    available_moves: ActionSet;
+};
+"#;
+
+impl Serialize for CraftState<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CraftState", 19)?;
+        state.serialize_field("step", &self.step)?;
+        state.serialize_field("step_max", &self.context.step_max)?;
+        state.serialize_field("progress", &self.progress)?;
+        state.serialize_field("progress_target", &self.context.progress_target)?;
+        state.serialize_field("quality", &self.quality)?;
+        state.serialize_field("quality_target", &self.context.quality_target)?;
+        state.serialize_field("durability", &self.durability)?;
+        state.serialize_field("durability_max", &self.context.durability_max)?;
+        state.serialize_field("cp", &self.cp)?;
+        state.serialize_field("cp_max", &self.context.cp_max)?;
+        state.serialize_field("effective_stats", &self.context.effective_stats)?;
+        state.serialize_field("condition", &self.condition)?;
+        state.serialize_field("previous_combo_action", &self.previous_combo_action)?;
+        state.serialize_field(
+            "quick_innovation_available",
+            &self.quick_innovation_available,
+        )?;
+        state.serialize_field(
+            "careful_observations_remaining",
+            &self.careful_observations_remaining,
+        )?;
+        state.serialize_field("trained_perfection_active", &self.trained_perfection_active)?;
+        state.serialize_field("buffs", &self.buffs)?;
+        state.serialize_field("action", &self.action)?;
+        state.serialize_field("available_moves", &self.available_moves)?;
+        state.end()
+    }
+}
+
+impl fmt::Display for CraftState<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
@@ -87,7 +426,12 @@ impl<'a> fmt::Display for CraftState<'a> {
             self.context.durability_max,
             self.cp,
             self.context.cp_max
-        )
+        )?;
+        // the condition is always Normal otherwise, so it isn't worth showing
+        if Condition::is_variable(self.context.conditions_flag) {
+            write!(f, " | {:?}", self.condition)?;
+        }
+        Ok(())
     }
 }
 
@@ -100,8 +444,14 @@ impl<'a> CraftState<'a> {
             quality: context.starting_quality,
             durability: context.durability_max,
             cp: context.cp_max,
+            condition: Condition::Normal,
             previous_combo_action: None,
             quick_innovation_available: context.use_delineation,
+            careful_observations_remaining: if context.player_is_specialist {
+                CAREFUL_OBSERVATION_USES
+            } else {
+                0
+            },
             trained_perfection_active: None,
             buffs: Buffs::new(),
             action: None,
@@ -124,181 +474,390 @@ impl<'a> CraftState<'a> {
         state
     }
 
+    /// A state partway through a craft, so tests can start from specific
+    /// values without replaying the actions that would lead to them. The craft
+    /// is treated as already started, so first step actions can't be used.
+    #[cfg(test)]
+    pub(crate) fn with_values(
+        context: &'a CraftContext,
+        progress: u32,
+        quality: u32,
+        durability: i8,
+        cp: u32,
+        buffs: Buffs,
+    ) -> Self {
+        let mut state = Self {
+            step: 2,
+            progress,
+            quality,
+            durability,
+            cp,
+            buffs,
+            ..Self::_new(context)
+        };
+        state.set_available_moves(false);
+        state
+    }
+
+    /// Picks up a craft from the values shown in game. Once-per-craft actions
+    /// like Quick Innovation and Trained Perfection are assumed to be unused,
+    /// since the game doesn't show whether they were.
+    pub fn from_in_game(context: &'a CraftContext, in_game: &InGameState) -> Self {
+        let previous_combo_action = in_game
+            .last_action
+            .filter(|action| action.can_be_followed_up(in_game.action_before_last));
+        let mut state = Self {
+            step: in_game.step.max(1),
+            progress: in_game.progress,
+            quality: in_game.quality,
+            durability: in_game.durability,
+            cp: in_game.cp,
+            condition: in_game.condition,
+            previous_combo_action,
+            buffs: Buffs {
+                inner_quiet: in_game.inner_quiet,
+                waste_not: in_game.waste_not,
+                waste_not_ii: in_game.waste_not_ii,
+                manipulation: in_game.manipulation,
+                great_strides: in_game.great_strides,
+                innovation: in_game.innovation,
+                veneration: in_game.veneration,
+                muscle_memory: in_game.muscle_memory,
+            },
+            ..Self::_new(context)
+        };
+        state.set_available_moves(false);
+        state
+    }
+
+    /// Rebuilds a state from its serialized values, so a craft can be continued
+    /// one action at a time without replaying every action before it. The
+    /// state has all available moves, like one from `execute`. Steps start at
+    /// 1, so a step of 0 is treated as 1.
+    pub fn from_serialized(context: &'a CraftContext, serialized: &SerializedCraftState) -> Self {
+        let mut state = Self {
+            step: serialized.step.max(1),
+            progress: serialized.progress,
+            quality: serialized.quality,
+            durability: serialized.durability,
+            cp: serialized.cp,
+            condition: serialized.condition,
+            previous_combo_action: serialized.previous_combo_action,
+            quick_innovation_available: serialized.quick_innovation_available,
+            careful_observations_remaining: serialized.careful_observations_remaining,
+            trained_perfection_active: serialized.trained_perfection_active,
+            buffs: serialized.buffs.clone(),
+            action: serialized.action,
+            ..Self::_new(context)
+        };
+        state.set_available_moves(false);
+        state
+    }
+
+    /// The change in each value going from `self` to `other`. Useful for
+    /// seeing what a single action did to the craft.
+    pub fn diff(&self, other: &CraftState) -> StateDiff {
+        // values are far below i32::MAX, so the difference always fits
+        #[allow(clippy::cast_possible_truncation)]
+        let delta = |from: u32, to: u32| (i64::from(to) - i64::from(from)) as i32;
+
+        StateDiff {
+            step: i16::from(other.step) - i16::from(self.step),
+            progress: delta(self.progress, other.progress),
+            quality: delta(self.quality, other.quality),
+            durability: i16::from(other.durability) - i16::from(self.durability),
+            cp: delta(self.cp, other.cp),
+            buffs: self.buffs.diff(&other.buffs),
+        }
+    }
+
     pub fn clone_strict(&self) -> Self {
         let mut state = self.clone();
         state.set_available_moves(true);
         state
     }
 
+    /// Whether the craft has finished or failed, regardless of available moves
+    pub(crate) fn is_over(&self) -> bool {
+        self.progress >= self.context.progress_target
+            || self.step >= self.context.step_max
+            || self.durability <= 0
+    }
+
     /// Examine the current craft state and populate `available_moves`.
     /// Enabling `strict` will add more rules that aim to prune as many
     /// suboptimal moves as possible.
-    #[allow(clippy::too_many_lines)]
-    fn set_available_moves(&mut self, strict: bool) -> &mut Self {
-        if self.progress >= self.context.progress_target
-            || self.step >= self.context.step_max
-            || self.durability <= 0
-        {
+    pub(crate) fn set_available_moves(&mut self, strict: bool) -> &mut Self {
+        if self.is_over() {
             return self;
         }
 
-        let mut available_moves = self.context.action_pool.clone();
+        let mut available_moves = self.context.action_pool;
         available_moves.keep(|action| {
-            use Action::*;
             let attrs = action.attributes();
+            self.check_rules(*action, &attrs).is_ok()
+                && (!strict || self.pruning_rule_for(*action, &attrs).is_none())
+        });
+        self.available_moves = available_moves;
 
-            if let Some(base_cost) = attrs.cp_cost {
-                if Action::calc_cp_cost(self, base_cost) > self.cp {
-                    return false;
-                }
+        self
+    }
+
+    /// Checks whether an action can be used from this state, and if not, why.
+    /// Runs the same checks used to determine the non-strict `available_moves`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first rule that the action fails.
+    pub fn can_use(&self, action: Action) -> Result<(), InvalidActionReason> {
+        if self.is_over() {
+            return Err(InvalidActionReason::CraftOver);
+        }
+        if !self.context.action_pool.contains(action) {
+            return Err(InvalidActionReason::Unavailable);
+        }
+        self.check_rules(action, &action.attributes())
+    }
+
+    /// Rules that every action must follow to be usable
+    fn check_rules(&self, action: Action, attrs: &Attributes) -> Result<(), InvalidActionReason> {
+        use Action::*;
+        use InvalidActionReason::*;
+
+        fn require(
+            condition: bool,
+            reason: InvalidActionReason,
+        ) -> Result<(), InvalidActionReason> {
+            if condition {
+                Ok(())
+            } else {
+                Err(reason)
             }
+        }
+
+        if let Some(base_cost) = attrs.cp_cost {
+            require(
+                Action::calc_cp_cost(self, action, base_cost) <= self.cp,
+                NotEnoughCp,
+            )?;
+        }
+
+        // don't allow quality moves at max quality
+        require(
+            self.quality < self.context.quality_target || attrs.quality_efficiency.is_none(),
+            QualityMaxed,
+        )?;
 
-            // don't allow quality moves at max quality
-            if self.quality >= self.context.quality_target && attrs.quality_efficiency.is_some() {
-                return false;
+        match action {
+            MuscleMemory | Reflect => require(self.step == 1, FirstStepOnly),
+            TrainedEye => {
+                require(self.step == 1, FirstStepOnly)?;
+                require(!self.context.is_expert, ExpertRecipe)
             }
+            ByregotsBlessing => require(self.buffs.inner_quiet > 0, NotEnoughInnerQuiet),
+            TrainedFinesse => require(
+                self.buffs.inner_quiet == MAX_INNER_QUIET,
+                NotEnoughInnerQuiet,
+            ),
+            TrainedPerfection => require(self.trained_perfection_active.is_none(), AlreadyUsed),
+            PrudentSynthesis | PrudentTouch => {
+                require(self.buffs.waste_not_remaining() == 0, WasteNotActive)
+            }
+            // don't allow Observe if observing
+            Observe => require(
+                self.previous_combo_action != Some(Observe),
+                AlreadyObserving,
+            ),
+            RefinedTouch => require(
+                action.continues_combo(self.previous_combo_action),
+                ComboRequired,
+            ),
+            Manipulation => require(self.context.use_manipulation, ManipulationDisabled),
+            QuickInnovation => {
+                require(self.quick_innovation_available, AlreadyUsed)?;
+                require(self.buffs.innovation == 0, InnovationActive)
+            }
+            CarefulObservation => require(self.careful_observations_remaining > 0, NoUsesRemaining),
+            // make sure we've exhaustively handled every action; don't use a wildcard here
+            AdvancedTouch
+            | BasicSynthesis
+            | BasicSynthesisTraited
+            | BasicTouch
+            | CarefulSynthesis
+            | CarefulSynthesisTraited
+            | DelicateSynthesis
+            | DelicateSynthesisTraited
+            | GreatStrides
+            | Groundwork
+            | GroundworkTraited
+            | Innovation
+            | ImmaculateMend
+            | MastersMend
+            | PreparatoryTouch
+            | StandardTouch
+            | Veneration
+            | WasteNot
+            | WasteNotII => Ok(()),
+        }
+    }
 
-            if strict {
-                // always used Trained Eye if it's available
-                if self.step == 1
-                    && self.context.quality_target > 0
-                    && !self.context.is_expert
-                    && self.context.action_pool.contains(TrainedEye)
-                {
-                    return action == &TrainedEye;
-                }
+    /// The strict pruning rule that keeps `action` out of the strict moveset,
+    /// or None if it's kept. Doesn't check whether the action can be used at
+    /// all; see `can_use` for that.
+    pub fn pruning_rule(&self, action: Action) -> Option<PruningRule> {
+        self.pruning_rule_for(action, &action.attributes())
+    }
 
-                // don't allow quality moves under Muscle Memory for difficult crafts
-                if self.context.recipe_job_level == self.context.player_job_level
-                    && self.buffs.muscle_memory > 0
-                    && attrs.quality_efficiency.is_some()
-                {
-                    return false;
-                }
+    /// Heuristics used to prune moves that are unlikely to lead to a good
+    /// craft when `strict` is enabled. Assumes `check_rules` already passed.
+    #[allow(clippy::too_many_lines)]
+    fn pruning_rule_for(&self, action: Action, attrs: &Attributes) -> Option<PruningRule> {
+        use Action::*;
+        use PruningRule::*;
 
-                // don't allow pure quality moves under Veneration
-                if self.buffs.veneration > 0
-                    && attrs.progress_efficiency.is_none()
-                    && attrs.quality_efficiency.is_some()
-                {
-                    return false;
-                }
+        // always used Trained Eye if it's available, unless the starting
+        // quality already reaches the target
+        if self.step == 1
+            && !self.context.skip_trained_eye_opener
+            && self.context.quality_relevant
+            && self.quality < self.context.quality_target
+            && !self.context.is_expert
+            && self.context.action_pool.contains(TrainedEye)
+        {
+            return (action != TrainedEye).then_some(TrainedEyeOpener);
+        }
 
-                // only allow Advanced Touch when Observing
-                if self.previous_combo_action == Some(Observe) && action != &AdvancedTouch {
-                    return false;
-                }
+        // don't allow quality moves under Muscle Memory for difficult crafts,
+        // unless asked to
+        if !self.context.allow_quality_under_muscle_memory
+            && self.context.recipe_job_level == self.context.player_job_level
+            && self.buffs.muscle_memory > 0
+            && attrs.quality_efficiency.is_some()
+        {
+            return Some(QualityUnderMuscleMemory);
+        }
 
-                if let Some(progress_eff) = attrs.progress_efficiency {
-                    let progress_increase = Action::calc_progress_increase(self, progress_eff);
-                    let would_finish =
-                        self.progress + progress_increase >= self.context.progress_target;
-
-                    if would_finish {
-                        // don't allow finishing the craft if there is significant quality remaining
-                        if self.quality < self.context.quality_target / 5 {
-                            return false;
-                        }
-                    } else {
-                        // don't allow pure progress moves under Innovation, if it wouldn't finish the craft
-                        if self.buffs.innovation > 0
-                            && attrs.quality_efficiency.is_none()
-                            && attrs.progress_efficiency.is_some()
-                        {
-                            return false;
-                        }
-                    }
-                }
-            }
+        // don't allow pure quality moves under Veneration
+        if self.buffs.veneration > 0
+            && attrs.progress_efficiency.is_none()
+            && attrs.quality_efficiency.is_some()
+        {
+            return Some(QualityUnderVeneration);
+        }
 
-            match action {
-                MuscleMemory | Reflect => self.step == 1,
-                TrainedEye => self.step == 1 && !self.context.is_expert,
-                ByregotsBlessing if strict => self.buffs.inner_quiet > 1,
-                ByregotsBlessing => self.buffs.inner_quiet > 0,
-                TrainedFinesse => self.buffs.inner_quiet == 10,
-                TrainedPerfection => self.trained_perfection_active.is_none(),
-                // use of Waste Not should be efficient
-                PrudentSynthesis | PrudentTouch | WasteNot | WasteNotII if strict => {
-                    self.buffs.waste_not == 0 && self.buffs.waste_not_ii == 0
-                }
-                PrudentSynthesis | PrudentTouch => {
-                    self.buffs.waste_not == 0 && self.buffs.waste_not_ii == 0
-                }
-                // don't allow Observe if observing
-                // should also have enough CP to follow up with Advanced Touch (7 + 18 CP)
-                Observe if strict => self.previous_combo_action != Some(Observe) && self.cp >= 25,
-                Observe => self.previous_combo_action != Some(Observe),
-                // don't allow Groundwork if it's downgraded
-                Groundwork | GroundworkTraited => {
-                    let cost = Action::calc_durability_cost(self, attrs.durability_cost.unwrap());
-                    self.durability >= cost
-                }
-                // don't allow Refined Touch without a combo
-                RefinedTouch => self.previous_combo_action == Some(BasicTouch),
-                // don't allow Immaculate Mends that are too inefficient
-                ImmaculateMend if strict => {
-                    self.context.durability_max - self.durability > 45
-                        && self.buffs.manipulation == 0
-                }
-                // don't allow buffs too early
-                MastersMend if strict => self.context.durability_max - self.durability >= 25,
-                Manipulation if strict => {
-                    self.context.use_manipulation && self.buffs.manipulation == 0
-                }
-                Manipulation => self.context.use_manipulation,
-                GreatStrides if strict => self.buffs.great_strides == 0,
-                Veneration | Innovation if strict => {
-                    self.buffs.veneration <= 1 && self.buffs.innovation <= 1
+        // quality buffs are wasted once quality is maxed, e.g. from starting
+        // with enough HQ materials
+        if self.quality >= self.context.quality_target
+            && matches!(action, Innovation | GreatStrides | QuickInnovation)
+        {
+            return Some(QualityBuffAtTarget);
+        }
+
+        // only allow Advanced Touch when Observing
+        if self.previous_combo_action == Some(Observe) && action != AdvancedTouch {
+            return Some(ObserveFollowUp);
+        }
+
+        if let Some(progress_eff) = attrs.progress_efficiency {
+            let progress_increase = Action::calc_progress_increase(self, progress_eff);
+            let would_finish = self.progress + progress_increase >= self.context.progress_target;
+
+            if would_finish {
+                // don't allow finishing the craft if there is significant quality remaining
+                if self.quality < self.context.finishing_quality_floor {
+                    return Some(EarlyFinish);
                 }
-                QuickInnovation if strict => {
-                    self.quick_innovation_available
-                        && self.buffs.innovation == 0
-                        && self.quality > self.context.quality_target / 3
+            } else {
+                // don't allow pure progress moves under Innovation, if it wouldn't finish the craft
+                if self.buffs.innovation > 0
+                    && attrs.quality_efficiency.is_none()
+                    && attrs.progress_efficiency.is_some()
+                {
+                    return Some(ProgressUnderInnovation);
                 }
-                QuickInnovation => self.quick_innovation_available && self.buffs.innovation == 0,
-                // make sure we've exhaustively handled every action; don't use a wildcard here
-                AdvancedTouch
-                | BasicSynthesis
-                | BasicSynthesisTraited
-                | BasicTouch
-                | CarefulSynthesis
-                | CarefulSynthesisTraited
-                | DelicateSynthesis
-                | DelicateSynthesisTraited
-                | GreatStrides
-                | Innovation
-                | ImmaculateMend
-                | MastersMend
-                | PreparatoryTouch
-                | StandardTouch
-                | Veneration
-                | WasteNot
-                | WasteNotII => true,
             }
-        });
-        self.available_moves = available_moves;
+        }
 
-        self
+        let worth_using = match action {
+            ByregotsBlessing => self.buffs.inner_quiet > 1,
+            // use of Waste Not should be efficient
+            WasteNot | WasteNotII => self.buffs.waste_not_remaining() == 0,
+            // should have enough CP to follow up with Advanced Touch (7 + 18 CP)
+            Observe => self.cp >= 25,
+            // don't allow Immaculate Mends that are too inefficient
+            ImmaculateMend => {
+                self.context.durability_max - self.durability > 45 && self.buffs.manipulation == 0
+            }
+            // don't allow buffs too early
+            MastersMend => self.context.durability_max - self.durability >= 25,
+            Manipulation => self.buffs.manipulation == 0,
+            // don't allow Groundwork if it's downgraded
+            Groundwork | GroundworkTraited => {
+                let cost = Action::calc_durability_cost(self, attrs.durability_cost.unwrap());
+                self.durability >= cost
+            }
+            GreatStrides => self.buffs.great_strides == 0,
+            // a heuristic, not a game rule; refreshing either buff is always
+            // allowed when moves aren't strict
+            Veneration | Innovation => self.buffs.veneration <= 1 && self.buffs.innovation <= 1,
+            QuickInnovation => self.quality > self.context.quality_target / 3,
+            // rerolling is only worth it to get out of a Poor condition
+            CarefulObservation => self.condition == Condition::Poor,
+            // make sure we've exhaustively handled every action; don't use a wildcard here
+            AdvancedTouch
+            | BasicSynthesis
+            | BasicSynthesisTraited
+            | BasicTouch
+            | CarefulSynthesis
+            | CarefulSynthesisTraited
+            | DelicateSynthesis
+            | DelicateSynthesisTraited
+            | MuscleMemory
+            | PreparatoryTouch
+            | PrudentSynthesis
+            | PrudentTouch
+            | RefinedTouch
+            | Reflect
+            | StandardTouch
+            | TrainedEye
+            | TrainedFinesse
+            | TrainedPerfection => true,
+        };
+        (!worth_using).then_some(ActionHeuristic)
     }
 
     // interesting lint, but passing by value apparently results in a 2-3% performance regression?
     #[allow(clippy::trivially_copy_pass_by_ref)]
-    fn _execute(&self, &action: &Action) -> Self {
+    fn _execute(&self, action: &Action) -> Self {
         let mut state = Self {
-            step: if action == Action::QuickInnovation {
-                self.step
-            } else {
-                self.step + 1
-            },
             buffs: self.buffs.clone(),
-            action: Some(action),
-            score_sum: 0.0,
-            max_score: 0.0,
-            visits: 0.0,
             available_moves: ActionSet::new(),
             ..*self
         };
+        state._execute_in_place(action);
+        state
+    }
+
+    /// Applies an action directly to this state, leaving `available_moves` for
+    /// the caller to set.
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn _execute_in_place(&mut self, &action: &Action) {
+        let state = self;
+        if !matches!(action, Action::QuickInnovation | Action::CarefulObservation) {
+            state.step += 1;
+        }
+        state.action = Some(action);
+        state.score_sum = 0.0;
+        state.max_score = 0.0;
+        state.visits = 0.0;
+
+        // Careful Observation only rerolls the condition, which the caller does
+        // after every action. It doesn't count as a step, so buffs, combos, and
+        // Manipulation are left untouched.
+        if action == Action::CarefulObservation {
+            state.careful_observations_remaining -= 1;
+            return;
+        }
 
         let Attributes {
             level: _,
@@ -306,25 +865,39 @@ impl<'a> CraftState<'a> {
             quality_efficiency,
             durability_cost,
             cp_cost,
+            combo_from: _,
+            combo_cp_cost: _,
             effect,
+            is_buff: _,
         } = action.attributes();
 
-        if let Some(efficiency) = progress_efficiency {
-            state.progress += Action::calc_progress_increase(&state, efficiency);
+        if let Some(mut efficiency) = progress_efficiency {
+            // Groundwork is only half as efficient without enough durability
+            // for its full cost
+            if matches!(action, Action::Groundwork | Action::GroundworkTraited)
+                && state.durability < Action::calc_durability_cost(state, durability_cost.unwrap())
+            {
+                efficiency /= 2;
+            }
+            state.progress = state
+                .progress
+                .saturating_add(Action::calc_progress_increase(state, efficiency));
             state.buffs.muscle_memory = 0;
         }
 
         if let Some(efficiency) = quality_efficiency {
-            state.quality += Action::calc_quality_increase(&state, efficiency);
+            state.quality = state
+                .quality
+                .saturating_add(Action::calc_quality_increase(state, efficiency));
 
             if state.context.player_job_level >= 11 {
                 state.buffs.inner_quiet = match (state.previous_combo_action, action) {
                     (Some(Action::BasicTouch), Action::RefinedTouch)
                     | (_, Action::Reflect | Action::PreparatoryTouch) => {
-                        cmp::min(state.buffs.inner_quiet + 2, 10)
+                        cmp::min(state.buffs.inner_quiet + 2, MAX_INNER_QUIET)
                     }
                     (_, Action::ByregotsBlessing) => 0,
-                    _ => cmp::min(state.buffs.inner_quiet + 1, 10),
+                    _ => cmp::min(state.buffs.inner_quiet + 1, MAX_INNER_QUIET),
                 };
             }
 
@@ -332,26 +905,27 @@ impl<'a> CraftState<'a> {
         }
 
         if let Some(base_cost) = durability_cost {
-            state.durability -= Action::calc_durability_cost(&state, base_cost);
+            state.durability -= Action::calc_durability_cost(state, base_cost);
 
             if base_cost > 0 && state.trained_perfection_active == Some(true) {
                 state.trained_perfection_active = Some(false);
             }
         }
 
-        if state.buffs.manipulation > 0 && state.durability > 0 {
+        // Manipulation never restores durability on the step it's cast, even when
+        // refreshing an active Manipulation
+        if state.buffs.manipulation > 0 && state.durability > 0 && action != Action::Manipulation {
             state.durability = cmp::min(state.durability + 5, state.context.durability_max);
         }
 
         if let Some(base_cost) = cp_cost {
-            state.cp -= Action::calc_cp_cost(&state, base_cost);
+            state.cp -= Action::calc_cp_cost(state, action, base_cost);
         }
 
-        state.previous_combo_action = match (state.previous_combo_action, action) {
-            (Some(Action::BasicTouch), Action::StandardTouch)
-            | (Some(Action::BasicTouch), Action::RefinedTouch)
-            | (_, Action::BasicTouch | Action::Observe) => Some(action),
-            _ => None,
+        state.previous_combo_action = if action.can_be_followed_up(state.previous_combo_action) {
+            Some(action)
+        } else {
+            None
         };
 
         if action != Action::QuickInnovation {
@@ -360,10 +934,8 @@ impl<'a> CraftState<'a> {
 
         // Always apply buffs last
         if let Some(apply_effect) = effect {
-            apply_effect(&mut state);
+            apply_effect(state);
         }
-
-        state
     }
 
     /// Executes the action against a `CraftState`, and returns a `CraftState` with
@@ -382,11 +954,46 @@ impl<'a> CraftState<'a> {
         state
     }
 
+    /// Executes the action against this `CraftState` in place, leaving it with
+    /// a strict, pruned moveset. This avoids creating a new state for each step
+    /// when intermediate states don't need to be kept, e.g. during playouts.
+    pub fn execute_strict_in_place(&mut self, action: &Action) {
+        self._execute_in_place(action);
+        self.set_available_moves(true);
+    }
+
+    /// Like `execute_strict_in_place`, but leaves the state with all available
+    /// moves
+    pub fn execute_in_place(&mut self, action: &Action) {
+        self._execute_in_place(action);
+        self.set_available_moves(false);
+    }
+
     /// An evaluation of the craft. Returns a value from 0 to 1.
-    #[allow(clippy::cast_precision_loss)]
     pub fn score(&self) -> f32 {
-        fn apply(bonus: f32, value: f32, target: f32) -> f32 {
-            bonus * 1f32.min(value / target)
+        let ScoreComponents {
+            progress,
+            quality,
+            durability,
+            cp,
+            fewer_steps,
+        } = self.score_components();
+        progress + quality + durability + cp + fewer_steps
+    }
+
+    /// The part of `score` that each metric contributes
+    fn score_components(&self) -> ScoreComponents {
+        // values past the target are clamped, so overshooting doesn't earn
+        // any extra score. Values are compared as integers, and divided at
+        // double precision, since large values lose precision as f32.
+        #[allow(clippy::cast_precision_loss)]
+        #[allow(clippy::cast_possible_truncation)]
+        fn apply(bonus: f32, value: i64, target: i64) -> f32 {
+            if value >= target {
+                bonus
+            } else {
+                bonus * (value as f64 / target as f64) as f32
+            }
         }
 
         // bonuses should add up to 1.0
@@ -400,35 +1007,68 @@ impl<'a> CraftState<'a> {
         //    zero without having to rely solely on durability, cp, and step
         //    metrics, which by themselves could provide a bad signal.
         let progress_bonus = 0.20;
-        let quality_bonus = 0.65;
         let durability_bonus = 0.05;
         let cp_bonus = 0.05;
-        let fewer_steps_bonus = 0.05;
-
-        let progress_score = apply(
-            progress_bonus,
-            self.progress as f32,
-            self.context.progress_target as f32,
-        );
+        // whatever's left is split between quality and steps
+        let (quality_bonus, fewer_steps_bonus) = match self.context.quality_weight {
+            Some(weight) => (0.70 * weight, 0.70 * (1.0 - weight)),
+            None => (0.65, 0.05),
+        };
 
-        let quality_score = apply(
-            quality_bonus,
-            self.quality as f32,
-            self.context.quality_target as f32,
-        );
+        ScoreComponents {
+            progress: apply(
+                progress_bonus,
+                self.progress.into(),
+                self.context.progress_target.into(),
+            ),
+            quality: apply(
+                quality_bonus,
+                self.quality.into(),
+                self.context.quality_target.into(),
+            ),
+            durability: apply(
+                durability_bonus,
+                self.durability.into(),
+                self.context.durability_max.into(),
+            ),
+            cp: apply(cp_bonus, self.cp.into(), self.context.cp_max.into()),
+            fewer_steps: fewer_steps_bonus
+                * (1.0_f32 - f32::from(self.step) / f32::from(self.context.step_max)),
+        }
+    }
 
-        let durability_score = apply(
-            durability_bonus,
-            f32::from(self.durability),
-            f32::from(self.context.durability_max),
+    /// A readable breakdown of the craft's score, e.g. "Quality 100% (0.650),
+    /// Progress 100% (0.200), Durability 20/80 (0.013), CP 40/600 (0.003),
+    /// 12/25 steps (0.026)". Crafts that aren't scored on quality only list
+    /// their steps, which make up the whole score.
+    pub fn explain_score(&self) -> String {
+        let steps = format!(
+            "{}/{} steps",
+            self.step.saturating_sub(1),
+            self.context.step_max
         );
+        if !self.context.quality_relevant {
+            return format!("{steps} ({:.3})", self.score_no_quality());
+        }
 
-        let cp_score = apply(cp_bonus, self.cp as f32, self.context.cp_max as f32);
-
-        let fewer_steps_score =
-            fewer_steps_bonus * (1.0_f32 - f32::from(self.step) / f32::from(self.context.step_max));
-
-        progress_score + quality_score + durability_score + cp_score + fewer_steps_score
+        let percent =
+            |value: u32, target: u32| (u64::from(value) * 100 / u64::from(target.max(1))).min(100);
+        let components = self.score_components();
+        format!(
+            "Quality {}% ({:.3}), Progress {}% ({:.3}), Durability {}/{} ({:.3}), \
+             CP {}/{} ({:.3}), {steps} ({:.3})",
+            percent(self.quality, self.context.quality_target),
+            components.quality,
+            percent(self.progress, self.context.progress_target),
+            components.progress,
+            self.durability,
+            self.context.durability_max,
+            components.durability,
+            self.cp,
+            self.context.cp_max,
+            components.cp,
+            components.fewer_steps,
+        )
     }
 
     /// Evaluates the craft based on step count since quality doesn't matter.
@@ -438,14 +1078,74 @@ impl<'a> CraftState<'a> {
         1.0_f32 - f32::from(self.step) / f32::from(self.context.step_max)
     }
 
+    /// How much quality `action` would add from this state at each Inner
+    /// Quiet stack count, from 0 to `MAX_INNER_QUIET`, with the state's other
+    /// buffs and condition as they are. None if the action doesn't add
+    /// quality. Doesn't check whether the action can be used.
+    pub fn quality_by_inner_quiet(&self, action: Action) -> Option<Vec<u32>> {
+        let efficiency = action.attributes().quality_efficiency?;
+        // quality increases depend on the action being used
+        let mut state = self.clone();
+        state.action = Some(action);
+        let increases = (0..=MAX_INNER_QUIET)
+            .map(|inner_quiet| {
+                state.buffs.inner_quiet = inner_quiet;
+                Action::calc_quality_increase(&state, efficiency)
+            })
+            .collect();
+        Some(increases)
+    }
+
+    /// The chance of an HQ result for the current quality, from 0 to 100.
+    pub fn hq_percent(&self) -> u8 {
+        Self::hq_percent_for_quality(self.quality, self.context.quality_target)
+    }
+
+    /// The chance of an HQ result with `quality` out of `quality_max`, from 0
+    /// to 100. The reverse of `quality_for_hq_percent`.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn hq_percent_for_quality(quality: u32, quality_max: u32) -> u8 {
+        if quality_max == 0 {
+            return 0;
+        }
+        let quality_percent = u64::from(quality) * 100 / u64::from(quality_max);
+        HQ_TABLE[quality_percent.min(100) as usize]
+    }
+
+    /// The least quality out of `quality_max` with at least `hq_percent`
+    /// chance of an HQ result. Chances above 100 are treated as 100.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn quality_for_hq_percent(quality_max: u32, hq_percent: u8) -> u32 {
+        let quality_percent = HQ_TABLE
+            .iter()
+            .position(|&chance| chance >= hq_percent.min(100))
+            .unwrap_or(HQ_TABLE.len() - 1) as u64;
+        // rounded up, since `hq_percent` rounds the quality percentage down
+        let quality = (u64::from(quality_max) * quality_percent).div_ceil(100);
+        u32::try_from(quality).unwrap_or(u32::MAX)
+    }
+
+    /// Progress is checked before durability on purpose: like in game, an
+    /// action that finishes the craft succeeds even if it also uses up the
+    /// last of the durability.
     pub fn check_result(&self) -> Option<CraftResult> {
+        // don't reorder these checks, see above
         if self.progress >= self.context.progress_target {
-            let score = if self.context.quality_target > 0 {
+            let score = if self.context.quality_relevant {
                 self.score()
             } else {
                 self.score_no_quality()
             };
-            Some(CraftResult::Finished(score))
+            Some(CraftResult::Finished(CraftMetrics {
+                score,
+                progress: self.progress,
+                quality: self.quality,
+                durability: self.durability,
+                cp: self.cp,
+                steps: self.step - 1,
+                hq_percent: self.hq_percent(),
+                collectability_tier: self.context.collectability_tier(self.quality),
+            }))
         } else if self.durability <= 0 {
             Some(CraftResult::DurabilityFailure)
         } else if self.step >= self.context.step_max {
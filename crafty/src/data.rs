@@ -1,8 +1,170 @@
+#![allow(clippy::unreadable_literal)]
+
 use crate::Recipe;
 
 include!(concat!(env!("OUT_DIR"), "/recipes.rs"));
 
-#[allow(clippy::missing_panics_doc)]
-pub fn recipes(player_job_level: u32) -> &'static [Recipe] {
-    RECIPES.get(&player_job_level).unwrap()
+/// Recipes whose job level (`Recipe::job_level`) is exactly `recipe_job_level`.
+/// Empty if there are no recipes at that level.
+pub fn recipes(recipe_job_level: u32) -> &'static [Recipe] {
+    RECIPES.get(&recipe_job_level).copied().unwrap_or_default()
+}
+
+/// Every distinct recipe variant with the internal `Recipe::recipe_level`
+/// `recipe_level`, e.g. for checking values against the game's data sheets.
+/// Empty if there are none.
+pub fn variants_at_recipe_level(recipe_level: u32) -> Vec<&'static Recipe> {
+    // a recipe level always belongs to a single job level, so its variants
+    // are a range of one group, in their sorted order
+    RECIPE_LEVELS
+        .get(&recipe_level)
+        .map(|&(job_level, start, end)| recipes(job_level)[start..end].iter().collect())
+        .unwrap_or_default()
+}
+
+/// The recipe variant with the internal `Recipe::recipe_level` `recipe_level`
+/// and the given durability, e.g. to tell apart recipes at the same level that
+/// only differ in durability. If several variants match, the one with the
+/// fewest stars, then the least progress, is returned.
+pub fn recipe_variant(recipe_level: u32, durability: i8) -> Option<&'static Recipe> {
+    variants_at_recipe_level(recipe_level)
+        .into_iter()
+        .find(|recipe| recipe.durability == durability)
+}
+
+/// Every distinct recipe variant, sorted by job level, then stars, recipe
+/// level, durability, and progress. Useful for checking every derived value
+/// against other data sources at once.
+pub fn all_recipes() -> Vec<&'static Recipe> {
+    available_recipe_levels()
+        .into_iter()
+        .flat_map(|level| recipes(level).iter())
+        .collect()
+}
+
+/// Every job level that has at least one recipe, in ascending order
+pub fn available_recipe_levels() -> Vec<u32> {
+    let mut levels: Vec<u32> = RECIPES.keys().copied().collect();
+    levels.sort_unstable();
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn available_levels_have_recipes() {
+        let levels = available_recipe_levels();
+        assert!(!levels.is_empty());
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+        for level in levels {
+            assert!(!recipes(level).is_empty());
+        }
+    }
+
+    #[test]
+    fn recipes_match_job_level_exactly() {
+        let recipes_90 = recipes(90);
+        assert!(!recipes_90.is_empty());
+        assert!(recipes_90.iter().all(|recipe| recipe.job_level == 90));
+        assert!(recipes(89).iter().all(|recipe| recipe.job_level == 89));
+    }
+
+    #[test]
+    fn variants_match_recipe_level() {
+        let recipe_level = recipes(90)[0].recipe_level;
+        let variants = variants_at_recipe_level(recipe_level);
+        assert!(!variants.is_empty());
+        assert!(variants
+            .iter()
+            .all(|recipe| recipe.recipe_level == recipe_level && recipe.job_level == 90));
+        assert!(variants_at_recipe_level(100_000).is_empty());
+
+        // the index finds the same variants, in the same order, as a scan
+        let all = all_recipes();
+        let mut recipe_levels: Vec<u32> = all.iter().map(|recipe| recipe.recipe_level).collect();
+        recipe_levels.dedup();
+        for recipe_level in recipe_levels {
+            let scanned: Vec<&Recipe> = all
+                .iter()
+                .copied()
+                .filter(|recipe| recipe.recipe_level == recipe_level)
+                .collect();
+            assert_eq!(variants_at_recipe_level(recipe_level), scanned);
+        }
+    }
+
+    #[test]
+    fn all_recipes_are_distinct_and_sorted() {
+        let all = all_recipes();
+        let group_sizes: usize = available_recipe_levels()
+            .into_iter()
+            .map(|level| recipes(level).len())
+            .sum();
+        assert_eq!(all.len(), group_sizes);
+        assert!(all
+            .windows(2)
+            .all(|pair| pair[0].job_level <= pair[1].job_level));
+
+        let mut fingerprints: Vec<u64> = all.iter().map(|recipe| recipe.fingerprint()).collect();
+        fingerprints.sort_unstable();
+        fingerprints.dedup();
+        assert_eq!(fingerprints.len(), all.len());
+    }
+
+    #[test]
+    fn recipe_variant_matches_level_and_durability() {
+        for recipe in recipes(90) {
+            let variant = recipe_variant(recipe.recipe_level, recipe.durability).unwrap();
+            assert_eq!(
+                (variant.recipe_level, variant.durability),
+                (recipe.recipe_level, recipe.durability)
+            );
+            // the first in sorted order is returned
+            assert!(variant.stars <= recipe.stars);
+        }
+        assert_eq!(recipe_variant(recipes(90)[0].recipe_level, -1), None);
+        assert_eq!(recipe_variant(100_000, 80), None);
+    }
+
+    #[test]
+    fn missing_level_has_no_recipes() {
+        assert!(!available_recipe_levels().contains(&1000));
+        assert!(recipes(1000).is_empty());
+    }
+
+    #[test]
+    fn recipe_fingerprints_are_pinned() {
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        assert_eq!(recipe.fingerprint(), 13_457_741_480_822_359_852);
+        // the item is only a representative of recipes with these attributes
+        let other_item = Recipe {
+            item_id: 1,
+            ..recipe
+        };
+        assert_eq!(other_item.fingerprint(), recipe.fingerprint());
+        let expert = Recipe {
+            is_expert: true,
+            ..recipe
+        };
+        assert_ne!(expert.fingerprint(), recipe.fingerprint());
+    }
 }
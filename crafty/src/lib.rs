@@ -2,20 +2,42 @@
 #![allow(clippy::must_use_candidate)]
 #![allow(clippy::return_self_not_must_use)]
 #![allow(clippy::enum_glob_use)]
+#![allow(clippy::struct_excessive_bools)]
+#![allow(clippy::used_underscore_items)]
 
 mod action;
 mod action_set;
+mod condition;
 mod craft_context;
 mod craft_state;
 pub mod data;
+pub mod macros;
+pub mod materials;
 mod player;
+pub mod rotation_code;
 mod simulator;
 mod tree;
 
-pub use action::Action;
+pub use action::{
+    Action, ActionCategory, ActionMetadata, BuffEffects, Language, CAREFUL_OBSERVATION_USES,
+    GREAT_STRIDES_DURATION, INNOVATION_DURATION, MANIPULATION_DURATION, MAX_INNER_QUIET,
+    MUSCLE_MEMORY_DURATION, VENERATION_DURATION, WASTE_NOT_DURATION, WASTE_NOT_II_DURATION,
+};
 use action_set::ActionSet;
-pub use craft_context::{CraftContext, CraftOptions};
-pub use craft_state::{Buffs, CraftResult, CraftState};
+pub use condition::Condition;
+pub use craft_context::{
+    CraftContext, CraftContextError, CraftOptions, EffectiveStats, MAX_STEPS_RANGE,
+};
+pub use craft_state::{
+    BuffKind, BuffMetadata, Buffs, BuffsDiff, CraftMetrics, CraftResult, CraftState, InGameState,
+    InvalidActionReason, PruningRule, SerializedCraftState, StateDiff,
+};
 pub use player::Player;
 pub use recipe::Recipe;
-pub use simulator::{SearchOptions, Simulator};
+pub use simulator::{
+    ActionSuggestion, ActionUsage, CollectableRotation, Difficulty, FailurePoint,
+    NoCompleteRotation, NodeSnapshot, Preflight, PruningAudit, PruningExclusion, ResourcePoint,
+    RotationEvaluation, RotationWarning, RotationWarningKind, ScoreImprovement, SearchImprovement,
+    SearchOptions, SearchProgress, SearchSnapshot, SearchStats, Simulator, StatChange,
+    StatSensitivity,
+};
@@ -0,0 +1,332 @@
+use crate::{Action, Language};
+use serde::Deserialize;
+use std::fmt;
+use ts_type::{wasm_bindgen, TsType};
+
+/// The most lines a single macro can hold in game
+pub const MACRO_MAX_LINES: usize = 15;
+
+/// Missing fields take their default values.
+#[derive(Debug, Clone, Default, Deserialize, TsType)]
+#[serde(default)]
+pub struct MacroOptions {
+    /// Drops the `<wait>` from the last line of each macro, since nothing
+    /// follows it. Finishes each macro a little faster.
+    pub omit_final_wait: bool,
+    /// The client language that action names are written in
+    pub language: Language,
+    /// Ends each macro with an `/echo` of the steps it covered, e.g.
+    /// `/echo Steps 1-14 done`. The echo takes up one of the macro's lines.
+    pub echo_steps: bool,
+    /// Seconds to wait after a buff. Defaults to 2 if None.
+    pub buff_wait: Option<u8>,
+    /// Seconds to wait after any other action. Defaults to 3 if None.
+    pub action_wait: Option<u8>,
+    /// Waits for specific actions, used instead of `buff_wait` or `action_wait`
+    pub wait_overrides: Vec<(Action, u8)>,
+}
+
+impl MacroOptions {
+    /// Seconds to wait after using `action`
+    pub fn wait_time(&self, action: Action) -> u8 {
+        if let Some(&(_, wait)) = self.wait_overrides.iter().find(|(a, _)| *a == action) {
+            return wait;
+        }
+        if action.is_buff() {
+            self.buff_wait.unwrap_or_else(|| action.macro_wait_time())
+        } else {
+            self.action_wait.unwrap_or_else(|| action.macro_wait_time())
+        }
+    }
+}
+
+/// An estimate of how long it takes to use every action, in seconds, using the
+/// same waits as the macros
+pub fn duration_secs(actions: &[Action], options: &MacroOptions) -> u32 {
+    actions
+        .iter()
+        .map(|&action| u32::from(options.wait_time(action)))
+        .sum()
+}
+
+/// An action whose name isn't known in the requested client language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownActionName {
+    pub action: Action,
+    pub language: Language,
+}
+
+impl fmt::Display for UnknownActionName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the {:?} name of {} isn't known",
+            self.language, self.action
+        )
+    }
+}
+
+impl std::error::Error for UnknownActionName {}
+
+/// Splits a rotation into macros of at most `MACRO_MAX_LINES` lines each
+///
+/// # Errors
+///
+/// Returns `UnknownActionName` if an action's name isn't known in
+/// `options.language`, rather than writing a line the game won't accept.
+pub fn generate_macros(
+    actions: &[Action],
+    options: &MacroOptions,
+) -> Result<Vec<Vec<String>>, UnknownActionName> {
+    let block_size = MACRO_MAX_LINES - usize::from(options.echo_steps);
+    actions
+        .chunks(block_size)
+        .enumerate()
+        .map(|(block_index, block)| {
+            let mut lines = block
+                .iter()
+                .enumerate()
+                .map(|(i, &action)| {
+                    let line =
+                        action
+                            .macro_line(options.language, false)
+                            .ok_or(UnknownActionName {
+                                action,
+                                language: options.language,
+                            })?;
+                    if options.omit_final_wait && i == block.len() - 1 {
+                        Ok(line)
+                    } else {
+                        Ok(format!("{line} <wait.{}>", options.wait_time(action)))
+                    }
+                })
+                .collect::<Result<Vec<String>, _>>()?;
+
+            if options.echo_steps {
+                let first_step = block_index * block_size + 1;
+                let last_step = first_step + block.len() - 1;
+                lines.push(format!("/echo Steps {first_step}-{last_step} done"));
+            }
+            Ok(lines)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Action::*;
+
+    /// Reads an action's label and wait back out of a generated `/ac` line, the
+    /// way the game would. Traited actions share their original's label, so
+    /// labels are compared instead of actions.
+    fn parse_line(line: &str, language: Language) -> Option<(&'static str, Option<u8>)> {
+        let rest = line.strip_prefix("/ac ")?;
+        let (label, wait) = match rest.rsplit_once(" <wait.") {
+            Some((label, wait)) => (label, Some(wait.strip_suffix('>')?.parse().ok()?)),
+            None => (rest, None),
+        };
+        let label = label
+            .strip_prefix('"')
+            .and_then(|label| label.strip_suffix('"'))
+            .unwrap_or(label);
+        Action::ACTIONS
+            .iter()
+            .filter_map(|action| action.label_for(language))
+            .find(|&known| known == label)
+            .map(|known| (known, wait))
+    }
+
+    #[test]
+    fn macros_round_trip_to_the_rotation() {
+        let mut actions = vec![
+            MuscleMemory,
+            Manipulation,
+            Veneration,
+            WasteNotII,
+            GroundworkTraited,
+            Innovation,
+            PreparatoryTouch,
+            Observe,
+            AdvancedTouch,
+            GreatStrides,
+            ByregotsBlessing,
+        ];
+        actions.extend([BasicTouch, StandardTouch].repeat(5));
+        actions.push(BasicSynthesisTraited);
+
+        let option_sets = [
+            MacroOptions::default(),
+            MacroOptions {
+                omit_final_wait: true,
+                echo_steps: true,
+                language: Language::German,
+                ..Default::default()
+            },
+            MacroOptions {
+                buff_wait: Some(1),
+                wait_overrides: vec![(Observe, 4)],
+                language: Language::Japanese,
+                ..Default::default()
+            },
+        ];
+        for options in option_sets {
+            let macros = generate_macros(&actions, &options).unwrap();
+            assert!(macros.iter().all(|lines| lines.len() <= MACRO_MAX_LINES));
+            assert_eq!(macros.len(), 2, "{options:?}");
+
+            let parsed: Vec<(&str, Option<u8>)> = macros
+                .iter()
+                .flat_map(|lines| lines.iter().filter(|line| !line.starts_with("/echo")))
+                .map(|line| parse_line(line, options.language).unwrap())
+                .collect();
+            let block_size = MACRO_MAX_LINES - usize::from(options.echo_steps);
+            let expected: Vec<(&str, Option<u8>)> = actions
+                .iter()
+                .enumerate()
+                .map(|(i, &action)| {
+                    let ends_block = (i + 1) % block_size == 0 || i == actions.len() - 1;
+                    let wait = Some(options.wait_time(action))
+                        .filter(|_| !(options.omit_final_wait && ends_block));
+                    (action.label_for(options.language).unwrap(), wait)
+                })
+                .collect();
+            assert_eq!(parsed, expected, "{options:?}");
+        }
+    }
+
+    #[test]
+    fn splits_into_blocks() {
+        let actions = vec![BasicTouch; 20];
+        let macros = generate_macros(&actions, &MacroOptions::default()).unwrap();
+
+        assert_eq!(macros.len(), 2);
+        assert_eq!(macros[0].len(), MACRO_MAX_LINES);
+        assert_eq!(macros[1].len(), 5);
+        assert!(macros
+            .iter()
+            .flatten()
+            .all(|line| line == "/ac \"Basic Touch\" <wait.3>"));
+    }
+
+    #[test]
+    fn omits_final_wait_of_each_block() {
+        let mut actions = vec![Veneration];
+        actions.extend([BasicSynthesis; 15]);
+        let options = MacroOptions {
+            omit_final_wait: true,
+            ..Default::default()
+        };
+        let macros = generate_macros(&actions, &options).unwrap();
+
+        assert_eq!(macros[0][0], "/ac Veneration <wait.2>");
+        assert_eq!(macros[0][14], "/ac \"Basic Synthesis\"");
+        assert_eq!(macros[1], vec!["/ac \"Basic Synthesis\""]);
+        assert!(generate_macros(&[], &options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn echoes_steps_within_line_limit() {
+        let actions = vec![BasicTouch; 20];
+        let options = MacroOptions {
+            echo_steps: true,
+            ..Default::default()
+        };
+        let macros = generate_macros(&actions, &options).unwrap();
+
+        assert_eq!(macros.len(), 2);
+        assert_eq!(macros[0].len(), MACRO_MAX_LINES);
+        assert_eq!(macros[0][14], "/echo Steps 1-14 done");
+        assert_eq!(macros[1].len(), 7);
+        assert_eq!(macros[1][6], "/echo Steps 15-20 done");
+    }
+
+    #[test]
+    fn uses_custom_waits() {
+        let actions = [Veneration, BasicSynthesis, Observe];
+        let options = MacroOptions {
+            buff_wait: Some(1),
+            action_wait: Some(2),
+            wait_overrides: vec![(Observe, 3)],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            generate_macros(&actions, &options).unwrap()[0],
+            vec![
+                "/ac Veneration <wait.1>",
+                "/ac \"Basic Synthesis\" <wait.2>",
+                "/ac Observe <wait.3>",
+            ]
+        );
+        assert_eq!(duration_secs(&actions, &options), 6);
+        assert_eq!(duration_secs(&actions, &MacroOptions::default()), 8);
+    }
+
+    #[test]
+    fn waits_on_actions_without_durability_cost() {
+        // these don't cost durability, but aren't buffs either
+        for action in [Observe, MastersMend, ImmaculateMend, CarefulObservation] {
+            assert!(!action.is_buff(), "{action:?}");
+            assert_eq!(action.macro_wait_time(), 3, "{action:?}");
+        }
+        for action in [Manipulation, QuickInnovation, TrainedPerfection] {
+            assert!(action.is_buff(), "{action:?}");
+            assert_eq!(action.macro_wait_time(), 2, "{action:?}");
+        }
+    }
+
+    #[test]
+    fn uses_client_language() {
+        let actions = [BasicTouch, GreatStrides, ByregotsBlessing];
+        let macro_in = |language| {
+            let options = MacroOptions {
+                language,
+                ..Default::default()
+            };
+            generate_macros(&actions, &options).unwrap().remove(0)
+        };
+
+        assert_eq!(
+            macro_in(Language::Japanese),
+            vec![
+                "/ac 加工 <wait.3>",
+                "/ac グレートストライド <wait.2>",
+                "/ac ビエルゴの祝福 <wait.3>",
+            ]
+        );
+        assert_eq!(
+            macro_in(Language::German)[1],
+            "/ac \"Große Schritte\" <wait.2>"
+        );
+        assert_eq!(macro_in(Language::French)[0], "/ac Retouche <wait.3>");
+        assert_eq!(
+            macro_in(Language::English)[2],
+            "/ac \"Byregot's Blessing\" <wait.3>"
+        );
+    }
+
+    #[test]
+    fn rejects_names_that_arent_known() {
+        let options = MacroOptions {
+            language: Language::German,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_macros(&[BasicTouch, ImmaculateMend], &options),
+            Err(UnknownActionName {
+                action: ImmaculateMend,
+                language: Language::German,
+            })
+        );
+        assert_eq!(CarefulObservation.label_for(Language::French), None);
+
+        for language in [Language::English, Language::Japanese] {
+            let options = MacroOptions {
+                language,
+                ..Default::default()
+            };
+            assert!(generate_macros(&[BasicTouch, ImmaculateMend], &options).is_ok());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::Recipe;
+use serde::Deserialize;
+use std::cmp;
+use ts_type::{wasm_bindgen, TsType};
+
+/// An ingredient of a recipe that can be used in HQ. Ingredients that can't be
+/// HQ, like crystals, don't affect starting quality and should be left out.
+#[derive(Debug, Clone, Copy, Deserialize, TsType)]
+pub struct Material {
+    pub item_level: u32,
+    /// How many of this material the recipe requires
+    pub amount: u32,
+}
+
+/// The quality a craft starts with when using `hq_amounts[i]` HQ units of
+/// `materials[i]`. Each material contributes in proportion to its item level,
+/// and using only HQ materials gives the recipe's `material_quality_factor`
+/// percent of its max quality.
+pub fn starting_quality(recipe: &Recipe, materials: &[Material], hq_amounts: &[u32]) -> u32 {
+    let item_levels =
+        |amount: u32, material: &Material| u64::from(amount) * u64::from(material.item_level);
+
+    let total: u64 = materials.iter().map(|m| item_levels(m.amount, m)).sum();
+    if total == 0 {
+        return 0;
+    }
+    let hq: u64 = materials
+        .iter()
+        .zip(hq_amounts)
+        .map(|(m, &hq_amount)| item_levels(hq_amount.min(m.amount), m))
+        .sum();
+
+    let quality =
+        u64::from(recipe.quality) * u64::from(recipe.material_quality_factor) * hq / (100 * total);
+    u32::try_from(quality).unwrap_or(u32::MAX)
+}
+
+/// The fewest HQ materials needed to start a craft with at least
+/// `target_quality`, as the number of HQ units to use of each material.
+/// Higher item level materials are used first, since they contribute the most.
+///
+/// Returns None if the target can't be reached even with every material HQ.
+pub fn hq_materials_needed(
+    recipe: &Recipe,
+    materials: &[Material],
+    target_quality: u32,
+) -> Option<Vec<u32>> {
+    let mut hq_amounts = vec![0; materials.len()];
+    if starting_quality(recipe, materials, &hq_amounts) >= target_quality {
+        return Some(hq_amounts);
+    }
+
+    let mut order: Vec<usize> = (0..materials.len()).collect();
+    order.sort_by_key(|&i| cmp::Reverse(materials[i].item_level));
+    for i in order {
+        for _ in 0..materials[i].amount {
+            hq_amounts[i] += 1;
+            if starting_quality(recipe, materials, &hq_amounts) >= target_quality {
+                return Some(hq_amounts);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (Recipe, Vec<Material>) {
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 50,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let materials = vec![
+            Material {
+                item_level: 710,
+                amount: 2,
+            },
+            Material {
+                item_level: 700,
+                amount: 1,
+            },
+            Material {
+                item_level: 690,
+                amount: 3,
+            },
+        ];
+        (recipe, materials)
+    }
+
+    #[test]
+    fn starting_quality_scales_with_item_level() {
+        let (recipe, materials) = setup();
+
+        assert_eq!(starting_quality(&recipe, &materials, &[0, 0, 0]), 0);
+        assert_eq!(starting_quality(&recipe, &materials, &[2, 1, 3]), 6000);
+        // 710 / (710 * 2 + 700 + 690 * 3) of 6000
+        assert_eq!(starting_quality(&recipe, &materials, &[1, 0, 0]), 1016);
+        // amounts past what the recipe requires are ignored
+        assert_eq!(starting_quality(&recipe, &materials, &[5, 1, 3]), 6000);
+    }
+
+    #[test]
+    fn hq_materials_needed_reaches_target() {
+        let (recipe, materials) = setup();
+
+        assert_eq!(
+            hq_materials_needed(&recipe, &materials, 0),
+            Some(vec![0, 0, 0])
+        );
+        assert_eq!(
+            hq_materials_needed(&recipe, &materials, 2000),
+            Some(vec![2, 0, 0])
+        );
+        assert_eq!(
+            hq_materials_needed(&recipe, &materials, 6000),
+            Some(vec![2, 1, 3])
+        );
+
+        let hq_amounts = hq_materials_needed(&recipe, &materials, 3500).unwrap();
+        assert!(starting_quality(&recipe, &materials, &hq_amounts) >= 3500);
+    }
+
+    #[test]
+    fn hq_materials_needed_handles_unreachable_targets() {
+        let (recipe, materials) = setup();
+        assert_eq!(hq_materials_needed(&recipe, &materials, 6001), None);
+        assert_eq!(hq_materials_needed(&recipe, &[], 1), None);
+    }
+}
@@ -1,8 +1,9 @@
-use serde::Deserialize;
+use recipe::Fingerprinter;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use ts_type::{wasm_bindgen, TsType};
 
-#[derive(Deserialize, TsType)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TsType)]
 pub struct Player {
     pub job_level: u32,
     pub craftsmanship: u32,
@@ -19,6 +20,57 @@ impl Player {
             cp,
         }
     }
+
+    pub fn with_job_level(self, job_level: u32) -> Self {
+        Self { job_level, ..self }
+    }
+
+    pub fn with_craftsmanship(self, craftsmanship: u32) -> Self {
+        Self {
+            craftsmanship,
+            ..self
+        }
+    }
+
+    pub fn with_control(self, control: u32) -> Self {
+        Self { control, ..self }
+    }
+
+    pub fn with_cp(self, cp: u32) -> Self {
+        Self { cp, ..self }
+    }
+
+    /// Changes craftsmanship by a percentage, e.g. -10 for 90% of the current value
+    pub fn with_craftsmanship_percent(self, percent: i32) -> Self {
+        self.with_craftsmanship(Self::apply_percent(self.craftsmanship, percent))
+    }
+
+    /// Changes control by a percentage, e.g. 5 for 105% of the current value
+    pub fn with_control_percent(self, percent: i32) -> Self {
+        self.with_control(Self::apply_percent(self.control, percent))
+    }
+
+    /// Changes cp by a percentage, e.g. 5 for 105% of the current value
+    pub fn with_cp_percent(self, percent: i32) -> Self {
+        self.with_cp(Self::apply_percent(self.cp, percent))
+    }
+
+    /// A hash of the player's stats that stays the same across builds, like
+    /// `Recipe::fingerprint`
+    pub fn fingerprint(&self) -> u64 {
+        Fingerprinter::new()
+            .write_u64(self.job_level.into())
+            .write_u64(self.craftsmanship.into())
+            .write_u64(self.control.into())
+            .write_u64(self.cp.into())
+            .finish()
+    }
+
+    /// Rounds down, and never goes below zero
+    fn apply_percent(value: u32, percent: i32) -> u32 {
+        let scaled = i64::from(value) * (100 + i64::from(percent)) / 100;
+        u32::try_from(scaled.max(0)).unwrap_or(u32::MAX)
+    }
 }
 
 impl fmt::Display for Player {
@@ -30,3 +82,45 @@ impl fmt::Display for Player {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Player;
+
+    #[test]
+    fn with_methods_override_single_stats() {
+        let player = Player::new(90, 3290, 3541, 649);
+
+        let player = player.with_control(player.control + 50).with_cp(600);
+        assert_eq!(player, Player::new(90, 3290, 3591, 600));
+        assert_eq!(player.with_job_level(100).job_level, 100);
+        assert_eq!(player.with_craftsmanship(4000).craftsmanship, 4000);
+    }
+
+    #[test]
+    fn percent_changes_round_down() {
+        let player = Player::new(90, 3290, 3541, 649)
+            .with_craftsmanship_percent(-10)
+            .with_control_percent(5)
+            .with_cp_percent(-200);
+        assert_eq!(player, Player::new(90, 2961, 3718, 0));
+    }
+
+    #[test]
+    fn players_round_trip() {
+        let player = Player::new(90, 3290, 3541, 649);
+        let json = serde_json::to_string(&player).unwrap();
+        assert_eq!(
+            json,
+            r#"{"job_level":90,"craftsmanship":3290,"control":3541,"cp":649}"#
+        );
+        assert_eq!(serde_json::from_str::<Player>(&json).unwrap(), player);
+    }
+
+    #[test]
+    fn fingerprints_are_pinned() {
+        let player = Player::new(90, 3290, 3541, 649);
+        assert_eq!(player.fingerprint(), 269_608_458_731_433_804);
+        assert_ne!(player.with_cp(650).fingerprint(), player.fingerprint());
+    }
+}
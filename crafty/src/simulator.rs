@@ -1,6 +1,14 @@
-use crate::{tree::Arena, Action, CraftContext, CraftResult, CraftState};
+use crate::{
+    tree::Arena, Action, ActionSet, Condition, CraftContext, CraftOptions, CraftResult, CraftState,
+    InvalidActionReason, Player, PruningRule, Recipe,
+};
+#[cfg(feature = "parallel")]
+use enum_indexing::EnumIndexing;
 use rand::{rngs::SmallRng, Rng, SeedableRng};
-use serde::Deserialize;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{cmp, fmt};
 use ts_type::{wasm_bindgen, TsType};
 
 #[derive(Clone, Copy, Deserialize, TsType)]
@@ -15,79 +23,620 @@ pub struct SearchOptions {
     /// The higher the weight, the more a node's potential max score is valued
     /// over its average score. A weight of 1.0 means only max scores will be used;
     /// 0.0 means only average scores will be used.
+    ///
+    /// Defaults to 0.1 if None, or 0.2 for `search_stepwise`.
     pub max_score_weighting_constant: Option<f32>,
     /// Higher values prioritize exploring less promising nodes.
+    ///
+    /// Defaults to 1.5 if None, or 1.0 for `search_stepwise`.
     pub exploration_constant: Option<f32>,
+    /// Numerical seed used to roll conditions during playouts. Derived from
+    /// `rng_seed` if None
+    pub condition_seed: Option<u64>,
+    /// During a stepwise search, the number of best first actions to compare
+    /// before committing to one. Each candidate gets a confirmatory search with
+    /// a quarter of `iterations`. Only the single best action is used if None.
+    pub stepwise_candidates: Option<u32>,
+    /// Only accept rotations that finish the craft. Every finished playout that
+    /// improves on the best score is stored, so the best one can be returned
+    /// even if it's far from HQ. Check the result with `check_solution`.
+    #[serde(default)]
+    pub require_complete: bool,
+    /// A hard limit on the number of nodes kept in the tree. Once it's
+    /// exceeded, the lowest scoring subtrees are removed to make room and the
+    /// search keeps going. Unlimited if None.
+    pub max_nodes: Option<usize>,
+    /// Search with every usable action instead of the strict, pruned moveset.
+    /// Much slower, but can find rotations that the pruning rules rule out,
+    /// e.g. as a fallback when a strict search can't finish the craft.
+    #[serde(default)]
+    pub permissive: bool,
+    /// Pick nodes to explore with integer arithmetic instead of `f32`, so a
+    /// fixed `rng_seed` gives the same rotation on every platform, e.g.
+    /// natively and in wasm. Scores and their sums stay `f32`, which is
+    /// already reproducible, see `backpropagate`; the default search also
+    /// uses `ln`, which may round differently between platforms. A little
+    /// slower.
+    #[serde(default)]
+    pub fixed_point_scores: bool,
 }
 
+/// A search with `SearchOptions::require_complete` set didn't find a rotation
+/// that finishes the craft
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoCompleteRotation;
+
+impl fmt::Display for NoCompleteRotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no rotation that completes the craft was found")
+    }
+}
+
+impl std::error::Error for NoCompleteRotation {}
+
 impl Default for SearchOptions {
     fn default() -> Self {
         Self {
             iterations: 10_000,
             rng_seed: Some(SmallRng::from_entropy().gen()),
             score_storage_threshold: Some(1.0),
-            max_score_weighting_constant: Some(0.1),
-            exploration_constant: Some(1.5),
+            max_score_weighting_constant: None,
+            exploration_constant: None,
+            condition_seed: None,
+            stepwise_candidates: None,
+            require_complete: false,
+            max_nodes: None,
+            permissive: false,
+            fixed_point_scores: false,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Used when the search constants aren't set, except by `search_stepwise`
+    const MAX_SCORE_WEIGHTING_CONSTANT: f32 = 0.1;
+    const EXPLORATION_CONSTANT: f32 = 1.5;
+
+    /// `search_stepwise` commits to its best action after every search, so it
+    /// does better favoring max scores and exploring less than a single search
+    /// that has to find the whole rotation. Over seeds 0-15 of a 20k iteration
+    /// stepwise search on a 580 recipe, these reached 6618 quality on average,
+    /// against 6391 with the oneshot defaults.
+    const STEPWISE_MAX_SCORE_WEIGHTING_CONSTANT: f32 = 0.2;
+    const STEPWISE_EXPLORATION_CONSTANT: f32 = 1.0;
+
+    /// Errors if `require_complete` is set and `state`, the end of a searched
+    /// rotation, doesn't finish the craft.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NoCompleteRotation` if the rotation fails or is unfinished.
+    pub fn check_solution(&self, state: &CraftState) -> Result<(), NoCompleteRotation> {
+        let finished = matches!(state.check_result(), Some(CraftResult::Finished(_)));
+        if self.require_complete && !finished {
+            return Err(NoCompleteRotation);
+        }
+        Ok(())
+    }
+
+    /// Fills in search constants that weren't set with the stepwise defaults
+    fn with_stepwise_defaults(self) -> Self {
+        Self {
+            max_score_weighting_constant: self
+                .max_score_weighting_constant
+                .or(Some(Self::STEPWISE_MAX_SCORE_WEIGHTING_CONSTANT)),
+            exploration_constant: self
+                .exploration_constant
+                .or(Some(Self::STEPWISE_EXPLORATION_CONSTANT)),
+            ..self
+        }
+    }
+}
+
+/// The distribution of outcomes from running a rotation under random conditions.
+#[derive(Debug, Clone, Serialize, TsType)]
+pub struct RotationEvaluation {
+    pub samples: u32,
+    /// Fraction of samples that reached 100% progress
+    pub finish_rate: f32,
+    pub mean_quality: f32,
+    pub quality_p10: u32,
+    pub quality_p50: u32,
+    pub quality_p90: u32,
+    /// Average HQ chance across all samples. Unfinished crafts count as 0%.
+    pub mean_hq_percent: f32,
+    /// Fraction of samples that finished with at least the context's
+    /// `quality_target`
+    pub target_rate: f32,
+}
+
+/// The cheapest rotation found for a collectable, from
+/// `Simulator::search_collectable`
+#[derive(Debug, Clone, Serialize, TsType)]
+pub struct CollectableRotation {
+    pub actions: Vec<Action>,
+    pub cp_spent: u32,
+    /// The collectability the rotation reaches with Normal conditions
+    pub collectability: u32,
+    /// Fraction of evaluated samples, under random conditions, that reached
+    /// the target collectability
+    pub success_rate: f32,
+}
+
+/// Reported periodically by `Simulator::search_more_with_progress`.
+#[derive(Debug, Clone, Copy, Serialize, TsType)]
+pub struct SearchProgress {
+    /// Iterations completed so far in this call
+    pub iterations: u32,
+    /// Best score found anywhere in the tree
+    pub max_score: f32,
+    /// Number of nodes in the tree
+    pub nodes: usize,
+}
+
+/// A better rotation than any reported before, from `Simulator::search_streaming`.
+#[derive(Debug, Clone, Serialize, TsType)]
+pub struct SearchImprovement<'a> {
+    /// Iterations completed when the rotation was found
+    pub iterations: u32,
+    /// The whole rotation, including the action history searched from
+    pub actions: Vec<Action>,
+    pub state: CraftState<'a>,
+}
+
+/// The resources left after an action, from `Simulator::resource_timeline`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub struct ResourcePoint {
+    pub progress: u32,
+    pub quality: u32,
+    pub durability: i8,
+    pub cp: u32,
+}
+
+/// The best quality found with one stat raised, from `Simulator::sensitivity`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TsType)]
+pub struct StatChange {
+    /// None if no rotation finishing the craft was found
+    pub quality: Option<u32>,
+    pub hq_percent: Option<u8>,
+    /// The change in quality for each point of the stat added, compared to the
+    /// player's current stats. None if either craft couldn't be finished.
+    pub quality_per_point: Option<f32>,
+}
+
+/// How much more quality each stat is worth, from `Simulator::sensitivity`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TsType)]
+pub struct StatSensitivity {
+    /// How much each stat was raised by
+    pub stat_delta: u32,
+    /// The best quality found with the player's current stats, or None if no
+    /// rotation finishing the craft was found
+    pub quality: Option<u32>,
+    pub hq_percent: Option<u8>,
+    pub craftsmanship: StatChange,
+    pub control: StatChange,
+    pub cp: StatChange,
+}
+
+/// What can be told about a craft before searching it, from
+/// `Simulator::preflight`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub struct Preflight {
+    /// Whether the player's level is at least the recipe's
+    pub meets_level_requirement: bool,
+    /// Whether the player's craftsmanship is at least the recipe's
+    /// `required_craftsmanship`
+    pub meets_craftsmanship_requirement: bool,
+    /// Whether the player's control is at least the recipe's
+    /// `required_control`
+    pub meets_control_requirement: bool,
+    /// Whether a rotation finishing the craft was found, ignoring quality
+    pub can_complete: bool,
+    /// Whether a rotation reaching the recipe's max quality was found
+    pub can_reach_max_quality: bool,
+    /// Whether the recipe's progress and quality modifiers apply, which they
+    /// do unless the player is above the recipe's level
+    pub recipe_modifiers_apply: bool,
+    /// Whether the player can use specialist actions like Careful Observation
+    pub specialist_actions_available: bool,
+}
+
+/// Where a rotation failed, from `Simulator::simulate_until_failure`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub struct FailurePoint {
+    /// Index of the action that failed the craft: either the one that couldn't
+    /// be used, or the one that used up the last durability or step
+    pub action_index: usize,
+    /// Why the action couldn't be used, if that's how the craft failed
+    pub invalid_reason: Option<InvalidActionReason>,
+}
+
+/// Why an action in a rotation is likely wasted, from `Simulator::lint_rotation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TsType)]
+pub enum RotationWarningKind {
+    /// A buff that wears off before any action benefits from it
+    UnusedBuff,
+    /// Observe that isn't followed by Advanced Touch
+    ObserveNotFollowedUp,
+    /// The craft finished or failed before this action
+    CraftAlreadyOver,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TsType)]
+pub struct RotationWarning {
+    pub action_index: usize,
+    pub kind: RotationWarningKind,
+    pub message: String,
+}
+
+/// How hard a rotation is to use by hand, from `Simulator::rotation_difficulty`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, TsType)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+/// A playout that scored higher than every playout before it.
+#[derive(Debug, Clone, Copy, Serialize, TsType)]
+pub struct ScoreImprovement {
+    /// Iterations run when the playout happened, counting from 1
+    pub iteration: u32,
+    pub score: f32,
+}
+
+/// How much the search explored one action, across every node it was used in.
+#[derive(Debug, Clone, Copy, Serialize, TsType)]
+pub struct ActionUsage {
+    pub action: Action,
+    /// Number of nodes in the tree that used this action
+    pub nodes: usize,
+    /// Playouts that went through those nodes
+    pub visits: f32,
+    /// Average score of those playouts
+    pub average_score: f32,
+}
+
+/// Telemetry for a simulator's search, from `Simulator::stats`.
+#[derive(Debug, Clone, Serialize, TsType)]
+pub struct SearchStats {
+    /// The seed used to pick moves
+    pub rng_seed: u32,
+    pub iterations: u32,
+    /// Number of nodes in the tree
+    pub nodes: usize,
+    /// Iterations that selected a node without any moves left to expand
+    pub dead_ends_selected: u64,
+    /// Nodes removed to stay under `SearchOptions::max_nodes`. Zero if the
+    /// tree was never pruned.
+    pub pruned_nodes: usize,
+    /// Every improvement to the best score, in the order they were found
+    pub best_scores: Vec<ScoreImprovement>,
+    /// Actions the search has explored, most visited first
+    pub action_usage: Vec<ActionUsage>,
+}
+
+/// How often a pruning rule kept an action out of the strict moveset when an
+/// unpruned search used it, from `Simulator::pruning_audit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruningExclusion {
+    pub rule: PruningRule,
+    pub action: Action,
+    /// Number of steps, across every audited rotation, where the action was used
+    /// but excluded
+    pub count: u32,
+}
+
+/// The results of `Simulator::pruning_audit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PruningAudit {
+    pub searches: u32,
+    /// Unpruned searches whose best rotation finished the craft. Only those
+    /// rotations are audited.
+    pub finished: u32,
+    /// Most frequent first
+    pub exclusions: Vec<PruningExclusion>,
+}
+
+impl fmt::Display for PruningAudit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} unpruned searches finished",
+            self.finished, self.searches
+        )?;
+        for exclusion in &self.exclusions {
+            writeln!(
+                f,
+                "{:>5}  {:?} excluded {}",
+                exclusion.count,
+                exclusion.rule,
+                exclusion.action.name()
+            )?;
         }
+        Ok(())
     }
 }
 
+/// A node in a `SearchSnapshot`. Only the action and search statistics are
+/// kept; the craft state is rebuilt by replaying actions from the root.
+#[derive(Debug, Clone, Serialize, Deserialize, TsType)]
+pub struct NodeSnapshot {
+    pub parent: Option<usize>,
+    pub action: Option<Action>,
+    /// The condition after `action` was used. Only the root's can be other
+    /// than Normal, since the tree doesn't keep the conditions rolled during
+    /// playouts.
+    pub condition: Condition,
+    pub score_sum: f32,
+    pub max_score: f32,
+    pub visits: f32,
+    /// Moves that haven't been expanded from this node yet
+    pub available_moves: Vec<Action>,
+}
+
+/// A partially searched tree that can be saved and resumed later. Doesn't
+/// include the `CraftContext`, which has to be provided again on restore.
+#[derive(Debug, Clone, Serialize, Deserialize, TsType)]
+pub struct SearchSnapshot {
+    pub nodes: Vec<NodeSnapshot>,
+    pub score_storage_threshold: f32,
+    pub max_score_weighting_constant: f32,
+    pub exploration_constant: f32,
+    /// Whether the tree was searched with `SearchOptions::permissive`
+    #[serde(default)]
+    pub permissive: bool,
+}
+
+/// The result of `Simulator::suggest_next_action`.
+#[derive(Debug, Clone, Serialize, TsType)]
+pub struct ActionSuggestion {
+    pub action: Action,
+    /// Fraction of the search's visits that went to `action`, from 0 to 1
+    pub confidence: f32,
+    /// The searched tree, which can be passed to the next call to avoid
+    /// searching from scratch
+    pub snapshot: SearchSnapshot,
+}
+
 #[derive(Debug)]
 pub struct Simulator<'a> {
     tree: Arena<CraftState<'a>>,
 
     // from SearchOptions
     iterations: u32,
+    rng_seed: u32,
     rng: SmallRng,
+    /// Rolls conditions during playouts. Kept separate from `rng` so that
+    /// condition rolls don't change which moves get explored.
+    condition_rng: SmallRng,
     score_storage_threshold: f32,
     max_score_weighting_constant: f32,
     exploration_constant: f32,
+    max_nodes: Option<usize>,
+    /// Whether nodes are expanded with the strict, pruned moveset, from
+    /// `SearchOptions::permissive`
+    strict: bool,
+    fixed_point_scores: bool,
 
     /// Amount of "dead ends" encountered. This means a node was selected, but
     /// there weren't any available moves.
     dead_ends_selected: u64,
+    /// Iterations run since the tree was last reset
+    iterations_run: u32,
+    /// Nodes removed by pruning since the tree was last reset
+    pruned_nodes: usize,
+    best_scores: Vec<ScoreImprovement>,
+
+    /// Scratch buffer for recording the actions taken during a playout. Reused
+    /// across iterations to avoid reallocating on every rollout.
+    rollout_history: Vec<Action>,
 }
 
 impl<'a> Simulator<'a> {
-    fn from_state(state: CraftState<'a>, options: SearchOptions) -> Self {
+    fn from_state(mut state: CraftState<'a>, options: SearchOptions) -> Self {
         let defaults = SearchOptions::default();
         let rng_seed = options.rng_seed.or(defaults.rng_seed).unwrap();
+        // a complete rotation has to be stored to be returned, however low it scores
+        let score_storage_threshold = if options.require_complete {
+            0.0
+        } else {
+            options
+                .score_storage_threshold
+                .or(defaults.score_storage_threshold)
+                .unwrap()
+        };
+        let mut capacity = Self::arena_capacity(
+            options.iterations,
+            score_storage_threshold,
+            state.context.step_max,
+        );
+        if let Some(max_nodes) = options.max_nodes {
+            // an iteration can add a whole playout before the tree is pruned
+            capacity = capacity.min(max_nodes + usize::from(state.context.step_max) + 1);
+        }
+
+        // callers pass in strict states, so a permissive search gets its root's
+        // moves back here
+        if options.permissive {
+            state.set_available_moves(false);
+        }
 
         Self {
-            tree: Arena::new(state),
+            tree: Arena::with_capacity(state, capacity),
             iterations: options.iterations,
             dead_ends_selected: 0,
+            iterations_run: 0,
+            pruned_nodes: 0,
+            best_scores: vec![],
+            rollout_history: vec![],
+            rng_seed,
             rng: SmallRng::seed_from_u64(u64::from(rng_seed)),
-            score_storage_threshold: options
-                .score_storage_threshold
-                .or(defaults.score_storage_threshold)
-                .unwrap(),
+            condition_rng: SmallRng::seed_from_u64(
+                options.condition_seed.unwrap_or(u64::from(rng_seed)),
+            ),
+            score_storage_threshold,
             max_score_weighting_constant: options
                 .max_score_weighting_constant
-                .or(defaults.max_score_weighting_constant)
-                .unwrap(),
+                .unwrap_or(SearchOptions::MAX_SCORE_WEIGHTING_CONSTANT),
             exploration_constant: options
                 .exploration_constant
-                .or(defaults.exploration_constant)
-                .unwrap(),
+                .unwrap_or(SearchOptions::EXPLORATION_CONSTANT),
+            max_nodes: options.max_nodes,
+            strict: !options.permissive,
+            fixed_point_scores: options.fixed_point_scores,
+        }
+    }
+
+    /// Estimates how many nodes a search will create so the arena can be
+    /// allocated up front. Every iteration expands one node, and playouts that
+    /// reach the storage threshold store up to `step_max` more. Thresholds
+    /// below 1.0 store many more playouts, so more room is reserved for them.
+    fn arena_capacity(iterations: u32, score_storage_threshold: f32, step_max: u8) -> usize {
+        // avoid reserving an unreasonable amount of memory for huge searches
+        const MAX_CAPACITY: usize = 1 << 20;
+
+        let iterations = iterations as usize;
+        let stored_nodes = if score_storage_threshold < 1.0 {
+            iterations / 4 * usize::from(step_max)
+        } else {
+            usize::from(step_max)
+        };
+        (iterations + stored_nodes + 1).min(MAX_CAPACITY)
+    }
+
+    /// Discards the current tree and starts searching from a new state, keeping
+    /// the arena's allocation for reuse.
+    fn reset(&mut self, mut state: CraftState<'a>) {
+        if !self.strict {
+            state.set_available_moves(false);
         }
+        self.tree.clear(state);
+        self.dead_ends_selected = 0;
+        self.iterations_run = 0;
+        self.pruned_nodes = 0;
+        self.best_scores.clear();
     }
 
     fn from_context(context: &'a CraftContext, options: SearchOptions) -> Self {
         Self::from_state(CraftState::new(context), options)
     }
 
+    /// A simulator that only replays a rotation. Its arena only has room for
+    /// one craft's worth of states, and it doesn't need a random seed.
+    fn for_replay(context: &'a CraftContext) -> Self {
+        let options = SearchOptions {
+            iterations: 0,
+            rng_seed: Some(0),
+            score_storage_threshold: Some(1.0),
+            ..Default::default()
+        };
+        Self::from_context(context, options)
+    }
+
+    /// Creates a simulator that keeps its search tree between calls, so that a
+    /// search can be run in increments with `search_more`. `options.iterations`
+    /// is unused here; each call to `search_more` specifies its own.
+    pub fn new(context: &'a CraftContext, options: SearchOptions) -> Self {
+        Self::from_context(context, options)
+    }
+
+    /// Captures the current search tree, so that it can be resumed later with
+    /// `restore`. Intended for simulators created with `Simulator::new`.
+    pub fn snapshot(&self) -> SearchSnapshot {
+        let nodes = self
+            .tree
+            .nodes
+            .iter()
+            .map(|node| NodeSnapshot {
+                parent: node.parent,
+                action: node.state.action,
+                condition: node.state.condition,
+                score_sum: node.state.score_sum,
+                max_score: node.state.max_score,
+                visits: node.state.visits,
+                available_moves: node.state.available_moves.to_vec(),
+            })
+            .collect();
+
+        SearchSnapshot {
+            nodes,
+            score_storage_threshold: self.score_storage_threshold,
+            max_score_weighting_constant: self.max_score_weighting_constant,
+            exploration_constant: self.exploration_constant,
+            permissive: !self.strict,
+        }
+    }
+
+    /// Rebuilds a simulator from a snapshot taken with `snapshot`, replaying
+    /// each node's action to recover its craft state with the same moveset the
+    /// search used. RNG state isn't part of the snapshot, so further searching
+    /// is seeded randomly.
+    ///
+    /// Returns None if the snapshot's nodes don't form a valid tree, or if an
+    /// action can't be used where it's played, e.g. in a stale snapshot.
+    pub fn restore(context: &'a CraftContext, snapshot: &SearchSnapshot) -> Option<Self> {
+        let (root, nodes) = snapshot.nodes.split_first()?;
+        if root.parent.is_some() {
+            return None;
+        }
+
+        let mut sim = Self::from_context(
+            context,
+            SearchOptions {
+                score_storage_threshold: Some(snapshot.score_storage_threshold),
+                max_score_weighting_constant: Some(snapshot.max_score_weighting_constant),
+                exploration_constant: Some(snapshot.exploration_constant),
+                permissive: snapshot.permissive,
+                ..Default::default()
+            },
+        );
+
+        let restore_stats = |state: &mut CraftState, node: &NodeSnapshot| {
+            state.condition = node.condition;
+            state.score_sum = node.score_sum;
+            state.max_score = node.max_score;
+            state.visits = node.visits;
+            state.available_moves = ActionSet::from_vec(&node.available_moves);
+        };
+        restore_stats(&mut sim.tree.get_mut(0).state, root);
+
+        for (i, node) in nodes.iter().enumerate() {
+            // parents always come before their children
+            let parent_index = node.parent.filter(|&parent| parent <= i)?;
+            let action = node.action?;
+            let parent_state = &sim.tree.get(parent_index).state;
+            parent_state.can_use(action).ok()?;
+            let mut state = if sim.strict {
+                parent_state.execute_strict(&action)
+            } else {
+                parent_state.execute(&action)
+            };
+            restore_stats(&mut state, node);
+            sim.tree.insert(parent_index, state);
+        }
+
+        Some(sim)
+    }
+
     /// Executes a series of actions with most game-valid moves available. Will
     /// return early with `CraftResult::InvalidActionFailure` if an illegal move
     /// is chosen.
+    ///
+    /// `conditions[i]`, if present, is the condition in effect when `actions[i]`
+    /// is used.
     fn execute_actions(
         &mut self,
         start_index: usize,
         actions: Vec<Action>,
+        conditions: &[Condition],
     ) -> (usize, Option<CraftResult>) {
         let mut current_index = start_index;
-        for action in actions {
+        for (i, action) in actions.into_iter().enumerate() {
             let current_state = &mut self.tree.get_mut(current_index).state;
+            if let Some(&condition) = conditions.get(i) {
+                current_state.condition = condition;
+            }
 
             if let Some(result) = current_state.check_result() {
                 return (current_index, Some(result));
@@ -110,14 +659,15 @@ impl<'a> Simulator<'a> {
         (current_index, current_state.check_result())
     }
 
-    /// Executes a series of actions with strict move pruning enabled.
+    /// Executes a series of actions with strict move pruning enabled. Like
+    /// every node in the tree, the new nodes are left with Normal conditions.
     fn execute_actions_strict(
         &mut self,
         start_index: usize,
-        actions: Vec<Action>,
+        actions: &[Action],
     ) -> (usize, Option<CraftResult>) {
         let mut current_index = start_index;
-        for action in actions {
+        for &action in actions {
             let current_state = &mut self.tree.get_mut(current_index).state;
 
             if let Some(result) = current_state.check_result() {
@@ -131,7 +681,12 @@ impl<'a> Simulator<'a> {
                 return (current_index, Some(CraftResult::InvalidActionFailure));
             }
 
-            let next_state = current_state.execute_strict(&action);
+            let mut next_state = if self.strict {
+                current_state.execute_strict(&action)
+            } else {
+                current_state.execute(&action)
+            };
+            next_state.condition = Condition::Normal;
             let next_index = self.tree.insert(current_index, next_state);
 
             current_index = next_index;
@@ -148,6 +703,10 @@ impl<'a> Simulator<'a> {
         let c = self.exploration_constant;
 
         let visits = state.visits;
+        // pruning can take away every playout a node was credited with
+        if visits == 0.0 {
+            return f32::INFINITY;
+        }
         let average_score = state.score_sum / visits;
 
         let exploitation = (1.0 - w) * average_score + w * state.max_score;
@@ -156,6 +715,30 @@ impl<'a> Simulator<'a> {
         exploitation + exploration
     }
 
+    /// `ucb1` in fixed-point, scaled by `FIXED_POINT_SCALE`, for
+    /// `SearchOptions::fixed_point_scores`
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    fn ucb1_fixed(&self, state: &CraftState, parent_state: &CraftState) -> u64 {
+        let fixed = |value: f32| (f64::from(value) * FIXED_POINT_SCALE as f64) as u64;
+        let w = fixed(self.max_score_weighting_constant);
+        let c = fixed(self.exploration_constant);
+
+        // visits are whole numbers, so they convert exactly
+        let visits = (state.visits as u64).max(1);
+        let average_score = fixed(state.score_sum) / visits;
+
+        let exploitation = (FIXED_POINT_SCALE.saturating_sub(w) * average_score
+            + w * fixed(state.max_score))
+            / FIXED_POINT_SCALE;
+        let parent_visits = (parent_state.visits as u64).max(1);
+        // scaled by FIXED_POINT_SCALE twice, so the root is scaled once
+        let exploration = (c * ln_fixed(parent_visits) / visits).isqrt();
+
+        exploitation + exploration
+    }
+
     /// Traverses the tree to find a good candidate node to expand.
     fn select(&self, current_index: usize) -> usize {
         let mut selected_index = current_index;
@@ -169,15 +752,19 @@ impl<'a> Simulator<'a> {
             }
 
             // select the node with the highest score
-            selected_index = *selected_node
-                .children
-                .iter()
-                .max_by(|&a, &b| {
+            let children = selected_node.children.iter();
+            selected_index = *if self.fixed_point_scores {
+                children.max_by_key(|&a| {
+                    self.ucb1_fixed(&self.tree.get(*a).state, &selected_node.state)
+                })
+            } else {
+                children.max_by(|&a, &b| {
                     let a_score = self.ucb1(&self.tree.get(*a).state, &selected_node.state);
                     let b_score = self.ucb1(&self.tree.get(*b).state, &selected_node.state);
                     a_score.partial_cmp(&b_score).unwrap()
                 })
-                .unwrap();
+            }
+            .unwrap();
         }
         selected_index
     }
@@ -186,44 +773,76 @@ impl<'a> Simulator<'a> {
     /// terminal state is encountered. To decrease memory usage, the tree should
     /// only expand by one node per iteration unless we hit a good score, in
     /// which case the the whole path should be stored.
+    ///
+    /// Conditions are sampled during the playout, so a node's average score
+    /// reflects the recipe's condition distribution rather than assuming every
+    /// step is Normal. The rolls are fresh on every visit and never stored:
+    /// nodes in the tree, including stored paths, always have Normal
+    /// conditions, so the tree's states are the ones a rotation reaches
+    /// without any lucky rolls. Recipes without variable conditions are
+    /// unaffected.
     fn expand_and_rollout(&mut self, initial_index: usize) -> (usize, CraftResult) {
         // expand once
+        let strict = self.strict;
         let initial_state = &mut self.tree.get_mut(initial_index).state;
         if let Some(result) = initial_state.check_result() {
             return (initial_index, result);
         }
         let random_action = initial_state.available_moves.pick(&mut self.rng);
-        let expanded_state = initial_state.execute_strict(&random_action);
+        let mut expanded_state = if strict {
+            initial_state.execute_strict(&random_action)
+        } else {
+            initial_state.execute(&random_action)
+        };
+        expanded_state.condition = Condition::Normal;
+        let conditions_flag = expanded_state.context.conditions_flag;
         let expanded_index = self.tree.insert(initial_index, expanded_state);
 
-        // playout to a terminal state
+        // playout to a terminal state, mutating a single scratch state in place
         let mut current_state = self.tree.get(expanded_index).state.clone();
-        let mut action_history: Vec<Action> = vec![];
+        let mut action_history = std::mem::take(&mut self.rollout_history);
+        action_history.clear();
         let result = loop {
             if let Some(result) = current_state.check_result() {
                 break result;
             }
             let random_action = current_state.available_moves.sample(&mut self.rng);
+            if strict {
+                current_state.execute_strict_in_place(&random_action);
+            } else {
+                current_state.execute_in_place(&random_action);
+            }
+            current_state.condition = current_state
+                .condition
+                .roll_after(conditions_flag, &mut self.condition_rng);
             action_history.push(random_action);
-            current_state = current_state.execute_strict(&random_action);
         };
 
         // store the result if a max score was reached
-        match result {
-            CraftResult::Finished(score)
-                if score >= self.score_storage_threshold
-                    && score >= self.tree.nodes[0].state.max_score =>
+        let end_index = match result {
+            CraftResult::Finished(metrics)
+                if metrics.score >= self.score_storage_threshold
+                    && metrics.score >= self.tree.nodes[0].state.max_score =>
             {
                 let (terminal_index, _) =
-                    self.execute_actions_strict(expanded_index, action_history);
-                (terminal_index, result)
+                    self.execute_actions_strict(expanded_index, &action_history);
+                terminal_index
             }
-            _ => (expanded_index, result),
-        }
+            _ => expanded_index,
+        };
+        self.rollout_history = action_history;
+
+        (end_index, result)
     }
 
     /// From a starting node, follow parent nodes back to the root node, updating
     /// statistics for each node along the way.
+    ///
+    /// Score sums are `f32`, but they come out the same on every platform:
+    /// `+`, `-`, and `max` are exactly rounded IEEE 754 operations that Rust
+    /// never fuses or reorders, and each node's scores are added in the order
+    /// its tree's seeded iterations produce them. Visits are whole numbers,
+    /// which `f32` holds exactly up to 2^24.
     fn backpropagate(&mut self, start_index: usize, target_index: usize, score: f32) {
         let mut current_index = start_index;
         loop {
@@ -241,9 +860,65 @@ impl<'a> Simulator<'a> {
         }
     }
 
-    /// The starting point for one round of MCTS.
+    /// The starting point for one round of MCTS. Logs the search's settings
+    /// and outcome at the debug level, which costs nothing unless a logger
+    /// is installed.
     fn search(&mut self, start_index: usize) -> &mut Self {
-        for _ in 0..self.iterations {
+        log::debug!(
+            "searching {} iterations with seed {}",
+            self.iterations,
+            self.rng_seed
+        );
+        self.run_iterations(start_index, self.iterations);
+        log::debug!(
+            "search finished: best score {:.4}, {} nodes, {} nodes pruned",
+            self.tree.get(start_index).state.max_score,
+            self.tree.nodes.len(),
+            self.pruned_nodes
+        );
+        self
+    }
+
+    /// Runs additional iterations on top of the existing tree, building on the
+    /// statistics gathered by previous searches. Use `solution` to inspect the
+    /// best result so far in between calls.
+    pub fn search_more(&mut self, iterations: u32) -> &mut Self {
+        self.run_iterations(0, iterations)
+    }
+
+    /// Like `search_more`, but calls `progress_callback` after every
+    /// `report_interval` iterations and once more at the end. Returning false
+    /// from the callback stops the search early.
+    pub fn search_more_with_progress(
+        &mut self,
+        iterations: u32,
+        report_interval: u32,
+        progress_callback: &dyn Fn(SearchProgress) -> bool,
+    ) -> &mut Self {
+        let report_interval = report_interval.max(1);
+        let mut completed = 0;
+        while completed < iterations {
+            let batch = report_interval.min(iterations - completed);
+            self.run_iterations(0, batch);
+            completed += batch;
+
+            let progress = SearchProgress {
+                iterations: completed,
+                max_score: self.tree.get(0).state.max_score,
+                nodes: self.tree.nodes.len(),
+            };
+            if !progress_callback(progress) {
+                break;
+            }
+        }
+        self
+    }
+
+    /// Pruning can move nodes around, so indices from before the call other
+    /// than the root's shouldn't be reused; `start_index` itself is never pruned.
+    fn run_iterations(&mut self, start_index: usize, iterations: u32) -> &mut Self {
+        let mut start_index = start_index;
+        for _ in 0..iterations {
             let selected_index = self.select(start_index);
             let (end_index, result) = self.expand_and_rollout(selected_index);
 
@@ -252,19 +927,264 @@ impl<'a> Simulator<'a> {
             }
 
             let score = match result {
-                CraftResult::Finished(s) => s,
+                CraftResult::Finished(metrics) => metrics.score,
                 _ => 0.0,
             };
             self.backpropagate(end_index, start_index, score);
+
+            self.iterations_run += 1;
+            if score > self.best_scores.last().map_or(0.0, |best| best.score) {
+                self.best_scores.push(ScoreImprovement {
+                    iteration: self.iterations_run,
+                    score,
+                });
+            }
+
+            if let Some(max_nodes) = self.max_nodes {
+                if self.tree.nodes.len() > max_nodes {
+                    start_index = self.prune(start_index, max_nodes);
+                }
+            }
         }
         self
     }
 
+    /// Removes the lowest scoring subtrees, ranked by max score and then by
+    /// visits, until the tree is back down to three quarters of `max_nodes`.
+    /// Leaving some room means the tree isn't pruned again on the very next
+    /// iteration. The best path from the root and the path to `start_index`
+    /// are always kept. Returns the new index of `start_index`.
+    ///
+    /// Each removed subtree is detached first, so its action can be expanded
+    /// again and its playouts no longer count towards its ancestors.
+    fn prune(&mut self, start_index: usize, max_nodes: usize) -> usize {
+        let node_count = self.tree.nodes.len();
+        let target = max_nodes - max_nodes / 4;
+
+        // every ancestor of a protected node is protected too, so removing an
+        // unprotected subtree never removes a protected node
+        let mut protected = vec![false; node_count];
+        for index in self.best_path() {
+            protected[index] = true;
+        }
+        let mut current_index = Some(start_index);
+        while let Some(index) = current_index {
+            protected[index] = true;
+            current_index = self.tree.get(index).parent;
+        }
+
+        let mut candidates: Vec<usize> = (0..node_count).filter(|&i| !protected[i]).collect();
+        candidates.sort_by(|&a, &b| {
+            let (a, b) = (&self.tree.get(a).state, &self.tree.get(b).state);
+            a.max_score
+                .total_cmp(&b.max_score)
+                .then(a.visits.total_cmp(&b.visits))
+        });
+
+        let mut keep = vec![true; node_count];
+        let mut removed = 0;
+        let mut subtree = vec![];
+        for index in candidates {
+            if node_count - removed <= target {
+                break;
+            }
+            // already removed along with an earlier subtree
+            if !keep[index] {
+                continue;
+            }
+            self.detach(index);
+            subtree.push(index);
+            while let Some(i) = subtree.pop() {
+                // already removed along with an earlier subtree
+                if !keep[i] {
+                    continue;
+                }
+                keep[i] = false;
+                removed += 1;
+                subtree.extend_from_slice(&self.tree.get(i).children);
+            }
+        }
+
+        let new_indices = self.tree.retain(&keep);
+        self.pruned_nodes += removed;
+        new_indices[start_index].unwrap()
+    }
+
+    /// Gives the action of the node at `index` back to its parent's available
+    /// moves, and takes the node's visits and scores out of every ancestor.
+    /// Max scores are left as they are, since they can't be taken back.
+    fn detach(&mut self, index: usize) {
+        let state = &self.tree.get(index).state;
+        let (visits, score_sum, action) = (state.visits, state.score_sum, state.action);
+        let mut ancestor = self.tree.get(index).parent;
+        if let (Some(parent_index), Some(action)) = (ancestor, action) {
+            self.tree
+                .get_mut(parent_index)
+                .state
+                .available_moves
+                .set(action);
+        }
+        while let Some(ancestor_index) = ancestor {
+            let node = self.tree.get_mut(ancestor_index);
+            node.state.visits = (node.state.visits - visits).max(0.0);
+            node.state.score_sum = (node.state.score_sum - score_sum).max(0.0);
+            ancestor = node.parent;
+        }
+    }
+
+    /// Telemetry for the search so far. Intended for simulators created with
+    /// `Simulator::new`, since the other searches don't return the simulator.
+    pub fn stats(&self) -> SearchStats {
+        SearchStats {
+            rng_seed: self.rng_seed,
+            iterations: self.iterations_run,
+            nodes: self.tree.nodes.len(),
+            dead_ends_selected: self.dead_ends_selected,
+            pruned_nodes: self.pruned_nodes,
+            best_scores: self.best_scores.clone(),
+            action_usage: self.action_usage(),
+        }
+    }
+
+    /// A diagnostic for tuning the strict pruning rules. Runs an unpruned
+    /// search for each of `seeds`, then walks the best rotation each one
+    /// finds and counts the steps where a pruning rule would have kept the
+    /// action it used out of the strict moveset. Rules with high counts may be
+    /// costing the strict searches good rotations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any node's max score is NaN.
+    pub fn pruning_audit(
+        context: &'a CraftContext,
+        search_options: SearchOptions,
+        seeds: &[u32],
+    ) -> PruningAudit {
+        let mut audit = PruningAudit {
+            searches: 0,
+            finished: 0,
+            exclusions: vec![],
+        };
+        for &seed in seeds {
+            // every improving rotation has to be stored for the best one to be
+            // walked, however low it scores
+            let options = SearchOptions {
+                rng_seed: Some(seed),
+                require_complete: true,
+                permissive: true,
+                ..search_options
+            };
+            let mut sim = Self::from_state(CraftState::new(context), options);
+            sim.search(0);
+            audit.searches += 1;
+
+            let path = sim.best_path();
+            let end_state = &sim.tree.get(*path.last().unwrap()).state;
+            if !matches!(end_state.check_result(), Some(CraftResult::Finished(_))) {
+                continue;
+            }
+            audit.finished += 1;
+
+            // the tree's states are checked, which all have Normal conditions
+            for pair in path.windows(2) {
+                let state = &sim.tree.get(pair[0]).state;
+                let action = sim.tree.get(pair[1]).state.action.unwrap();
+                let Some(rule) = state.pruning_rule(action) else {
+                    continue;
+                };
+                match audit
+                    .exclusions
+                    .iter_mut()
+                    .find(|e| e.rule == rule && e.action == action)
+                {
+                    Some(exclusion) => exclusion.count += 1,
+                    None => audit.exclusions.push(PruningExclusion {
+                        rule,
+                        action,
+                        count: 1,
+                    }),
+                }
+            }
+        }
+        audit
+            .exclusions
+            .sort_by_key(|exclusion| cmp::Reverse(exclusion.count));
+        audit
+    }
+
+    fn action_usage(&self) -> Vec<ActionUsage> {
+        let mut usage: Vec<ActionUsage> = vec![];
+        for node in &self.tree.nodes {
+            let Some(action) = node.state.action else {
+                continue;
+            };
+            let (visits, score_sum) = (node.state.visits, node.state.score_sum);
+            match usage.iter_mut().find(|u| u.action == action) {
+                Some(u) => {
+                    u.nodes += 1;
+                    u.visits += visits;
+                    // holds the score sum until every node is counted
+                    u.average_score += score_sum;
+                }
+                None => usage.push(ActionUsage {
+                    action,
+                    nodes: 1,
+                    visits,
+                    average_score: score_sum,
+                }),
+            }
+        }
+        for u in &mut usage {
+            if u.visits > 0.0 {
+                u.average_score /= u.visits;
+            }
+        }
+        usage.sort_by(|a, b| b.visits.total_cmp(&a.visits));
+        usage
+    }
+
     /// Traverses the current tree, following actions that result in the highest
     /// score to find the best solution. This is a convenient way to extract a
     /// solution after running `search`.
-    fn solution(&self) -> (Vec<Action>, CraftState<'a>) {
-        let mut actions = vec![];
+    ///
+    /// The returned state is replayed from the root with Normal conditions, so
+    /// it doesn't depend on any conditions a restored snapshot's nodes were
+    /// given. It keeps the end node's search statistics.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any node's max score is NaN.
+    pub fn solution(&self) -> (Vec<Action>, CraftState<'a>) {
+        let end_index = *self.best_path().last().unwrap();
+        let actions = self.actions_to(end_index);
+
+        let mut state = self.replay(&actions);
+        let end_state = &self.tree.get(end_index).state;
+        state.score_sum = end_state.score_sum;
+        state.max_score = end_state.max_score;
+        state.visits = end_state.visits;
+
+        (actions, state)
+    }
+
+    /// The state reached by using `actions` from the root, with Normal
+    /// conditions after each one
+    fn replay(&self, actions: &[Action]) -> CraftState<'a> {
+        let mut state = self.tree.get(0).state.clone();
+        for action in actions {
+            state = if self.strict {
+                state.execute_strict(action)
+            } else {
+                state.execute(action)
+            };
+            state.condition = Condition::Normal;
+        }
+        state
+    }
+
+    /// The indices of the nodes `solution` follows, starting with the root
+    fn best_path(&self) -> Vec<usize> {
+        let mut path = vec![0];
         let mut node = self.tree.get(0);
         while !node.children.is_empty() {
             let next_index: usize = *node
@@ -276,92 +1196,4065 @@ impl<'a> Simulator<'a> {
                     a_score.partial_cmp(&b_score).unwrap()
                 })
                 .unwrap();
+            path.push(next_index);
             node = self.tree.get(next_index);
-            if node.state.action.is_some() {
-                actions.push(node.state.action.unwrap());
+        }
+        path
+    }
+
+    /// The finished rotations stored in the tree that no other stored rotation
+    /// beats on quality, step count, and remaining CP at the same time. Quality
+    /// past the target isn't counted. Sorted from highest to lowest quality.
+    ///
+    /// Like `solution`, each rotation is replayed with Normal conditions
+    /// before it's compared, and rotations that don't finish when replayed
+    /// are left out.
+    pub fn pareto_front(&self) -> Vec<(Vec<Action>, CraftState<'a>)> {
+        let objectives = |state: &CraftState| {
+            (
+                state.quality.min(state.context.quality_target),
+                state.step,
+                state.cp,
+            )
+        };
+        let dominates =
+            |a: (u32, u8, u32), b: (u32, u8, u32)| a.0 >= b.0 && a.1 <= b.1 && a.2 >= b.2 && a != b;
+
+        let is_finished =
+            |state: &CraftState| matches!(state.check_result(), Some(CraftResult::Finished(_)));
+        let finished: Vec<(Vec<Action>, CraftState<'a>)> = (0..self.tree.nodes.len())
+            .filter(|&i| is_finished(&self.tree.get(i).state))
+            .map(|i| {
+                let actions = self.actions_to(i);
+                let state = self.replay(&actions);
+                (actions, state)
+            })
+            .filter(|(_, state)| is_finished(state))
+            .collect();
+
+        let mut front: Vec<usize> = vec![];
+        for (i, (_, state)) in finished.iter().enumerate() {
+            let candidate = objectives(state);
+            let is_dominated = finished
+                .iter()
+                .any(|(_, other)| dominates(objectives(other), candidate));
+            let is_duplicate = front
+                .iter()
+                .any(|&j| objectives(&finished[j].1) == candidate);
+            if !is_dominated && !is_duplicate {
+                front.push(i);
             }
         }
+        front.sort_by_key(|&i| cmp::Reverse(objectives(&finished[i].1)));
+
+        front.into_iter().map(|i| finished[i].clone()).collect()
+    }
 
-        (actions, node.state.clone())
+    /// The actions that lead from the root node to the node at `index`
+    fn actions_to(&self, index: usize) -> Vec<Action> {
+        let mut actions = vec![];
+        let mut node = self.tree.get(index);
+        while let Some(parent_index) = node.parent {
+            if let Some(action) = node.state.action {
+                actions.push(action);
+            }
+            node = self.tree.get(parent_index);
+        }
+        actions.reverse();
+        actions
     }
 
     /// A standalone method to obtain a `CraftState` from a series of actions.
+    /// Conditions are rolled from the context's `condition_seed`, if provided.
     pub fn simulate(
         context: &'a CraftContext,
         actions: Vec<Action>,
     ) -> (CraftState<'a>, Option<CraftResult>) {
-        let mut sim = Self::from_context(context, SearchOptions::default());
-        let (index, result) = sim.execute_actions(0, actions);
-        (sim.tree.get(index).state.clone(), result)
+        let conditions = Self::seeded_conditions(context, actions.len());
+        Self::simulate_with_conditions(context, actions, &conditions)
     }
 
-    /// Searches for good actions step by step. Creates a fresh tree and runs a
-    /// new search from scratch for each action picked.
-    pub fn search_stepwise(
-        context: &'a CraftContext,
-        action_history: Vec<Action>,
-        search_options: SearchOptions,
-        action_callback: Option<&dyn Fn(Action)>,
-    ) -> (Vec<Action>, CraftState<'a>) {
-        // only store perfect scores to reduce memory usage
-        let search_options = SearchOptions {
-            score_storage_threshold: None,
-            ..search_options
+    /// The conditions `simulate` uses for a rotation of `count` actions. They're
+    /// rolled from the context's `condition_seed`, or empty if it's None, which
+    /// leaves every step Normal.
+    pub fn seeded_conditions(context: &CraftContext, count: usize) -> Vec<Condition> {
+        let Some(seed) = context.condition_seed else {
+            return vec![];
         };
 
-        let (start_state, result) = Self::simulate(context, action_history.clone());
-        if result.is_some() {
-            return (action_history, start_state);
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut conditions = vec![Condition::Normal];
+        while conditions.len() < count {
+            let previous = conditions[conditions.len() - 1];
+            conditions.push(previous.roll_after(context.conditions_flag, &mut rng));
         }
+        conditions
+    }
 
-        let mut state = start_state.clone_strict();
-        let mut actions = action_history;
-        while state.check_result().is_none() {
-            let mut sim = Self::from_state(state.clone(), search_options);
-            let (solution_actions, solution_state) = sim.search(0).solution();
-
-            if solution_state.max_score >= 1.0 {
-                return ([actions, solution_actions].concat(), solution_state);
-            }
+    /// Like `simulate`, but uses `conditions[i]` as the condition of the step
+    /// where `actions[i]` is used instead of rolling. Steps without a provided
+    /// condition keep the condition of the previous step.
+    pub fn simulate_with_conditions(
+        context: &'a CraftContext,
+        actions: Vec<Action>,
+        conditions: &[Condition],
+    ) -> (CraftState<'a>, Option<CraftResult>) {
+        let mut sim = Self::for_replay(context);
+        let (index, result) = sim.execute_actions(0, actions, conditions);
+        (sim.tree.get(index).state.clone(), result)
+    }
 
-            let chosen_action = solution_actions[0];
-            state = state.execute_strict(&chosen_action);
-            actions.push(chosen_action);
+    /// Like `simulate_with_conditions`, but if the craft fails, also reports
+    /// which action it failed on and why. The returned state is the last one
+    /// the rotation reached.
+    pub fn simulate_until_failure(
+        context: &'a CraftContext,
+        actions: &[Action],
+        conditions: &[Condition],
+    ) -> (CraftState<'a>, Option<CraftResult>, Option<FailurePoint>) {
+        let mut sim = Self::for_replay(context);
+        let (index, result) = sim.execute_actions(0, actions.to_vec(), conditions);
+        let state = sim.tree.get(index).state.clone();
 
-            if let Some(action_callback) = action_callback {
-                action_callback(chosen_action);
+        let actions_used = sim.actions_to(index).len();
+        let failure = match result {
+            Some(CraftResult::InvalidActionFailure) => Some(FailurePoint {
+                action_index: actions_used,
+                invalid_reason: actions
+                    .get(actions_used)
+                    .and_then(|&action| state.can_use(action).err()),
+            }),
+            Some(CraftResult::DurabilityFailure | CraftResult::MaxStepsFailure) => {
+                Some(FailurePoint {
+                    action_index: actions_used.saturating_sub(1),
+                    invalid_reason: None,
+                })
             }
-        }
+            Some(CraftResult::Finished(_)) | None => None,
+        };
 
-        (actions, state)
+        (state, result, failure)
     }
 
-    /// Constructs a single large tree, storing good candidate paths in memory
-    /// based on the `score_storage_threshold` option. When the iteration limit
-    /// is reached, the action path that results in the highest score is returned.
-    pub fn search_oneshot(
-        context: &'a CraftContext,
-        action_history: Vec<Action>,
-        search_options: SearchOptions,
+    /// Flags actions in a rotation that are likely wasted, in the order they're
+    /// used. Conditions are assumed to be Normal, and linting stops at the
+    /// first action that can't be used, since simulating already reports it.
+    pub fn lint_rotation(context: &'a CraftContext, actions: &[Action]) -> Vec<RotationWarning> {
+        use crate::action::{
+            GREAT_STRIDES_DURATION, INNOVATION_DURATION, VENERATION_DURATION, WASTE_NOT_DURATION,
+            WASTE_NOT_II_DURATION,
+        };
+        use Action::*;
+
+        type Benefits = fn(Action) -> bool;
+        fn adds_quality(action: Action) -> bool {
+            action.attributes().quality_efficiency.is_some()
+        }
+        fn adds_progress(action: Action) -> bool {
+            action.attributes().progress_efficiency.is_some()
+        }
+        fn costs_durability(action: Action) -> bool {
+            // Observe and Master's Mend are declared with a cost of 0
+            action
+                .attributes()
+                .durability_cost
+                .is_some_and(|cost| cost > 0)
+        }
+
+        let warn = |action_index, kind, message: String| RotationWarning {
+            action_index,
+            kind,
+            message,
+        };
+        let mut warnings = vec![];
+        let mut state = CraftState::new(context);
+        for (i, &action) in actions.iter().enumerate() {
+            if state.is_over() {
+                warnings.extend((i..actions.len()).map(|j| {
+                    warn(
+                        j,
+                        RotationWarningKind::CraftAlreadyOver,
+                        format!("{} is used after the craft is over", actions[j].label()),
+                    )
+                }));
+                break;
+            }
+            if state.can_use(action).is_err() {
+                break;
+            }
+
+            // actions that a buff would benefit, and how many steps it lasts
+            let benefits: Option<(Benefits, u8)> = match action {
+                Innovation => Some((adds_quality, INNOVATION_DURATION)),
+                GreatStrides => Some((adds_quality, GREAT_STRIDES_DURATION)),
+                Veneration => Some((adds_progress, VENERATION_DURATION)),
+                WasteNot => Some((costs_durability, WASTE_NOT_DURATION)),
+                WasteNotII => Some((costs_durability, WASTE_NOT_II_DURATION)),
+                _ => None,
+            };
+            if let Some((benefits, duration)) = benefits {
+                let end = actions.len().min(i + 1 + usize::from(duration));
+                if !actions[i + 1..end].iter().copied().any(benefits) {
+                    warnings.push(warn(
+                        i,
+                        RotationWarningKind::UnusedBuff,
+                        format!(
+                            "{} wears off before any action benefits from it",
+                            action.label()
+                        ),
+                    ));
+                }
+            }
+            if action == Observe
+                && actions
+                    .get(i + 1)
+                    .is_some_and(|&next| next != AdvancedTouch)
+            {
+                warnings.push(warn(
+                    i,
+                    RotationWarningKind::ObserveNotFollowedUp,
+                    "Observe isn't followed by Advanced Touch".to_string(),
+                ));
+            }
+
+            state = state.execute(&action);
+        }
+        warnings
+    }
+
+    /// The resources left at the start of a rotation, then after each action,
+    /// replayed with Normal conditions. Like `available_moves_per_step`, stops
+    /// at the first action that can't be used.
+    pub fn resource_timeline(context: &'a CraftContext, actions: &[Action]) -> Vec<ResourcePoint> {
+        let point = |state: &CraftState| ResourcePoint {
+            progress: state.progress,
+            quality: state.quality,
+            durability: state.durability,
+            cp: state.cp,
+        };
+
+        let mut state = CraftState::new(context);
+        let mut timeline = vec![point(&state)];
+        for action in actions {
+            if !state.available_moves.contains(*action) {
+                break;
+            }
+            state = state.execute(action);
+            timeline.push(point(&state));
+        }
+        timeline
+    }
+
+    /// The lowest durability a rotation reaches, from `resource_timeline`,
+    /// for judging how much margin it leaves for mistakes. Can be zero or
+    /// below if the last action finishes, or breaks, the craft.
+    pub fn min_durability_during(context: &'a CraftContext, actions: &[Action]) -> i8 {
+        Self::resource_timeline(context, actions)
+            .iter()
+            .map(|point| point.durability)
+            .min()
+            .unwrap_or(context.durability_max)
+    }
+
+    /// A rough guess at how forgiving a rotation is. Each of these makes it
+    /// harder: durability dropping to 10 or less before the last action, so a
+    /// single wrong action could break the craft; finishing with less than 10%
+    /// of CP left; and continuing three or more combos, which have to be used
+    /// in order. One of these is Medium, and two or more are Hard.
+    ///
+    /// Replayed with Normal conditions, since no action depends on them. Like
+    /// `resource_timeline`, stops at the first action that can't be used.
+    pub fn rotation_difficulty(context: &'a CraftContext, actions: &[Action]) -> Difficulty {
+        let mut state = CraftState::new(context);
+        let mut lowest_durability = state.durability;
+        let mut combos = 0;
+        for (i, action) in actions.iter().enumerate() {
+            if !state.available_moves.contains(*action) {
+                break;
+            }
+            if action.continues_combo(state.previous_combo_action) {
+                combos += 1;
+            }
+            state = state.execute(action);
+            if i + 1 < actions.len() {
+                lowest_durability = lowest_durability.min(state.durability);
+            }
+        }
+
+        let tight_durability = lowest_durability <= 10;
+        let tight_cp = u64::from(state.cp) * 10 < u64::from(context.cp_max);
+        let many_combos = combos >= 3;
+        match [tight_durability, tight_cp, many_combos]
+            .into_iter()
+            .filter(|&tight| tight)
+            .count()
+        {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    /// The moves that could have been used instead of each action in a
+    /// rotation, replayed with Normal conditions. Stops at the first action
+    /// that can't be used, so there can be fewer steps than `actions`.
+    pub fn available_moves_per_step(
+        context: &'a CraftContext,
+        actions: &[Action],
+    ) -> Vec<Vec<Action>> {
+        let mut state = CraftState::new(context);
+        let mut moves = vec![];
+        for action in actions {
+            if !state.available_moves.contains(*action) {
+                break;
+            }
+            moves.push(state.available_moves.to_vec());
+            state = state.execute(action);
+        }
+        moves
+    }
+
+    /// Runs a fixed rotation `samples` times, rolling a random condition for
+    /// every step, and reports the distribution of the results. Rolls are
+    /// seeded from the context's `condition_seed`, or randomly if None.
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn evaluate(
+        context: &'a CraftContext,
+        actions: &[Action],
+        samples: u32,
+    ) -> RotationEvaluation {
+        let condition_seed = context
+            .condition_seed
+            .unwrap_or_else(|| SmallRng::from_entropy().gen());
+        let mut rng = SmallRng::seed_from_u64(condition_seed);
+
+        let mut qualities = Vec::with_capacity(samples as usize);
+        let mut finished_count = 0;
+        let mut on_target_count = 0;
+        let mut hq_percent_sum = 0;
+        for _ in 0..samples {
+            let mut state = CraftState::new(context);
+            let mut result = None;
+            for action in actions {
+                result = state.check_result();
+                if result.is_some() {
+                    break;
+                }
+                if !state.available_moves.contains(*action) {
+                    result = Some(CraftResult::InvalidActionFailure);
+                    break;
+                }
+                state = state.execute(action);
+                state.condition = state
+                    .condition
+                    .roll_after(context.conditions_flag, &mut rng);
+            }
+            if result.is_none() {
+                result = state.check_result();
+            }
+
+            if let Some(CraftResult::Finished(_)) = result {
+                finished_count += 1;
+                if state.quality >= context.quality_target {
+                    on_target_count += 1;
+                }
+                hq_percent_sum += u32::from(state.hq_percent());
+            }
+            qualities.push(state.quality);
+        }
+
+        qualities.sort_unstable();
+        let percentile = |p: usize| -> u32 {
+            if qualities.is_empty() {
+                0
+            } else {
+                qualities[(qualities.len() - 1) * p / 100]
+            }
+        };
+
+        let sample_count = samples.max(1) as f32;
+        RotationEvaluation {
+            samples,
+            finish_rate: finished_count as f32 / sample_count,
+            mean_quality: qualities.iter().map(|&q| u64::from(q)).sum::<u64>() as f32
+                / sample_count,
+            quality_p10: percentile(10),
+            quality_p50: percentile(50),
+            quality_p90: percentile(90),
+            mean_hq_percent: hq_percent_sum as f32 / sample_count,
+            target_rate: on_target_count as f32 / sample_count,
+        }
+    }
+
+    /// The actions leading to the root's `count` most promising children, from
+    /// best to worst
+    fn best_first_actions(&self, count: usize) -> Vec<Action> {
+        let mut children = self.tree.get(0).children.clone();
+        children.sort_by(|&a, &b| {
+            let a_score = self.tree.get(a).state.max_score;
+            let b_score = self.tree.get(b).state.max_score;
+            b_score.partial_cmp(&a_score).unwrap()
+        });
+        children
+            .into_iter()
+            .take(count)
+            .filter_map(|i| self.tree.get(i).state.action)
+            .collect()
+    }
+
+    /// Runs a short search after each of `actions` from `state`, and returns
+    /// the action whose rotation scored the highest. Earlier actions win ties.
+    fn confirm_best_action(
+        &mut self,
+        state: &CraftState<'a>,
+        actions: &[Action],
+        iterations: u32,
+    ) -> Action {
+        let mut best_action = actions[0];
+        let mut best_score = f32::NEG_INFINITY;
+        for &action in actions {
+            let score = self.confirmation_score(state, action, iterations);
+            if score > best_score {
+                best_action = action;
+                best_score = score;
+            }
+        }
+        best_action
+    }
+
+    /// The score of the rotation a short search finds after using `action`
+    /// from `state`. The rotation is replayed with Normal conditions, since
+    /// the tree's max score can come from lucky rolls. Unfinished rotations
+    /// score 0.
+    fn confirmation_score(
+        &mut self,
+        state: &CraftState<'a>,
+        action: Action,
+        iterations: u32,
+    ) -> f32 {
+        let mut end_state = state.execute_strict(&action);
+        if end_state.check_result().is_none() {
+            self.reset(end_state);
+            self.run_iterations(0, iterations);
+            end_state = self.solution().1;
+        }
+        match end_state.check_result() {
+            Some(CraftResult::Finished(metrics)) => metrics.score,
+            _ => 0.0,
+        }
+    }
+
+    /// Runs a bounded search from the state after `action_history` and
+    /// suggests a single next action. If a `snapshot` from a previous
+    /// suggestion for the same craft is provided, the search continues from it
+    /// instead of starting over, keeping its search constants.
+    ///
+    /// Returns None if the craft is already over or an action in the history
+    /// can't be used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any node's max score is NaN.
+    pub fn suggest_next_action(
+        context: &'a CraftContext,
+        action_history: &[Action],
+        search_options: SearchOptions,
+        snapshot: Option<&SearchSnapshot>,
+    ) -> Option<ActionSuggestion> {
+        let mut sim = snapshot
+            .and_then(|snapshot| Self::restore(context, snapshot))
+            .unwrap_or_else(|| Self::from_context(context, search_options));
+
+        let index = sim.descend(action_history)?;
+        // fully expanded nodes have no available moves left, so the result
+        // can't be checked here
+        if sim.tree.get(index).state.is_over() {
+            return None;
+        }
+        sim.run_iterations(index, search_options.iterations);
+        // pruning may have moved the node
+        let index = sim.descend(action_history)?;
+
+        let node = sim.tree.get(index);
+        // the most visited action is the one the search settled on, and its
+        // share of the visits is the confidence, so both use the same measure
+        let best_child = node
+            .children
+            .iter()
+            .map(|&i| &sim.tree.get(i).state)
+            .max_by(|a, b| a.visits.partial_cmp(&b.visits).unwrap())?;
+
+        Some(ActionSuggestion {
+            action: best_child.action?,
+            confidence: best_child.visits / node.state.visits,
+            snapshot: sim.snapshot(),
+        })
+    }
+
+    /// Follows `actions` down from the root, reusing existing nodes and adding
+    /// any that are missing. Returns the index of the last node, or None if an
+    /// action can't be used.
+    fn descend(&mut self, actions: &[Action]) -> Option<usize> {
+        let mut current_index = 0;
+        for &action in actions {
+            let current_node = self.tree.get(current_index);
+            let existing_child = current_node
+                .children
+                .iter()
+                .copied()
+                .find(|&i| self.tree.get(i).state.action == Some(action));
+
+            current_index = if let Some(child_index) = existing_child {
+                child_index
+            } else {
+                let current_state = &mut self.tree.get_mut(current_index).state;
+                current_state.can_use(action).ok()?;
+                current_state.available_moves.unset(action);
+                let mut next_state = if self.strict {
+                    current_state.execute_strict(&action)
+                } else {
+                    current_state.execute(&action)
+                };
+                next_state.condition = Condition::Normal;
+                self.tree.insert(current_index, next_state)
+            };
+        }
+        Some(current_index)
+    }
+
+    /// Searches for good actions step by step. Creates a fresh tree and runs a
+    /// new search from scratch for each action picked. With the
+    /// `stepwise_candidates` option, the best few actions are each confirmed
+    /// with another search before one is picked.
+    pub fn search_stepwise(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
+        action_callback: Option<&dyn Fn(Action)>,
+    ) -> (Vec<Action>, CraftState<'a>) {
+        // only store perfect scores to reduce memory usage
+        let search_options = SearchOptions {
+            score_storage_threshold: None,
+            ..search_options.with_stepwise_defaults()
+        };
+
+        let candidate_count = search_options.stepwise_candidates.unwrap_or(1) as usize;
+
+        let (start_state, result) = Self::simulate(context, action_history.clone());
+        if result.is_some() {
+            return (action_history, start_state);
+        }
+
+        let mut state = start_state.clone_strict();
+        let mut actions = action_history;
+        let mut sim = Self::from_state(state.clone(), search_options);
+        while state.check_result().is_none() {
+            sim.reset(state.clone());
+            let (solution_actions, solution_state) = sim.search(0).solution();
+
+            // the tree's stats can come from lucky rolls, so the rotation
+            // itself has to reach the max score
+            let reaches_max_score = matches!(
+                solution_state.check_result(),
+                Some(CraftResult::Finished(metrics)) if metrics.score >= 1.0
+            );
+            if reaches_max_score {
+                return ([actions, solution_actions].concat(), solution_state);
+            }
+
+            let candidates = sim.best_first_actions(candidate_count);
+            let chosen_action = if candidates.len() > 1 {
+                sim.confirm_best_action(&state, &candidates, search_options.iterations / 4)
+            } else {
+                solution_actions[0]
+            };
+            state = state.execute_strict(&chosen_action);
+            actions.push(chosen_action);
+            log::debug!("stepwise search chose {}", chosen_action.name());
+
+            if let Some(action_callback) = action_callback {
+                action_callback(chosen_action);
+            }
+        }
+
+        (actions, state)
+    }
+
+    /// Constructs a single large tree, storing good candidate paths in memory
+    /// based on the `score_storage_threshold` option. When the iteration limit
+    /// is reached, the action path that results in the highest score is returned.
+    ///
+    /// `action_history` is kept as is, e.g. to lock in an opener, and only the
+    /// actions after it are searched.
+    pub fn search_oneshot(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
     ) -> (Vec<Action>, CraftState<'a>) {
-        let mut sim = Self::from_context(context, search_options);
+        let (actions, state, _) =
+            Self::search_oneshot_with_stats(context, action_history, search_options);
+        (actions, state)
+    }
+
+    /// Like `search_oneshot`, but also returns the search's stats, e.g. to
+    /// check whether the tree had to be pruned to stay under
+    /// `SearchOptions::max_nodes`.
+    pub fn search_oneshot_with_stats(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
+    ) -> (Vec<Action>, CraftState<'a>, SearchStats) {
+        let (start_state, result) = Self::simulate(context, action_history.clone());
+        if result.is_some() {
+            // nothing to search, so the stats are for an empty search
+            let options = SearchOptions {
+                iterations: 0,
+                ..search_options
+            };
+            let stats = Self::from_state(start_state.clone_strict(), options).stats();
+            return (action_history, start_state, stats);
+        }
+
+        let mut sim = Self::from_state(start_state.clone_strict(), search_options);
         let (actions, result_state) = sim.search(0).solution();
-        ([action_history, actions].concat(), result_state)
+        (
+            [action_history, actions].concat(),
+            result_state,
+            sim.stats(),
+        )
+    }
+
+    /// Like `search_oneshot`, but starts from `start_state` instead of replaying
+    /// actions, e.g. one from `CraftState::from_in_game`. Returns only the
+    /// actions that follow it.
+    pub fn search_from_state(
+        start_state: &CraftState<'a>,
+        search_options: SearchOptions,
+    ) -> (Vec<Action>, CraftState<'a>) {
+        if start_state.check_result().is_some() {
+            return (vec![], start_state.clone());
+        }
+
+        let mut sim = Self::from_state(start_state.clone_strict(), search_options);
+        sim.search(0).solution()
+    }
+
+    /// Like `search_oneshot`, but checks the best rotation every
+    /// `report_interval` iterations and passes it to `on_improvement` whenever
+    /// it scores higher than the last one reported. Returns the best rotation
+    /// reported. Meant for callers that show results while searching, like a
+    /// web worker posting each improvement.
+    pub fn search_streaming(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
+        report_interval: u32,
+        on_improvement: &dyn Fn(SearchImprovement<'a>),
+    ) -> (Vec<Action>, CraftState<'a>) {
+        let (start_state, result) = Self::simulate(context, action_history.clone());
+        if result.is_some() {
+            return (action_history, start_state);
+        }
+
+        let mut sim = Self::from_state(start_state.clone_strict(), search_options);
+        let report_interval = report_interval.max(1);
+        let mut best: Option<(Vec<Action>, CraftState<'a>)> = None;
+        let mut completed = 0;
+        while completed < sim.iterations {
+            let batch = report_interval.min(sim.iterations - completed);
+            sim.run_iterations(0, batch);
+            completed += batch;
+
+            // the solution can get worse as statistics change, so only
+            // rotations better than every one before it are reported
+            let (actions, state) = sim.solution();
+            let best_score = best.as_ref().map_or(0.0, |(_, state)| state.max_score);
+            if state.max_score > best_score {
+                let actions = [action_history.clone(), actions].concat();
+                on_improvement(SearchImprovement {
+                    iterations: completed,
+                    actions: actions.clone(),
+                    state: state.clone(),
+                });
+                best = Some((actions, state));
+            }
+        }
+
+        best.unwrap_or_else(|| {
+            let (actions, result_state) = sim.solution();
+            ([action_history, actions].concat(), result_state)
+        })
+    }
+
+    /// A quick check for whether the craft can be finished at all, ignoring
+    /// quality. Runs a short search for a finishing rotation, so true means one
+    /// was found. False means none was found, which is very likely but not
+    /// guaranteed to mean the craft can't be finished.
+    pub fn can_complete(context: &CraftContext) -> bool {
+        Self::progress_rotation(context).is_some() || Self::can_reach_quality(context, 0)
+    }
+
+    /// A fast, deterministic attempt at finishing the craft while ignoring
+    /// quality. Opens with Muscle Memory and Veneration when they're available,
+    /// then greedily uses whichever synthesis action makes the most progress
+    /// without breaking the item, repairing when none can.
+    ///
+    /// Greedy choices usually give the shortest progress-only rotation, but
+    /// aren't guaranteed to, so None doesn't prove the craft can't be finished.
+    pub fn progress_rotation(context: &CraftContext) -> Option<Vec<Action>> {
+        Self::finish_progress(CraftState::new(context))
+    }
+
+    /// The greedy synthesis of `progress_rotation`, continuing from `state`
+    fn finish_progress(mut state: CraftState) -> Option<Vec<Action>> {
+        use Action::*;
+
+        let context = state.context;
+        let mut actions = vec![];
+        while state.progress < context.progress_target {
+            let usable = |action: Action| state.can_use(action).is_ok();
+            let synthesis: Vec<(Action, CraftState)> = Action::ACTIONS
+                .iter()
+                .filter(|action| action.attributes().progress_efficiency.is_some())
+                .filter(|&&action| usable(action))
+                .map(|&action| (action, state.execute(&action)))
+                .collect();
+
+            let finishing = synthesis
+                .iter()
+                .filter(|(_, next)| next.progress >= context.progress_target)
+                .max_by_key(|(_, next)| (next.durability, next.cp));
+            let safe = synthesis
+                .iter()
+                .filter(|(_, next)| next.durability > 0)
+                .max_by_key(|(_, next)| (next.progress, next.cp));
+
+            let action = if let Some(&(action, _)) = finishing {
+                action
+            } else if let Some(&opener) = [MuscleMemory, Veneration]
+                .iter()
+                .find(|&&buff| usable(buff) && state.buffs.veneration == 0)
+            {
+                opener
+            } else if let Some(&(action, _)) =
+                safe.filter(|(_, next)| next.durability > 10 || state.buffs.manipulation > 0)
+            {
+                action
+            } else if let Some(&repair) = [Manipulation, ImmaculateMend, MastersMend]
+                .iter()
+                .find(|&&repair| usable(repair) && state.buffs.manipulation == 0)
+            {
+                repair
+            } else {
+                safe?.0
+            };
+
+            state = state.execute(&action);
+            actions.push(action);
+            if state.is_over() && state.progress < context.progress_target {
+                return None;
+            }
+        }
+        Some(actions)
+    }
+
+    /// A quick, rule-based rotation to show while a real search runs. Opens
+    /// with Muscle Memory and Manipulation, uses touch combos under Innovation
+    /// for as long as the craft can still be finished afterwards, then uses
+    /// Byregot's Blessing if there's room left and finishes with
+    /// `progress_rotation`'s greedy synthesis. Far from optimal, but returns a
+    /// finishing rotation whenever `progress_rotation` finds one.
+    pub fn standard_rotation(context: &CraftContext) -> Option<Vec<Action>> {
+        use Action::*;
+
+        // only uses `sequence` if all of it is usable, and the craft can still
+        // be finished afterwards
+        fn try_use(state: &mut CraftState, actions: &mut Vec<Action>, sequence: &[Action]) -> bool {
+            let mut next = state.clone();
+            for action in sequence {
+                if next.can_use(*action).is_err() {
+                    return false;
+                }
+                next = next.execute(action);
+            }
+            if Simulator::finish_progress(next.clone()).is_none() {
+                return false;
+            }
+            *state = next;
+            actions.extend(sequence);
+            true
+        }
+
+        if !context.quality_relevant {
+            return Self::progress_rotation(context);
+        }
+
+        let mut state = CraftState::new(context);
+        let mut actions = vec![];
+        for opener in [MuscleMemory, Manipulation] {
+            try_use(&mut state, &mut actions, &[opener]);
+        }
+
+        while state.quality < context.quality_target {
+            let touches: &[Action] = match state.previous_combo_action {
+                Some(BasicTouch) => &[StandardTouch, BasicTouch, PrudentTouch],
+                Some(StandardTouch) => &[AdvancedTouch, BasicTouch, PrudentTouch],
+                _ => &[BasicTouch, PrudentTouch],
+            };
+            let used_touch = touches.iter().any(|&touch| {
+                // Innovation is only worth it if a touch can follow
+                (state.buffs.innovation == 0
+                    && try_use(&mut state, &mut actions, &[Innovation, touch]))
+                    || try_use(&mut state, &mut actions, &[touch])
+            });
+            if !used_touch {
+                break;
+            }
+        }
+
+        if state.quality < context.quality_target && state.buffs.inner_quiet > 0 {
+            // Great Strides is only worth it if Byregot's Blessing can follow
+            let _ = try_use(&mut state, &mut actions, &[GreatStrides, ByregotsBlessing])
+                || try_use(&mut state, &mut actions, &[ByregotsBlessing]);
+        }
+
+        actions.extend(Self::finish_progress(state)?);
+        Some(actions)
+    }
+
+    /// Like `can_complete`, but the rotation also has to reach `quality_target`
+    pub fn can_reach_quality(context: &CraftContext, quality_target: u32) -> bool {
+        const QUICK_CHECK_ITERATIONS: u32 = 5_000;
+
+        // only Normal conditions, so that a lucky roll isn't mistaken for a
+        // rotation that always works
+        let context = CraftContext {
+            quality_target,
+            quality_relevant: quality_target > 0,
+            conditions_flag: 1,
+            ..context.clone()
+        };
+        let search_options = SearchOptions {
+            iterations: QUICK_CHECK_ITERATIONS,
+            rng_seed: Some(0),
+            // store any finishing rotation that improves on the last one
+            score_storage_threshold: Some(0.0),
+            ..Default::default()
+        };
+
+        let (actions, _) = Simulator::search_oneshot(&context, vec![], search_options);
+        let (state, result) = Simulator::simulate(&context, actions);
+        matches!(result, Some(CraftResult::Finished(_))) && state.quality >= quality_target
+    }
+
+    /// The least CP, up to the player's, that a `can_reach_quality` check
+    /// still finds a rotation reaching the quality target with. Binary
+    /// searches on CP, so the checks are assumed to pass for any CP above a
+    /// passing one.
+    ///
+    /// Returns None if the target can't be reached with the player's CP.
+    pub fn minimum_cp_for_hq(
+        player: &Player,
+        recipe: &Recipe,
+        options: &CraftOptions,
+    ) -> Option<u32> {
+        let reaches_target = |cp: u32| {
+            let context = CraftContext::new(&player.with_cp(cp), recipe, *options);
+            Self::can_reach_quality(&context, context.quality_target)
+        };
+
+        let (mut low, mut high) = (0, player.cp);
+        if !reaches_target(high) {
+            return None;
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if reaches_target(mid) {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        Some(high)
+    }
+
+    /// The highest quality a search finds a finishing rotation for, aiming
+    /// for the recipe's max quality regardless of any target in `options`.
+    /// Quality past the recipe's max isn't counted, so reaching it means 100%
+    /// HQ is possible.
+    ///
+    /// Returns None if no rotation finishing the craft was found.
+    pub fn max_reachable_quality(
+        player: &Player,
+        recipe: &Recipe,
+        options: &CraftOptions,
+        search_options: SearchOptions,
+    ) -> Option<u32> {
+        let context = CraftContext::new(
+            player,
+            recipe,
+            CraftOptions {
+                quality_target: None,
+                collectability_tiers: None,
+                ..*options
+            },
+        );
+        // keep every improvement, not just ~100% HQ rotations
+        let search_options = SearchOptions {
+            score_storage_threshold: Some(0.0),
+            ..search_options
+        };
+
+        let (actions, _) = Simulator::search_oneshot(&context, vec![], search_options);
+        let (state, result) = Simulator::simulate(&context, actions);
+        matches!(result, Some(CraftResult::Finished(_))).then(|| state.quality.min(recipe.quality))
+    }
+
+    /// The `max_reachable_quality` for each CP value in `cps`, with the
+    /// player's other stats unchanged, e.g. to plot how much quality more CP
+    /// is worth. A rotation found with less CP also works with more, so each
+    /// quality is at least that of every lower CP before it in `cps`, even if
+    /// that search happened to find less. None if no finishing rotation was
+    /// found yet.
+    ///
+    /// Searches run in parallel with the `parallel` feature.
+    pub fn quality_cp_curve(
+        player: &Player,
+        recipe: &Recipe,
+        options: &CraftOptions,
+        search_options: SearchOptions,
+        cps: &[u32],
+    ) -> Vec<(u32, Option<u32>)> {
+        let quality_at = |&cp: &u32| {
+            let player = player.with_cp(cp);
+            (
+                cp,
+                Self::max_reachable_quality(&player, recipe, options, search_options),
+            )
+        };
+        #[cfg(feature = "parallel")]
+        let mut curve: Vec<(u32, Option<u32>)> = cps.par_iter().map(quality_at).collect();
+        #[cfg(not(feature = "parallel"))]
+        let mut curve: Vec<(u32, Option<u32>)> = cps.iter().map(quality_at).collect();
+
+        let mut order: Vec<usize> = (0..curve.len()).collect();
+        order.sort_by_key(|&i| curve[i].0);
+        let mut best = None;
+        for i in order {
+            best = best.max(curve[i].1);
+            curve[i].1 = best;
+        }
+        curve
+    }
+
+    /// Searches again with `stat_delta` more craftsmanship, control, and CP,
+    /// one at a time, to show which stat is worth raising. Each search is a
+    /// `max_reachable_quality` search, so quality targets in `options` are
+    /// ignored. Searches are random, so use an `rng_seed` and enough
+    /// iterations for the differences to mean something.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn sensitivity(
+        player: &Player,
+        recipe: &Recipe,
+        options: &CraftOptions,
+        search_options: SearchOptions,
+        stat_delta: u32,
+    ) -> StatSensitivity {
+        let best_quality =
+            |player: &Player| Self::max_reachable_quality(player, recipe, options, search_options);
+        let hq_percent = |quality: Option<u32>| {
+            quality.map(|quality| CraftState::hq_percent_for_quality(quality, recipe.quality))
+        };
+
+        let base_quality = best_quality(player);
+        let change = |player: Player| {
+            let quality = best_quality(&player);
+            StatChange {
+                quality,
+                hq_percent: hq_percent(quality),
+                quality_per_point: base_quality.zip(quality).map(|(base, quality)| {
+                    ((f64::from(quality) - f64::from(base)) / f64::from(stat_delta.max(1))) as f32
+                }),
+            }
+        };
+
+        StatSensitivity {
+            stat_delta,
+            quality: base_quality,
+            hq_percent: hq_percent(base_quality),
+            craftsmanship: change(player.with_craftsmanship(player.craftsmanship + stat_delta)),
+            control: change(player.with_control(player.control + stat_delta)),
+            cp: change(player.with_cp(player.cp + stat_delta)),
+        }
+    }
+
+    /// Runs the feasibility checks a craft needs before it's searched, e.g.
+    /// when a recipe is picked. The game won't start a craft if the player
+    /// doesn't meet the recipe's level and stat requirements, so the searches
+    /// are skipped, and false, if any of them aren't met. The quality check is
+    /// also skipped, and false, if the craft can't be finished.
+    pub fn preflight(player: &Player, recipe: &Recipe, options: &CraftOptions) -> Preflight {
+        let context = CraftContext::new(player, recipe, *options);
+        let meets_level_requirement = player.job_level >= recipe.job_level;
+        let meets_craftsmanship_requirement = player.craftsmanship >= recipe.required_craftsmanship;
+        let meets_control_requirement = player.control >= recipe.required_control;
+        let can_complete = meets_level_requirement
+            && meets_craftsmanship_requirement
+            && meets_control_requirement
+            && Self::can_complete(&context);
+
+        Preflight {
+            meets_level_requirement,
+            meets_craftsmanship_requirement,
+            meets_control_requirement,
+            can_complete,
+            can_reach_max_quality: can_complete
+                && Self::can_reach_quality(&context, recipe.quality),
+            recipe_modifiers_apply: player.job_level <= recipe.job_level,
+            specialist_actions_available: options.player_is_specialist
+                && context.action_pool.contains(Action::CarefulObservation),
+        }
+    }
+
+    /// Searches for the highest quality rotation, then repeatedly searches
+    /// again with a lower step limit. Returns the shortest rotation found that
+    /// still finishes with at least the same quality.
+    pub fn search_fewest_steps(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
+    ) -> (Vec<Action>, CraftState<'a>) {
+        Self::search_tightening(
+            context,
+            action_history,
+            search_options,
+            // a craft can still finish on the step where it reaches the limit
+            |best| CraftContext {
+                step_max: best.step.saturating_sub(1),
+                ..context.clone()
+            },
+            |candidate, best| candidate.step < best.step,
+        )
+    }
+
+    /// The fewest steps rotation that `search_fewest_steps` finds with at
+    /// least `target_hq_percent` chance of an HQ result, for when 100% HQ
+    /// isn't needed. Overrides any quality target in `options`.
+    ///
+    /// Returns None if no rotation reaching the target was found.
+    pub fn shortest_rotation_for_hq(
+        player: &Player,
+        recipe: &Recipe,
+        target_hq_percent: u8,
+        options: &CraftOptions,
+        search_options: SearchOptions,
+    ) -> Option<Vec<Action>> {
+        let quality_target = CraftState::quality_for_hq_percent(recipe.quality, target_hq_percent);
+        let context = CraftContext::new(
+            player,
+            recipe,
+            CraftOptions {
+                quality_target: Some(quality_target),
+                ..*options
+            },
+        );
+
+        let (actions, _) = Simulator::search_fewest_steps(&context, vec![], search_options);
+        let (state, result) = Simulator::simulate(&context, actions.clone());
+        let reached =
+            matches!(result, Some(CraftResult::Finished(_))) && state.quality >= quality_target;
+        reached.then_some(actions)
+    }
+
+    /// Cleans up a finishing rotation, e.g. one from a search, by trying to
+    /// remove or replace one action at a time. A change is kept if the
+    /// rotation still finishes with at least the same quality, up to the
+    /// target, and takes fewer steps or leaves more CP. Repeats until no
+    /// single change helps, so this is a local search rather than a full one.
+    ///
+    /// Rotations that don't finish are returned as is.
+    pub fn trim_rotation(context: &'a CraftContext, actions: &[Action]) -> Vec<Action> {
+        let finished_state = |actions: &[Action]| {
+            let (state, result) = Self::simulate(context, actions.to_vec());
+            matches!(result, Some(CraftResult::Finished(_))).then_some(state)
+        };
+
+        let mut best_actions = actions.to_vec();
+        let Some(mut best_state) = finished_state(&best_actions) else {
+            return best_actions;
+        };
+        let quality_required = best_state.quality.min(context.quality_target);
+        let replacements = context.action_pool.to_vec();
+
+        'improve: loop {
+            for i in 0..best_actions.len() {
+                let removed = [&best_actions[..i], &best_actions[i + 1..]].concat();
+                let replaced = replacements
+                    .iter()
+                    .filter(|&&action| action != best_actions[i])
+                    .map(|&action| {
+                        let mut replaced = best_actions.clone();
+                        replaced[i] = action;
+                        replaced
+                    });
+
+                for candidate in std::iter::once(removed).chain(replaced) {
+                    let Some(state) = finished_state(&candidate) else {
+                        continue;
+                    };
+                    let is_better = state.step < best_state.step
+                        || (state.step == best_state.step && state.cp > best_state.cp);
+                    if state.quality >= quality_required && is_better {
+                        best_actions = candidate;
+                        best_state = state;
+                        continue 'improve;
+                    }
+                }
+            }
+            break;
+        }
+
+        best_actions
+    }
+
+    /// Makes a hand-written rotation survivable by inserting repairs, e.g. for
+    /// a sequence of touches and synthesis written without durability in mind.
+    /// Whenever the next action would break the item, one of Manipulation,
+    /// Master's Mend, or Immaculate Mend is used first: whichever lets the most
+    /// of the remaining actions be used before another repair is needed, then
+    /// whichever leaves the most CP.
+    ///
+    /// Replayed with Normal conditions. Greedy, so it won't always find the
+    /// cheapest repairs. If no repair lets the next action be used, or an
+    /// action can't be used for another reason, the rest of the rotation is
+    /// kept as is, so the result isn't guaranteed to finish.
+    pub fn insert_durability_recovery(
+        context: &'a CraftContext,
+        actions: &[Action],
+    ) -> Vec<Action> {
+        use Action::*;
+
+        let breaks = |state: &CraftState, next: &CraftState| {
+            next.durability <= 0 && next.progress < state.context.progress_target
+        };
+        // how many of `actions` can be used in order before the item would break
+        let survived = |mut state: CraftState, actions: &[Action]| {
+            let mut count = 0;
+            for action in actions {
+                if state.can_use(*action).is_err() {
+                    break;
+                }
+                let next = state.execute(action);
+                if breaks(&state, &next) {
+                    break;
+                }
+                count += 1;
+                state = next;
+            }
+            count
+        };
+
+        let mut state = CraftState::new(context);
+        let mut planned = vec![];
+        for (i, action) in actions.iter().enumerate() {
+            if state.can_use(*action).is_err() {
+                planned.extend(&actions[i..]);
+                break;
+            }
+            if breaks(&state, &state.execute(action)) {
+                let repair = [Manipulation, MastersMend, ImmaculateMend]
+                    .into_iter()
+                    .filter(|&repair| state.can_use(repair).is_ok())
+                    .filter(|&repair| repair != Manipulation || state.buffs.manipulation == 0)
+                    .map(|repair| {
+                        let repaired = state.execute(&repair);
+                        let count = survived(repaired.clone(), &actions[i..]);
+                        (repair, repaired, count)
+                    })
+                    .filter(|&(_, _, count)| count > 0)
+                    .max_by_key(|(_, repaired, count)| (*count, repaired.cp));
+                let Some((repair, repaired, _)) = repair else {
+                    planned.extend(&actions[i..]);
+                    break;
+                };
+                planned.push(repair);
+                state = repaired;
+            }
+            state = state.execute(action);
+            planned.push(*action);
+        }
+        planned
+    }
+
+    /// Searches for a rotation that finishes and reaches the context's
+    /// `quality_target`, then repeatedly searches again with less CP
+    /// available. Returns the rotation found that spends the least CP. Most
+    /// useful with a `quality_target` below the recipe's max quality.
+    pub fn search_min_cp(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
+    ) -> (Vec<Action>, CraftState<'a>) {
+        let cp_spent = |state: &CraftState| context.cp_max - state.cp;
+        Self::search_tightening(
+            context,
+            action_history,
+            search_options,
+            |best| CraftContext {
+                cp_max: cp_spent(best).saturating_sub(1),
+                ..context.clone()
+            },
+            |candidate, best| cp_spent(candidate) < cp_spent(best),
+        )
+    }
+
+    /// Searches for the rotation that spends the least CP while still reaching
+    /// `target_collectability` with Normal conditions, like `search_min_cp`,
+    /// e.g. for a collectable that's crafted many times over. The rotation is
+    /// then run `samples` times under the recipe's random conditions, like
+    /// `evaluate`, to report how often it still reaches the target. Any
+    /// quality target in `options` is replaced.
+    ///
+    /// Returns None if no rotation reaching the target with Normal conditions
+    /// was found.
+    pub fn search_collectable(
+        player: &Player,
+        recipe: &Recipe,
+        target_collectability: u32,
+        options: &CraftOptions,
+        search_options: SearchOptions,
+        samples: u32,
+    ) -> Option<CollectableRotation> {
+        let context = CraftContext::new(
+            player,
+            recipe,
+            CraftOptions {
+                quality_target: Some(Recipe::quality_from_collectability(target_collectability)),
+                ..*options
+            },
+        );
+        // searches with variable conditions can return rotations that only
+        // reach the target with lucky rolls, so the search only plans for Normal
+        let normal_context = CraftContext {
+            conditions_flag: 1,
+            ..context.clone()
+        };
+        let (actions, state) = Simulator::search_min_cp(&normal_context, vec![], search_options);
+        let finished = matches!(state.check_result(), Some(CraftResult::Finished(_)));
+        if !finished || state.quality < context.quality_target {
+            return None;
+        }
+
+        let evaluation = Simulator::evaluate(&context, &actions, samples);
+        Some(CollectableRotation {
+            cp_spent: context.cp_max - state.cp,
+            collectability: Recipe::collectability_from_quality(state.quality),
+            success_rate: evaluation.target_rate,
+            actions,
+        })
+    }
+
+    /// Runs a search, then keeps searching with contexts built by `tighten`
+    /// from the best state so far. A result replaces the best one as long as
+    /// it finishes, keeps the same quality, and `is_better` agrees.
+    ///
+    /// Candidates are compared by simulating them against `context`, since
+    /// searches may have rolled different conditions along the way.
+    fn search_tightening<T, B>(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
+        tighten: T,
+        is_better: B,
+    ) -> (Vec<Action>, CraftState<'a>)
+    where
+        T: Fn(&CraftState) -> CraftContext,
+        B: Fn(&CraftState, &CraftState) -> bool,
+    {
+        // playouts are only stored when they beat the best score so far, so
+        // this keeps every search's best rotation without storing many paths
+        let search_options = SearchOptions {
+            score_storage_threshold: Some(0.0),
+            ..search_options
+        };
+
+        let prefix_len = action_history.len();
+        let (mut best_actions, _) = Self::search_oneshot(context, action_history, search_options);
+        let (mut best_state, result) = Self::simulate(context, best_actions.clone());
+        if !matches!(result, Some(CraftResult::Finished(_))) {
+            return (best_actions, best_state);
+        }
+
+        let quality_required = best_state.quality.min(context.quality_target);
+        loop {
+            let tightened_context = tighten(&best_state);
+            let action_history = best_actions[..prefix_len].to_vec();
+            let (actions, _) =
+                Simulator::search_oneshot(&tightened_context, action_history, search_options);
+
+            let (state, result) = Self::simulate(context, actions.clone());
+            let finished = matches!(result, Some(CraftResult::Finished(_)));
+            if !finished || state.quality < quality_required || !is_better(&state, &best_state) {
+                break;
+            }
+
+            best_actions = actions;
+            best_state = state;
+        }
+
+        (best_actions, best_state)
+    }
+}
+
+/// The scale of fixed-point values in `Simulator::ucb1_fixed`, so 1.0 is
+/// 1,000,000
+const FIXED_POINT_SCALE: u64 = 1_000_000;
+
+/// `ln(n)` in fixed-point, scaled by `FIXED_POINT_SCALE`. Computes `log2(n)`
+/// one fractional bit at a time by repeated squaring, then converts to `ln`.
+fn ln_fixed(n: u64) -> u64 {
+    const FRACTION_BITS: u32 = 32;
+    const ONE: u128 = 1 << FRACTION_BITS;
+    // ln(2) scaled by FIXED_POINT_SCALE, then by another 1,000,000 so that it
+    // doesn't round off
+    const LN_2: u128 = 693_147_180_560;
+
+    let whole = n.max(1).ilog2();
+    // n / 2^whole, in [1, 2)
+    let mut x = (u128::from(n) << FRACTION_BITS) >> whole;
+    let mut log2 = u128::from(whole) << FRACTION_BITS;
+    for bit in (0..FRACTION_BITS).rev() {
+        x = (x * x) >> FRACTION_BITS;
+        if x >= 2 * ONE {
+            x >>= 1;
+            log2 |= 1 << bit;
+        }
+    }
+    u64::try_from(((log2 * LN_2) >> FRACTION_BITS) / 1_000_000).unwrap_or(u64::MAX)
+}
+
+/// Search statistics for one of the root's children, pooled across the
+/// trees of a root-parallel search. Trees are merged one after another in the
+/// same order every time, so the pooled `f32` sums are reproducible like a
+/// single tree's.
+#[cfg(feature = "parallel")]
+#[derive(Debug, Clone, Copy, Default)]
+struct RootChildStats {
+    score_sum: f32,
+    max_score: f32,
+    visits: f32,
+}
+
+#[cfg(feature = "parallel")]
+impl<'a> Simulator<'a> {
+    /// Iterations each tree runs in between merges during `search_root_parallel`
+    const ROOT_MERGE_INTERVAL: u32 = 1_000;
+
+    /// Runs a oneshot search for each recipe in parallel, with the same player
+    /// and options for all of them. Returns each recipe in the same order as
+    /// `recipes`, with its best rotation and that rotation's score. The score
+    /// is 0 if the rotation doesn't finish the craft.
+    pub fn solve_batch(
+        player: &Player,
+        recipes: &[&Recipe],
+        options: &CraftOptions,
+        search_options: SearchOptions,
+    ) -> Vec<(Recipe, Vec<Action>, f32)> {
+        // keep the best rotation for each recipe, even if it isn't ~100% HQ
+        let search_options = SearchOptions {
+            score_storage_threshold: Some(0.0),
+            ..search_options
+        };
+        recipes
+            .par_iter()
+            .map(|&recipe| {
+                let context = CraftContext::new(player, recipe, *options);
+                let (actions, _) = Simulator::search_oneshot(&context, vec![], search_options);
+                let score = match Simulator::simulate(&context, actions.clone()) {
+                    (_, Some(CraftResult::Finished(metrics))) => metrics.score,
+                    _ => 0.0,
+                };
+                (*recipe, actions, score)
+            })
+            .collect()
+    }
+
+    /// Like `search_oneshot`, but grows `tree_count` trees in parallel that
+    /// each run `search_options.iterations`. Every so often the statistics of
+    /// the root's children are pooled across all trees, so every tree picks its
+    /// first move using the combined results while exploring its own subtrees.
+    /// Returns the best finished rotation stored in any of the trees.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any score is NaN.
+    pub fn search_root_parallel(
+        context: &'a CraftContext,
+        action_history: Vec<Action>,
+        search_options: SearchOptions,
+        tree_count: u32,
+    ) -> (Vec<Action>, CraftState<'a>) {
+        let (start_state, result) = Self::simulate(context, action_history.clone());
+        if result.is_some() {
+            return (action_history, start_state);
+        }
+
+        let rng_seed = search_options
+            .rng_seed
+            .or(SearchOptions::default().rng_seed)
+            .unwrap();
+        let start_state = start_state.clone_strict();
+        let mut sims: Vec<Self> = (0..tree_count.max(1))
+            .map(|i| {
+                let options = SearchOptions {
+                    rng_seed: Some(rng_seed.wrapping_add(i)),
+                    condition_seed: search_options
+                        .condition_seed
+                        .map(|seed| seed.wrapping_add(u64::from(i))),
+                    ..search_options
+                };
+                Self::from_state(start_state.clone(), options)
+            })
+            .collect();
+
+        let mut merged = vec![RootChildStats::default(); Action::ACTIONS.len()];
+        let mut last_merged = vec![merged.clone(); sims.len()];
+        let mut completed = 0;
+        while completed < search_options.iterations {
+            let batch = Self::ROOT_MERGE_INTERVAL.min(search_options.iterations - completed);
+            sims.par_iter_mut().for_each(|sim| {
+                sim.run_iterations(0, batch);
+            });
+            completed += batch;
+
+            Self::merge_root_children(&mut sims, &mut merged, &mut last_merged);
+        }
+
+        let (actions, state) = sims
+            .iter()
+            .map(Self::best_finished)
+            .max_by(|(_, a), (_, b)| {
+                Self::finished_score(a)
+                    .partial_cmp(&Self::finished_score(b))
+                    .unwrap()
+            })
+            .unwrap();
+        ([action_history, actions].concat(), state)
+    }
+
+    /// Adds what each tree learned about the root's children since the last
+    /// merge to `merged`, then overwrites every tree's root children with the
+    /// pooled statistics. `last_merged` keeps track of the values each tree was
+    /// given, so that they aren't counted twice.
+    fn merge_root_children(
+        sims: &mut [Self],
+        merged: &mut [RootChildStats],
+        last_merged: &mut [Vec<RootChildStats>],
+    ) {
+        for (sim, last) in sims.iter().zip(last_merged.iter()) {
+            for &child_index in &sim.tree.get(0).children {
+                let state = &sim.tree.get(child_index).state;
+                let i = state.action.unwrap().index();
+                merged[i].score_sum += state.score_sum - last[i].score_sum;
+                merged[i].max_score = merged[i].max_score.max(state.max_score);
+                merged[i].visits += state.visits - last[i].visits;
+            }
+        }
+
+        let root_visits: f32 = merged.iter().map(|stats| stats.visits).sum();
+        for (sim, last) in sims.iter_mut().zip(last_merged.iter_mut()) {
+            // children that haven't been expanded in this tree start from zero
+            last.fill(RootChildStats::default());
+
+            for c in 0..sim.tree.get(0).children.len() {
+                let child_index = sim.tree.get(0).children[c];
+                let state = &mut sim.tree.get_mut(child_index).state;
+                let i = state.action.unwrap().index();
+                state.score_sum = merged[i].score_sum;
+                state.max_score = merged[i].max_score;
+                state.visits = merged[i].visits;
+                last[i] = merged[i];
+            }
+
+            let root = &mut sim.tree.get_mut(0).state;
+            root.visits = root.visits.max(root_visits);
+        }
+    }
+
+    /// The highest scoring finished rotation stored in the tree, or the root if
+    /// there aren't any. Merged root statistics may point `solution` towards
+    /// rotations that are stored in other trees, so stored nodes are checked
+    /// directly instead. Like `solution`, each rotation is replayed with
+    /// Normal conditions and ranked by the replayed result.
+    fn best_finished(&self) -> (Vec<Action>, CraftState<'a>) {
+        let replayed = (0..self.tree.nodes.len())
+            .filter(|&i| Self::finished_score(&self.tree.get(i).state) >= 0.0)
+            .map(|i| {
+                let actions = self.actions_to(i);
+                let state = self.replay(&actions);
+                (actions, state)
+            });
+        // the root comes last, so it's only picked if nothing finishes
+        let root = (vec![], self.tree.get(0).state.clone());
+
+        replayed
+            .chain(std::iter::once(root))
+            .max_by(|(_, a), (_, b)| {
+                Self::finished_score(a)
+                    .partial_cmp(&Self::finished_score(b))
+                    .unwrap()
+            })
+            .unwrap()
+    }
+
+    /// Ranks states so that any finished craft beats an unfinished one
+    fn finished_score(state: &CraftState) -> f32 {
+        match state.check_result() {
+            Some(CraftResult::Finished(metrics)) => metrics.score,
+            _ => -1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ln_fixed;
+    use crate::{
+        Action, BuffKind, Buffs, Condition, CraftContext, CraftContextError, CraftOptions,
+        CraftResult, CraftState, Difficulty, FailurePoint, InvalidActionReason, NoCompleteRotation,
+        NodeSnapshot, Player, PruningRule, Recipe, RotationWarning, RotationWarningKind,
+        SearchOptions, SerializedCraftState, Simulator, StateDiff, GREAT_STRIDES_DURATION,
+        INNOVATION_DURATION, MANIPULATION_DURATION, MUSCLE_MEMORY_DURATION, VENERATION_DURATION,
+        WASTE_NOT_DURATION, WASTE_NOT_II_DURATION,
+    };
+    use Action::*;
+
+    fn setup_1() -> (CraftContext, SearchOptions) {
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(100, 4747, 4353, 577);
+        let craft_options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let context = CraftContext::new(&player, &recipe, craft_options);
+        let options = SearchOptions {
+            rng_seed: Some(0),
+            ..Default::default()
+        };
+        (context, options)
+    }
+
+    fn setup_2() -> (CraftContext, SearchOptions) {
+        let recipe = Recipe {
+            recipe_level: 580,
+            job_level: 90,
+            stars: 2,
+            progress: 3900,
+            quality: 10920,
+            durability: 70,
+            progress_div: 130,
+            progress_mod: 80,
+            quality_div: 115,
+            quality_mod: 70,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(90, 3290, 3541, 649);
+        let craft_options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let context = CraftContext::new(&player, &recipe, craft_options);
+        let options = SearchOptions {
+            rng_seed: Some(123),
+            ..Default::default()
+        };
+        (context, options)
+    }
+
+    fn assert_craft(
+        context: &CraftContext,
+        actions: Vec<Action>,
+        progress: u32,
+        quality: u32,
+        durability: i8,
+        cp: u32,
+    ) {
+        let (end_state, _) = Simulator::simulate(context, actions);
+        assert_eq!(end_state.progress, progress);
+        assert_eq!(end_state.quality, quality);
+        assert_eq!(end_state.durability, durability);
+        assert_eq!(end_state.cp, cp);
+    }
+
+    #[test]
+    fn basic_actions() {
+        let actions = vec![BasicTouch, BasicSynthesisTraited, MastersMend];
+        let (context, _) = setup_1();
+        assert_craft(&context, actions, 303, 243, 80, 471);
+    }
+
+    #[test]
+    fn basic_touch_combo() {
+        let actions = vec![
+            Innovation,
+            BasicTouch,
+            StandardTouch,
+            AdvancedTouch,
+            Observe,
+            AdvancedTouch,
+            StandardTouch,
+            AdvancedTouch,
+        ];
+        let (context, _) = setup_1();
+        assert_craft(&context, actions, 0, 2965, 20, 402);
+    }
+
+    #[test]
+    fn with_buffs_1() {
+        let actions = vec![
+            Reflect,
+            Manipulation,
+            WasteNotII,
+            PreparatoryTouch,
+            DelicateSynthesisTraited,
+        ];
+        let (context, _) = setup_1();
+        assert_craft(&context, actions, 379, 1652, 70, 305);
+    }
+
+    #[test]
+    fn with_buffs_2() {
+        let actions = vec![
+            MuscleMemory,
+            GreatStrides,
+            PrudentTouch,
+            DelicateSynthesisTraited,
+        ];
+        let (context, _) = setup_1();
+        assert_craft(&context, actions, 1518, 753, 55, 482);
+    }
+
+    #[test]
+    fn with_buffs_3() {
+        let actions = vec![
+            MuscleMemory,
+            Manipulation,
+            MastersMend,
+            WasteNotII,
+            Innovation,
+            DelicateSynthesisTraited,
+            BasicTouch,
+            GreatStrides,
+            ByregotsBlessing,
+        ];
+        let (context, _) = setup_1();
+        assert_craft(&context, actions, 1518, 1784, 80, 165);
+    }
+
+    #[test]
+    fn trained_finesse_procs() {
+        let actions = vec![
+            Reflect,
+            WasteNot,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            BasicTouch,
+            StandardTouch,
+            PrudentTouch,
+            PreparatoryTouch,
+        ];
+        let (context, _) = setup_1();
+        let (end_state, _) = Simulator::simulate(&context, actions);
+        // 10 stacks of IQ
+        assert_eq!(10, end_state.buffs.inner_quiet);
+        // should proc Trained Finesse
+        assert!(end_state.available_moves.contains(TrainedFinesse));
+    }
+
+    #[test]
+    fn rotation_should_not_panic_1() {
+        let actions = vec![
+            Reflect,
+            Manipulation,
+            PreparatoryTouch,
+            WasteNotII,
+            PreparatoryTouch,
+            Innovation,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            GreatStrides,
+            ByregotsBlessing,
+            Veneration,
+            GroundworkTraited,
+            GroundworkTraited,
+            GroundworkTraited,
+        ];
+        let (context, _) = setup_1();
+        Simulator::simulate(&context, actions);
+    }
+
+    #[test]
+    fn floating_point_error() {
+        let actions = vec![
+            MuscleMemory,
+            Manipulation,
+            Veneration,
+            WasteNotII,
+            GroundworkTraited,
+            GroundworkTraited,
+            StandardTouch,
+            Innovation,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            GreatStrides,
+            Innovation,
+            PreparatoryTouch,
+            TrainedFinesse,
+            GreatStrides,
+            ByregotsBlessing,
+        ];
+        let (context, _) = setup_2();
+        assert_craft(&context, actions, 3549, 10932, 5, 7);
+    }
+
+    #[test]
+    fn evaluate_matches_simulate_without_variable_conditions() {
+        let (mut context, _) = setup_2();
+        context.conditions_flag = 1;
+        context.condition_seed = Some(0);
+        let actions = vec![
+            MuscleMemory,
+            Manipulation,
+            Veneration,
+            WasteNotII,
+            GroundworkTraited,
+            GroundworkTraited,
+            StandardTouch,
+            Innovation,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            GreatStrides,
+            Innovation,
+            PreparatoryTouch,
+            TrainedFinesse,
+            GreatStrides,
+            ByregotsBlessing,
+            CarefulSynthesisTraited,
+            CarefulSynthesisTraited,
+        ];
+        let (end_state, _) = Simulator::simulate(&context, actions.clone());
+        let evaluation = Simulator::evaluate(&context, &actions, 20);
+
+        assert!((evaluation.finish_rate - 1.0).abs() < f32::EPSILON);
+        assert_eq!(evaluation.quality_p10, end_state.quality);
+        assert_eq!(evaluation.quality_p90, end_state.quality);
+        assert!((evaluation.mean_hq_percent - f32::from(end_state.hq_percent())).abs() < 0.01);
+    }
+
+    #[test]
+    fn search_with_variable_conditions_is_reproducible() {
+        let (context, options) = setup_2();
+        let options = SearchOptions {
+            iterations: 2_000,
+            ..options
+        };
+        let (actions_a, state_a) = Simulator::search_oneshot(&context, vec![], options);
+        let (actions_b, state_b) = Simulator::search_oneshot(&context, vec![], options);
+        assert_eq!(actions_a, actions_b);
+        assert_eq!(state_a.condition, state_b.condition);
+        assert_eq!(state_a.quality, state_b.quality);
+    }
+
+    #[test]
+    fn simulate_replays_condition_seed() {
+        let (mut context, _) = setup_1();
+        let actions = vec![
+            BasicTouch, BasicTouch, BasicTouch, BasicTouch, BasicTouch, BasicTouch,
+        ];
+
+        context.condition_seed = Some(42);
+        let (state_a, _) = Simulator::simulate(&context, actions.clone());
+        let (state_b, _) = Simulator::simulate(&context, actions.clone());
+        assert_eq!(state_a.condition, state_b.condition);
+        assert_eq!(state_a.quality, state_b.quality);
+
+        context.condition_seed = None;
+        let (state, _) = Simulator::simulate(&context, actions);
+        assert_eq!(state.condition, Condition::Normal);
+    }
+
+    #[test]
+    fn simulate_with_conditions_applies_each_step() {
+        let (context, _) = setup_1();
+        let actions = vec![BasicTouch, BasicTouch, BasicTouch];
+        let conditions = [Condition::Normal, Condition::Good, Condition::Excellent];
+
+        let (end_state, result) =
+            Simulator::simulate_with_conditions(&context, actions, &conditions);
+        assert!(result.is_none());
+        assert_eq!(end_state.step, 4);
+        assert_eq!(end_state.condition, Condition::Excellent);
+    }
+
+    #[test]
+    fn condition_multipliers() {
+        let (context, _) = setup_1();
+        let simulate_one = |action: Action, condition: Condition| {
+            let (end_state, _) =
+                Simulator::simulate_with_conditions(&context, vec![action], &[condition]);
+            end_state
+        };
+
+        let normal = simulate_one(BasicTouch, Condition::Normal).quality;
+        assert_eq!(normal, 243);
+        assert_eq!(
+            simulate_one(BasicTouch, Condition::Good).quality,
+            normal * 3 / 2
+        );
+        assert_eq!(
+            simulate_one(BasicTouch, Condition::Excellent).quality,
+            normal * 4
+        );
+        assert_eq!(
+            simulate_one(BasicTouch, Condition::Poor).quality,
+            normal / 2
+        );
+        assert_eq!(
+            simulate_one(BasicTouch, Condition::Malleable).quality,
+            normal
+        );
+
+        let normal = simulate_one(BasicSynthesisTraited, Condition::Normal).progress;
+        assert_eq!(normal, 303);
+        assert_eq!(
+            simulate_one(BasicSynthesisTraited, Condition::Malleable).progress,
+            normal * 3 / 2
+        );
+        assert_eq!(
+            simulate_one(BasicSynthesisTraited, Condition::Good).progress,
+            normal
+        );
+    }
+
+    #[test]
+    fn durability_restoration_is_capped() {
+        let (context, _) = setup_1();
+        let rotations = vec![
+            vec![BasicTouch, MastersMend],
+            vec![BasicTouch, ImmaculateMend],
+            vec![Manipulation, Observe, Observe],
+            vec![BasicTouch, Manipulation, MastersMend],
+            vec![BasicTouch, Manipulation, ImmaculateMend],
+            vec![Manipulation, BasicTouch, MastersMend, ImmaculateMend],
+        ];
+
+        for actions in rotations {
+            for step in 1..=actions.len() {
+                let (state, _) = Simulator::simulate(&context, actions[..step].to_vec());
+                assert!(
+                    state.durability <= context.durability_max,
+                    "{:?} exceeded max durability",
+                    &actions[..step]
+                );
+            }
+
+            let (end_state, _) = Simulator::simulate(&context, actions);
+            assert_eq!(end_state.durability, context.durability_max);
+        }
+    }
+
+    #[test]
+    fn manipulation_doesnt_restore_when_cast() {
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch, Manipulation]);
+        assert_eq!(state.durability, 70);
+        assert_eq!(state.buffs.manipulation, 8);
+
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch, Manipulation, BasicTouch]);
+        assert_eq!(state.durability, 65);
+        assert_eq!(state.buffs.manipulation, 7);
+
+        // refreshing an active Manipulation doesn't restore durability either
+        let (state, _) = Simulator::simulate(
+            &context,
+            vec![BasicTouch, Manipulation, BasicTouch, Manipulation],
+        );
+        assert_eq!(state.durability, 65);
+        assert_eq!(state.buffs.manipulation, 8);
+    }
+
+    #[test]
+    fn diff_reports_changes_between_states() {
+        let (context, _) = setup_1();
+
+        let (before, _) = Simulator::simulate(&context, vec![Veneration]);
+        let (after, _) = Simulator::simulate(&context, vec![Veneration, BasicTouch]);
+        let diff = before.diff(&after);
+        assert_eq!(diff.step, 1);
+        assert_eq!(diff.progress, 0);
+        assert_eq!(diff.quality, 243);
+        assert_eq!(diff.durability, -10);
+        assert_eq!(diff.cp, -18);
+        assert_eq!(diff.buffs.inner_quiet, 1);
+        assert_eq!(diff.buffs.veneration, -1);
+        assert_eq!(diff.buffs.manipulation, 0);
+
+        assert_eq!(after.diff(&after), StateDiff::default());
+    }
+
+    #[test]
+    fn search_more_builds_on_previous_iterations() {
+        let (context, options) = setup_1();
+        let mut sim = Simulator::new(&context, options);
+
+        sim.search_more(500);
+        assert!((sim.tree.get(0).state.visits - 500.0).abs() < f32::EPSILON);
+        let (_, first_state) = sim.solution();
+
+        sim.search_more(500);
+        assert!((sim.tree.get(0).state.visits - 1000.0).abs() < f32::EPSILON);
+        let (_, second_state) = sim.solution();
+        assert!(second_state.max_score >= first_state.max_score);
+    }
+
+    #[test]
+    fn stats_track_the_search() {
+        let (context, options) = setup_2();
+        let mut sim = Simulator::new(&context, options);
+        assert_eq!(sim.stats().iterations, 0);
+        assert!(sim.stats().best_scores.is_empty());
+
+        sim.search_more(2_000).search_more(1_000);
+        let stats = sim.stats();
+        assert_eq!(stats.rng_seed, 123);
+        assert_eq!(stats.iterations, 3_000);
+        assert!(stats.nodes > 1);
+        assert!(!stats.best_scores.is_empty());
+        assert!(stats
+            .best_scores
+            .windows(2)
+            .all(|pair| pair[0].iteration < pair[1].iteration && pair[0].score < pair[1].score));
+        assert!(stats.best_scores.last().unwrap().iteration <= 3_000);
+
+        let usage = &stats.action_usage;
+        assert_eq!(
+            usage.iter().map(|u| u.nodes).sum::<usize>(),
+            stats.nodes - 1
+        );
+        assert!(usage
+            .windows(2)
+            .all(|pair| pair[0].visits >= pair[1].visits));
+        assert!(usage.iter().all(|u| (0.0..=1.0).contains(&u.average_score)));
+    }
+
+    #[test]
+    fn max_nodes_prunes_the_tree() {
+        let (context, mut options) = setup_2();
+        options.iterations = 3_000;
+        let (_, _, stats) = Simulator::search_oneshot_with_stats(&context, vec![], options);
+        assert_eq!(stats.pruned_nodes, 0);
+
+        let options = SearchOptions {
+            score_storage_threshold: Some(0.5),
+            max_nodes: Some(1_000),
+            ..options
+        };
+        let (actions, end_state, stats) =
+            Simulator::search_oneshot_with_stats(&context, vec![], options);
+        assert!(stats.pruned_nodes > 0);
+        assert!(stats.nodes <= 1_000 + usize::from(context.step_max) + 1);
+        assert_eq!(stats.iterations, options.iterations);
+        // the best rotation found is never pruned
+        assert!(!actions.is_empty());
+        assert!(matches!(
+            end_state.check_result(),
+            Some(CraftResult::Finished(_))
+        ));
+    }
+
+    #[test]
+    fn pruned_subtrees_can_be_expanded_again() {
+        let (context, mut options) = setup_2();
+        options.iterations = 500;
+        let mut sim = Simulator::from_context(&context, options);
+        sim.search(0);
+
+        let best_child = sim.best_path()[1];
+        let best_action = sim.tree.get(best_child).state.action.unwrap();
+        let pruned_actions: Vec<Action> = sim
+            .tree
+            .get(0)
+            .children
+            .iter()
+            .filter(|&&i| i != best_child)
+            .filter_map(|&i| sim.tree.get(i).state.action)
+            .collect();
+        assert!(!pruned_actions.is_empty());
+
+        // leaves little more than the best path
+        sim.prune(0, 2);
+        let root = sim.tree.get(0);
+        assert_eq!(root.children.len(), 1);
+        assert!(pruned_actions
+            .iter()
+            .all(|&action| root.state.available_moves.contains(action)));
+        assert!(!root.state.available_moves.contains(best_action));
+        // the root only counts the playouts that are still in the tree
+        let best_visits = sim.tree.get(root.children[0]).state.visits;
+        assert!((root.state.visits - best_visits).abs() < f32::EPSILON);
+
+        sim.run_iterations(0, 100);
+        let expanded: Vec<Action> = sim
+            .tree
+            .get(0)
+            .children
+            .iter()
+            .filter_map(|&i| sim.tree.get(i).state.action)
+            .collect();
+        assert!(pruned_actions
+            .iter()
+            .any(|action| expanded.contains(action)));
+    }
+
+    #[test]
+    fn permissive_search_finishes_when_strict_pruning_cant() {
+        let (context, _) = setup_2();
+        // without any quality actions, quality never reaches the strict
+        // finishing floor
+        let quality_actions: Vec<Action> = Action::ACTIONS
+            .iter()
+            .copied()
+            .filter(|action| action.attributes().quality_efficiency.is_some())
+            .collect();
+        let context = CraftContext {
+            action_pool: {
+                let mut pool = context.action_pool;
+                pool.keep(|action| !quality_actions.contains(action));
+                pool
+            },
+            ..context
+        };
+        let options = SearchOptions {
+            iterations: 2_000,
+            rng_seed: Some(123),
+            require_complete: true,
+            ..Default::default()
+        };
+
+        let (_, state) = Simulator::search_oneshot(&context, vec![], options);
+        assert_eq!(options.check_solution(&state), Err(NoCompleteRotation));
+
+        let permissive = SearchOptions {
+            permissive: true,
+            ..options
+        };
+        let (actions, state) = Simulator::search_oneshot(&context, vec![], permissive);
+        assert_eq!(permissive.check_solution(&state), Ok(()), "{actions:?}");
+        assert!(actions
+            .iter()
+            .all(|action| !quality_actions.contains(action)));
+    }
+
+    #[test]
+    fn collectable_search_reaches_the_target_cheaply() {
+        let recipe = Recipe {
+            recipe_level: 580,
+            job_level: 90,
+            stars: 2,
+            progress: 3900,
+            quality: 10920,
+            durability: 70,
+            progress_div: 130,
+            progress_mod: 80,
+            quality_div: 115,
+            quality_mod: 70,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let search_options = SearchOptions {
+            rng_seed: Some(123),
+            ..Default::default()
+        };
+
+        let found =
+            Simulator::search_collectable(&player, &recipe, 600, &options, search_options, 50)
+                .unwrap();
+        assert!(found.collectability >= 600);
+        assert!(found.cp_spent <= player.cp);
+        assert!((0.0..=1.0).contains(&found.success_rate));
+
+        let context = CraftContext::new(&player, &recipe, options);
+        let (state, result) = Simulator::simulate(&context, found.actions.clone());
+        assert!(matches!(result, Some(CraftResult::Finished(_))));
+        assert_eq!(player.cp - state.cp, found.cp_spent);
+
+        // more than the recipe's max quality
+        assert!(Simulator::search_collectable(
+            &player,
+            &recipe,
+            1_100,
+            &options,
+            search_options,
+            50
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn fixed_point_ln_is_close_to_ln() {
+        assert_eq!(ln_fixed(1), 0);
+        for n in [2_u64, 3, 10, 1_000, 123_456, 10_000_000] {
+            #[allow(clippy::cast_precision_loss)]
+            let expected = (n as f64).ln() * 1e6;
+            #[allow(clippy::cast_precision_loss)]
+            let error = (ln_fixed(n) as f64 - expected).abs();
+            assert!(error <= 2.0, "ln({n}) is off by {error}");
+        }
+    }
+
+    #[test]
+    fn fixed_point_scores_are_reproducible() {
+        let (context, mut options) = setup_2();
+        options.require_complete = true;
+        options.fixed_point_scores = true;
+
+        let mut sim = Simulator::from_context(&context, options);
+        sim.run_iterations(0, options.iterations);
+        // the fixed-point ucb1 ranks nodes like the f32 one, up to rounding
+        let root = &sim.tree.get(0);
+        for &child in &root.children {
+            let child = &sim.tree.get(child).state;
+            #[allow(clippy::cast_precision_loss)]
+            let fixed = sim.ucb1_fixed(child, &root.state) as f32 / 1e6;
+            assert!((fixed - sim.ucb1(child, &root.state)).abs() < 1e-4);
+        }
+
+        let (actions, state) = Simulator::search_oneshot(&context, vec![], options);
+        assert_eq!(options.check_solution(&state), Ok(()));
+        let (repeated, _) = Simulator::search_oneshot(&context, vec![], options);
+        assert_eq!(actions, repeated);
+    }
+
+    #[test]
+    fn pruning_audit_counts_excluded_actions() {
+        let (context, mut options) = setup_2();
+        options.iterations = 2_000;
+        let audit = Simulator::pruning_audit(&context, options, &[1, 2, 3]);
+
+        assert_eq!(audit.searches, 3);
+        assert_eq!(audit.finished, 3);
+        let excluded: u32 = audit.exclusions.iter().map(|e| e.count).sum();
+        assert!(excluded <= audit.finished * u32::from(context.step_max));
+        assert!(audit
+            .exclusions
+            .windows(2)
+            .all(|pair| pair[0].count >= pair[1].count));
+
+        // the seeded searches always find the same rotations
+        let counts: Vec<(PruningRule, Action, u32)> = audit
+            .exclusions
+            .iter()
+            .map(|e| (e.rule, e.action, e.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (PruningRule::ActionHeuristic, Observe, 2),
+                (PruningRule::ObserveFollowUp, BasicSynthesisTraited, 2),
+                (PruningRule::ProgressUnderInnovation, PrudentSynthesis, 2),
+                (PruningRule::ObserveFollowUp, CarefulSynthesisTraited, 1),
+                (PruningRule::ActionHeuristic, WasteNot, 1),
+                (PruningRule::ActionHeuristic, Manipulation, 1),
+                (PruningRule::ActionHeuristic, WasteNotII, 1),
+            ]
+        );
+        let lines: Vec<String> = audit.to_string().lines().map(String::from).collect();
+        assert_eq!(lines.len(), 1 + audit.exclusions.len());
+        assert_eq!(lines[0], "3 of 3 unpruned searches finished");
+        assert_eq!(lines[1], "    2  ActionHeuristic excluded Observe");
+
+        // rules are reported against the state an action was used from
+        let state = CraftState::new(&context).execute(&Observe);
+        assert_eq!(
+            state.pruning_rule(BasicTouch),
+            Some(PruningRule::ObserveFollowUp)
+        );
+        assert_eq!(state.pruning_rule(AdvancedTouch), None);
+    }
+
+    #[test]
+    fn serialized_states_include_their_limits() {
+        let (context, _) = setup_2();
+        let (state, _) = Simulator::simulate(&context, vec![MuscleMemory]);
+        let json = serde_json::to_value(&state).unwrap();
+
+        assert_eq!(json["step"], 2);
+        assert_eq!(json["step_max"], context.step_max);
+        assert_eq!(json["progress"], state.progress);
+        assert_eq!(json["progress_target"], context.progress_target);
+        assert_eq!(json["quality_target"], context.quality_target);
+        assert_eq!(json["durability"], 60);
+        assert_eq!(json["durability_max"], 70);
+        assert_eq!(json["cp_max"], 649);
+        assert_eq!(
+            json["effective_stats"]["craftsmanship"],
+            context.effective_stats.craftsmanship
+        );
+        assert_eq!(json["buffs"]["muscle_memory"], state.buffs.muscle_memory);
+        assert_eq!(json["action"], "MuscleMemory");
+        assert!(json["available_moves"]
+            .as_array()
+            .unwrap()
+            .contains(&"BasicSynthesisTraited".into()));
+        // search statistics stay in the tree
+        for field in ["context", "score_sum", "max_score", "visits"] {
+            assert!(json.get(field).is_none(), "{field}");
+        }
+    }
+
+    #[test]
+    fn serialized_states_continue_the_craft() {
+        let (context, _) = setup_2();
+        let (state, _) = Simulator::simulate(&context, vec![MuscleMemory, Manipulation, Observe]);
+        let json = serde_json::to_string(&state).unwrap();
+        let serialized: SerializedCraftState = serde_json::from_str(&json).unwrap();
+        let restored = CraftState::from_serialized(&context, &serialized);
+
+        let (expected, _) = Simulator::simulate(
+            &context,
+            vec![MuscleMemory, Manipulation, Observe, AdvancedTouch],
+        );
+        let next = restored.execute(&AdvancedTouch);
+        assert_eq!(
+            serde_json::to_string(&next).unwrap(),
+            serde_json::to_string(&expected).unwrap()
+        );
+
+        // step 0 isn't a real step, and would underflow the finished step count
+        let finishing = SerializedCraftState {
+            step: 0,
+            progress: context.progress_target - 1,
+            ..serialized
+        };
+        let restored = CraftState::from_serialized(&context, &finishing);
+        assert_eq!(restored.step, 1);
+        let finished = restored.execute(&BasicSynthesisTraited);
+        assert!(matches!(
+            finished.check_result(),
+            Some(CraftResult::Finished(metrics)) if metrics.steps == 1
+        ));
+    }
+
+    #[test]
+    fn restored_snapshot_matches_original() {
+        let (context, options) = setup_1();
+        let permissive = SearchOptions {
+            permissive: true,
+            ..options
+        };
+        let mut sim = Simulator::new(&context, permissive);
+        sim.search_more(500);
+        let restored = Simulator::restore(&context, &sim.snapshot()).unwrap();
+        assert!(!restored.strict);
+        for (a, b) in restored.tree.nodes.iter().zip(&sim.tree.nodes) {
+            assert_eq!(
+                a.state.available_moves.to_vec(),
+                b.state.available_moves.to_vec()
+            );
+        }
+
+        let mut sim = Simulator::new(&context, options);
+        sim.search_more(500);
+        let snapshot = sim.snapshot();
+
+        let mut restored = Simulator::restore(&context, &snapshot).unwrap();
+        assert_eq!(restored.tree.nodes.len(), sim.tree.nodes.len());
+        for (a, b) in restored.tree.nodes.iter().zip(&sim.tree.nodes) {
+            assert_eq!(a.parent, b.parent);
+            assert_eq!(a.children, b.children);
+            assert_eq!(a.state.action, b.state.action);
+            assert_eq!(a.state.progress, b.state.progress);
+            assert_eq!(a.state.quality, b.state.quality);
+            assert_eq!(a.state.durability, b.state.durability);
+            assert_eq!(a.state.cp, b.state.cp);
+            assert_eq!(
+                a.state.available_moves.to_vec(),
+                b.state.available_moves.to_vec()
+            );
+        }
+        assert_eq!(restored.solution().0, sim.solution().0);
+
+        // searching can continue from where the snapshot left off
+        restored.search_more(500);
+        assert!((restored.tree.get(0).state.visits - 1000.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn restore_rejects_invalid_snapshot() {
+        let (context, options) = setup_1();
+        let mut snapshot = Simulator::new(&context, options).snapshot();
+        assert!(Simulator::restore(&context, &snapshot).is_some());
+
+        // more Manipulations in a row than there's CP for
+        let mut unaffordable = snapshot.clone();
+        let manipulations = context.cp_max / Manipulation.attributes().cp_cost.unwrap() + 1;
+        for i in 0..manipulations as usize {
+            unaffordable.nodes.push(NodeSnapshot {
+                parent: Some(i),
+                action: Some(Manipulation),
+                ..snapshot.nodes[0].clone()
+            });
+        }
+        assert!(Simulator::restore(&context, &unaffordable).is_none());
+        unaffordable.nodes.pop();
+        assert!(Simulator::restore(&context, &unaffordable).is_some());
+
+        snapshot.nodes[0].parent = Some(0);
+        assert!(Simulator::restore(&context, &snapshot).is_none());
+
+        snapshot.nodes.clear();
+        assert!(Simulator::restore(&context, &snapshot).is_none());
+    }
+
+    #[test]
+    fn overshooting_targets_doesnt_inflate_score() {
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(100, 1_000_000, 1_000_000, 577);
+        let craft_options = CraftOptions {
+            max_steps: 25,
+            ..Default::default()
+        };
+        let context = CraftContext::new(&player, &recipe, craft_options);
+
+        let (state, result) =
+            Simulator::simulate(&context, vec![BasicTouch, BasicSynthesisTraited]);
+        assert!(state.quality > state.context.quality_target);
+        assert!(state.progress > state.context.progress_target);
+        assert_eq!(state.hq_percent(), 100);
+
+        match result {
+            Some(CraftResult::Finished(metrics)) => {
+                assert!(metrics.score <= 1.0);
+                assert!((metrics.score - state.score()).abs() < f32::EPSILON);
+                assert_eq!(metrics.quality, state.quality);
+                assert_eq!(metrics.steps, 2);
+                assert_eq!(metrics.hq_percent, 100);
+            }
+            _ => panic!("expected the craft to finish"),
+        }
+    }
+
+    #[test]
+    fn search_fewest_steps_keeps_quality_with_fewer_steps() {
+        let (mut context, options) = setup_2();
+        context.quality_target = 5000;
+        let options = SearchOptions {
+            rng_seed: Some(0),
+            score_storage_threshold: Some(0.0),
+            ..options
+        };
+
+        let (oneshot_actions, _) = Simulator::search_oneshot(&context, vec![], options);
+        let (oneshot_state, _) = Simulator::simulate(&context, oneshot_actions);
+        let (actions, state) = Simulator::search_fewest_steps(&context, vec![], options);
+
+        assert!(matches!(
+            state.check_result(),
+            Some(CraftResult::Finished(_))
+        ));
+        assert_eq!(usize::from(state.step) - 1, actions.len());
+        // the seeded searches always find the same rotations
+        assert_eq!((oneshot_state.step, oneshot_state.quality), (19, 2275));
+        assert_eq!((state.step, state.quality), (18, 2376));
+    }
+
+    #[test]
+    fn shortest_rotation_for_hq_reaches_the_chance() {
+        let recipe = Recipe {
+            recipe_level: 580,
+            job_level: 90,
+            stars: 2,
+            progress: 3900,
+            quality: 10920,
+            durability: 70,
+            progress_div: 130,
+            progress_mod: 80,
+            quality_div: 115,
+            quality_mod: 70,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            ..Default::default()
+        };
+
+        assert_eq!(CraftState::quality_for_hq_percent(10920, 100), 10920);
+        assert_eq!(CraftState::quality_for_hq_percent(10920, 0), 0);
+        let context = CraftContext::new(&player, &recipe, options);
+        let hq_percent = |quality| {
+            CraftState::with_values(&context, 0, quality, 40, 0, Buffs::new()).hq_percent()
+        };
+        let quality = CraftState::quality_for_hq_percent(10920, 50);
+        assert!(hq_percent(quality) >= 50);
+        assert!(hq_percent(quality - 1) < 50);
+
+        let search_options = SearchOptions {
+            iterations: 5_000,
+            rng_seed: Some(0),
+            ..Default::default()
+        };
+        // any finished rotation has at least a 1% chance
+        let actions =
+            Simulator::shortest_rotation_for_hq(&player, &recipe, 1, &options, search_options)
+                .unwrap();
+        let (_, result) = Simulator::simulate(&context, actions);
+        assert!(matches!(result, Some(CraftResult::Finished(_))));
+
+        // without CP for any touches, quality can't be raised at all
+        assert_eq!(
+            Simulator::shortest_rotation_for_hq(
+                &player.with_cp(0),
+                &recipe,
+                50,
+                &options,
+                search_options
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn quality_cp_curve_never_drops_with_more_cp() {
+        let player = Player::new(100, 4747, 4353, 577);
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let search_options = SearchOptions {
+            iterations: 2_000,
+            rng_seed: Some(0),
+            ..Default::default()
+        };
+
+        let cps = [577, 0, 300];
+        let curve = Simulator::quality_cp_curve(&player, &recipe, &options, search_options, &cps);
+        assert_eq!(curve.iter().map(|&(cp, _)| cp).collect::<Vec<_>>(), cps);
+        let (high, none, mid) = (curve[0].1, curve[1].1, curve[2].1);
+        assert!(high.is_some());
+        assert!(none <= mid && mid <= high, "{curve:?}");
+    }
+
+    #[test]
+    fn minimum_cp_for_hq_is_the_lowest_passing_cp() {
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(100, 4747, 4353, 577);
+        let options = CraftOptions {
+            max_steps: 25,
+            quality_target: Some(3000),
+            use_manipulation: true,
+            ..Default::default()
+        };
+
+        let cp = Simulator::minimum_cp_for_hq(&player, &recipe, &options).unwrap();
+        assert!(cp < player.cp);
+        let reaches_target = |cp| {
+            let context = CraftContext::new(&player.with_cp(cp), &recipe, options);
+            Simulator::can_reach_quality(&context, 3000)
+        };
+        assert!(reaches_target(cp));
+        assert!(!reaches_target(cp - 1));
+
+        let unreachable = CraftOptions {
+            quality_target: None,
+            ..options
+        };
+        assert_eq!(
+            Simulator::minimum_cp_for_hq(&player.with_cp(100), &recipe, &unreachable),
+            None
+        );
+    }
+
+    #[test]
+    fn sensitivity_searches_each_raised_stat() {
+        let recipe = Recipe {
+            recipe_level: 580,
+            job_level: 90,
+            stars: 2,
+            progress: 3900,
+            quality: 10920,
+            durability: 70,
+            progress_div: 130,
+            progress_mod: 80,
+            quality_div: 115,
+            quality_mod: 70,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let search_options = SearchOptions {
+            iterations: 2_000,
+            rng_seed: Some(0),
+            ..Default::default()
+        };
+
+        let sensitivity = Simulator::sensitivity(&player, &recipe, &options, search_options, 100);
+        let best_quality = |player: &Player| {
+            Simulator::max_reachable_quality(player, &recipe, &options, search_options)
+        };
+        let base_quality = best_quality(&player);
+        assert_eq!(sensitivity.quality, base_quality);
+        assert_eq!(sensitivity.stat_delta, 100);
+
+        let cp_quality = best_quality(&player.with_cp(749));
+        assert_eq!(sensitivity.cp.quality, cp_quality);
+        assert_eq!(
+            sensitivity.cp.hq_percent,
+            cp_quality.map(|quality| CraftState::hq_percent_for_quality(quality, 10920))
+        );
+        if let (Some(base), Some(quality), Some(per_point)) =
+            (base_quality, cp_quality, sensitivity.cp.quality_per_point)
+        {
+            let expected = (f64::from(quality) - f64::from(base)) / 100.0;
+            assert!((f64::from(per_point) - expected).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn max_reachable_quality_ignores_quality_targets() {
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 1,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(100, 4747, 4353, 577);
+        let options = CraftOptions {
+            max_steps: 25,
+            quality_target: Some(1000),
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let search_options = SearchOptions {
+            iterations: 5_000,
+            rng_seed: Some(0),
+            ..Default::default()
+        };
+
+        let quality =
+            Simulator::max_reachable_quality(&player, &recipe, &options, search_options).unwrap();
+        assert!(quality > 1000);
+        assert!(quality <= recipe.quality);
+
+        let too_much_progress = Recipe {
+            progress: 100_000,
+            ..recipe
+        };
+        assert_eq!(
+            Simulator::max_reachable_quality(&player, &too_much_progress, &options, search_options),
+            None
+        );
+    }
+
+    #[test]
+    fn search_min_cp_reaches_target_with_less_cp() {
+        let (context, options) = setup_1();
+        // conditions are disabled so the search and the simulated replay agree
+        let context = CraftContext {
+            quality_target: 3000,
+            conditions_flag: 1,
+            ..context
+        };
+        let options = SearchOptions {
+            score_storage_threshold: Some(0.0),
+            ..options
+        };
+
+        let (oneshot_actions, _) = Simulator::search_oneshot(&context, vec![], options);
+        let (oneshot_state, _) = Simulator::simulate(&context, oneshot_actions);
+        let (_, state) = Simulator::search_min_cp(&context, vec![], options);
+
+        assert!(matches!(
+            state.check_result(),
+            Some(CraftResult::Finished(_))
+        ));
+        assert!(state.quality >= context.quality_target);
+        assert!(state.cp >= oneshot_state.cp);
+    }
+
+    #[test]
+    fn pareto_front_is_nondominated() {
+        let (context, options) = setup_1();
+        assert!(Condition::is_variable(context.conditions_flag));
+        let mut sim = Simulator::new(
+            &context,
+            SearchOptions {
+                score_storage_threshold: Some(0.0),
+                ..options
+            },
+        );
+        sim.search_more(2_000);
+
+        let front = sim.pareto_front();
+        assert!(!front.is_empty());
+        for (actions, state) in &front {
+            // simulating without a condition seed keeps every step Normal
+            let (simulated, result) = Simulator::simulate(&context, actions.clone());
+            assert!(matches!(result, Some(CraftResult::Finished(_))));
+            assert_eq!(
+                (simulated.quality, simulated.step, simulated.cp),
+                (state.quality, state.step, state.cp)
+            );
+        }
+        for pair in front.windows(2) {
+            let (a, b) = (&pair[0].1, &pair[1].1);
+            assert!(a.quality.min(context.quality_target) >= b.quality.min(context.quality_target));
+            // lower quality is only on the front if it's better in another way
+            assert!(b.step < a.step || b.cp > a.cp);
+        }
+    }
+
+    #[test]
+    fn search_reports_progress_and_can_stop() {
+        let (context, options) = setup_1();
+
+        let reports = std::cell::RefCell::new(vec![]);
+        Simulator::new(&context, options).search_more_with_progress(1_000, 300, &|progress| {
+            reports.borrow_mut().push(progress);
+            true
+        });
+        let reports = reports.into_inner();
+        let iterations: Vec<u32> = reports.iter().map(|p| p.iterations).collect();
+        assert_eq!(iterations, vec![300, 600, 900, 1_000]);
+        assert!(reports
+            .windows(2)
+            .all(|pair| pair[0].max_score <= pair[1].max_score));
+
+        let mut sim = Simulator::new(&context, options);
+        sim.search_more_with_progress(1_000, 300, &|progress| progress.iterations < 600);
+        assert!((sim.tree.get(0).state.visits - 600.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn refined_touch_combo_grants_extra_inner_quiet() {
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch, RefinedTouch]);
+        assert_eq!(state.buffs.inner_quiet, 3);
+
+        // other touches only grant a single stack, even after Basic Touch
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch, StandardTouch]);
+        assert_eq!(state.buffs.inner_quiet, 2);
+
+        // Refined Touch can't be used without the combo
+        let (_, result) = Simulator::simulate(&context, vec![RefinedTouch]);
+        assert!(matches!(result, Some(CraftResult::InvalidActionFailure)));
+        let (_, result) = Simulator::simulate(&context, vec![BasicTouch, Observe, RefinedTouch]);
+        assert!(matches!(result, Some(CraftResult::InvalidActionFailure)));
+    }
+
+    #[test]
+    fn combos_reduce_cp_cost() {
+        let (context, _) = setup_1();
+
+        let (state, _) =
+            Simulator::simulate(&context, vec![BasicTouch, StandardTouch, AdvancedTouch]);
+        assert_eq!(state.cp, 577 - 18 * 3);
+
+        // Standard Touch only continues a combo after Basic Touch
+        let (state, _) = Simulator::simulate(&context, vec![StandardTouch, AdvancedTouch]);
+        assert_eq!(state.cp, 577 - 32 - 46);
+        assert_eq!(state.previous_combo_action, None);
+
+        // a combo should be usable with only enough CP for its reduced cost
+        let context = CraftContext {
+            cp_max: 36,
+            ..context
+        };
+        let (state, result) = Simulator::simulate(&context, vec![BasicTouch, StandardTouch]);
+        assert!(result.is_none());
+        assert_eq!(state.cp, 0);
+    }
+
+    #[test]
+    fn advanced_touch_combo_cp_cost() {
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![Observe]);
+        assert_eq!(state.previous_combo_action, Some(Observe));
+        let (state, _) = Simulator::simulate(&context, vec![Observe, AdvancedTouch]);
+        assert_eq!(state.cp, 577 - 7 - 18);
+        assert_eq!(state.previous_combo_action, None);
+
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch, StandardTouch]);
+        assert_eq!(state.previous_combo_action, Some(StandardTouch));
+        let (state, _) =
+            Simulator::simulate(&context, vec![BasicTouch, StandardTouch, AdvancedTouch]);
+        assert_eq!(state.cp, 577 - 18 - 18 - 18);
+        assert_eq!(state.previous_combo_action, None);
+
+        // the combo is broken by anything in between
+        let (state, _) = Simulator::simulate(&context, vec![Observe, Veneration, AdvancedTouch]);
+        assert_eq!(state.cp, 577 - 7 - 18 - 46);
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch, Veneration, StandardTouch]);
+        assert_eq!(state.cp, 577 - 18 - 18 - 32);
+    }
+
+    #[test]
+    fn can_use_reports_failing_rule() {
+        use InvalidActionReason::*;
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![]);
+        assert_eq!(state.can_use(Reflect), Ok(()));
+        assert_eq!(state.can_use(ByregotsBlessing), Err(NotEnoughInnerQuiet));
+        assert_eq!(state.can_use(RefinedTouch), Err(ComboRequired));
+        // replaced by its traited version
+        assert_eq!(state.can_use(BasicSynthesis), Err(Unavailable));
+
+        let (state, _) = Simulator::simulate(&context, vec![Reflect]);
+        assert_eq!(state.can_use(Reflect), Err(FirstStepOnly));
+        assert_eq!(state.can_use(ByregotsBlessing), Ok(()));
+
+        let (state, _) = Simulator::simulate(&context, vec![Observe]);
+        assert_eq!(state.can_use(Observe), Err(AlreadyObserving));
+
+        let (state, _) = Simulator::simulate(&context, vec![WasteNot]);
+        assert_eq!(state.can_use(PrudentTouch), Err(WasteNotActive));
+
+        let low_cp = CraftContext {
+            cp_max: 10,
+            ..context.clone()
+        };
+        let (state, _) = Simulator::simulate(&low_cp, vec![]);
+        assert_eq!(state.can_use(BasicTouch), Err(NotEnoughCp));
+
+        let no_manipulation = CraftContext {
+            use_manipulation: false,
+            ..context.clone()
+        };
+        let (state, _) = Simulator::simulate(&no_manipulation, vec![]);
+        assert_eq!(state.can_use(Manipulation), Err(ManipulationDisabled));
+
+        let (state, result) = Simulator::simulate(&context, vec![BasicSynthesisTraited; 8]);
+        assert!(matches!(result, Some(CraftResult::DurabilityFailure)));
+        assert_eq!(state.can_use(BasicTouch), Err(CraftOver));
+
+        // agrees with the moves the simulator considers available
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch, Veneration]);
+        for action in Action::ACTIONS {
+            assert_eq!(
+                state.can_use(*action).is_ok(),
+                state.available_moves.contains(*action)
+            );
+        }
+    }
+
+    #[test]
+    fn stepwise_search_confirms_candidates() {
+        let (context, options) = setup_2();
+        let options = SearchOptions {
+            iterations: 2_000,
+            rng_seed: Some(0),
+            stepwise_candidates: Some(3),
+            score_storage_threshold: None,
+            ..options
+        };
+
+        // repeat search_stepwise's first step, scoring each candidate's
+        // Normal-replayed rotation
+        let state = Simulator::simulate(&context, vec![]).0.clone_strict();
+        let search_options = options.with_stepwise_defaults();
+        let mut sim = Simulator::from_state(state.clone(), search_options);
+        sim.reset(state.clone());
+        let (_, solution_state) = sim.search(0).solution();
+        assert!(!matches!(
+            solution_state.check_result(),
+            Some(CraftResult::Finished(metrics)) if metrics.score >= 1.0
+        ));
+        let candidates = sim.best_first_actions(3);
+        assert_eq!(candidates.len(), 3);
+        let scores: Vec<f32> = candidates
+            .iter()
+            .map(|&action| sim.confirmation_score(&state, action, search_options.iterations / 4))
+            .collect();
+        let best_score = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let best_action = candidates[scores.iter().position(|&s| s >= best_score).unwrap()];
+
+        let (actions, state) = Simulator::search_stepwise(&context, vec![], options, None);
+        assert_eq!(actions[0], best_action);
+        assert_eq!(
+            Simulator::search_stepwise(&context, vec![], options, None).0,
+            actions
+        );
+        let (replayed_state, result) = Simulator::simulate(&context, actions);
+        assert!(matches!(result, Some(CraftResult::Finished(_))));
+        assert_eq!(replayed_state.quality, state.quality);
+    }
+
+    #[test]
+    fn tree_nodes_keep_normal_conditions() {
+        let (context, mut options) = setup_2();
+        assert_eq!(context.conditions_flag, 15);
+        options.iterations = 5_000;
+        options.score_storage_threshold = Some(0.0);
+
+        let mut sim = Simulator::from_context(&context, options);
+        sim.search(0);
+        // playouts roll conditions, but none of them are stored in the tree
+        assert!(sim
+            .tree
+            .nodes
+            .iter()
+            .all(|node| node.state.condition == Condition::Normal));
+
+        let (actions, state) = sim.solution();
+        let (replayed, _) = Simulator::simulate(&context, actions);
+        assert_eq!(state.condition, Condition::Normal);
+        assert_eq!(
+            (state.progress, state.quality, state.cp),
+            (replayed.progress, replayed.quality, replayed.cp)
+        );
+    }
+
+    #[test]
+    fn stepwise_search_reaches_known_quality() {
+        let (context, options) = setup_2();
+        let options = SearchOptions {
+            iterations: 20_000,
+            ..options
+        };
+        let (actions, state) = Simulator::search_stepwise(&context, vec![], options, None);
+        assert!(matches!(
+            state.check_result(),
+            Some(CraftResult::Finished(_))
+        ));
+        // a seeded search found 9012 quality with these iterations, which are
+        // far fewer than the web app uses; leave a little room for tweaks
+        assert!(
+            state.quality >= 5000,
+            "{} quality: {actions:?}",
+            state.quality
+        );
+    }
+
+    #[test]
+    fn suggest_next_action_reuses_snapshot() {
+        let (context, options) = setup_1();
+        let options = SearchOptions {
+            iterations: 1_000,
+            ..options
+        };
+
+        let first = Simulator::suggest_next_action(&context, &[], options, None).unwrap();
+        assert!(first.confidence > 0.0 && first.confidence <= 1.0);
+        // the suggestion is the most visited first action
+        let root_visits = first.snapshot.nodes[0].visits;
+        let most_visited = first
+            .snapshot
+            .nodes
+            .iter()
+            .filter(|node| node.parent == Some(0))
+            .max_by(|a, b| a.visits.partial_cmp(&b.visits).unwrap())
+            .unwrap();
+        assert_eq!(most_visited.action, Some(first.action));
+        assert!((first.confidence - most_visited.visits / root_visits).abs() < 1e-6);
+        let (state, _) = Simulator::simulate(&context, vec![]);
+        assert!(state.can_use(first.action).is_ok());
+
+        let history = [first.action];
+        let second =
+            Simulator::suggest_next_action(&context, &history, options, Some(&first.snapshot))
+                .unwrap();
+        assert!(second.snapshot.nodes.len() > first.snapshot.nodes.len());
+        // the search continued from the node for the suggested action
+        let node = second
+            .snapshot
+            .nodes
+            .iter()
+            .find(|node| node.parent == Some(0) && node.action == Some(first.action))
+            .unwrap();
+        assert!(node.visits >= 1_000.0);
+
+        // history that can't be played doesn't get a suggestion
+        assert!(
+            Simulator::suggest_next_action(&context, &[ByregotsBlessing], options, None).is_none()
+        );
+    }
+
+    #[test]
+    fn durability_costs_under_buffs() {
+        let (context, _) = setup_1();
+        let (normal, _) = Simulator::simulate(&context, vec![]);
+        let (waste_not, _) = Simulator::simulate(&context, vec![WasteNot]);
+        let (waste_not_ii, _) = Simulator::simulate(&context, vec![WasteNotII]);
+        let (trained_perfection, _) = Simulator::simulate(&context, vec![TrainedPerfection]);
+
+        // (action, normal cost, cost under Waste Not)
+        let expected_costs = [
+            (BasicSynthesis, 10, 5),
+            (BasicTouch, 10, 5),
+            (StandardTouch, 10, 5),
+            (BasicSynthesisTraited, 10, 5),
+            (ByregotsBlessing, 10, 5),
+            (MuscleMemory, 10, 5),
+            (CarefulSynthesis, 10, 5),
+            (AdvancedTouch, 10, 5),
+            (Reflect, 10, 5),
+            (PreparatoryTouch, 20, 10),
+            (Groundwork, 20, 10),
+            (DelicateSynthesis, 10, 5),
+            (CarefulSynthesisTraited, 10, 5),
+            (GroundworkTraited, 20, 10),
+            (RefinedTouch, 10, 5),
+            (DelicateSynthesisTraited, 10, 5),
+            // Prudent actions can't be used under Waste Not, so odd costs are
+            // never actually halved in game
+            (PrudentTouch, 5, 2),
+            (PrudentSynthesis, 5, 2),
+        ];
+
+        let costing_actions = Action::ACTIONS
+            .iter()
+            .filter(|action| action.attributes().durability_cost.unwrap_or(0) > 0)
+            .count();
+        assert_eq!(costing_actions, expected_costs.len());
+
+        for (action, cost, halved_cost) in expected_costs {
+            let base_cost = action.attributes().durability_cost.unwrap();
+            assert_eq!(Action::calc_durability_cost(&normal, base_cost), cost);
+            assert_eq!(
+                Action::calc_durability_cost(&waste_not, base_cost),
+                halved_cost
+            );
+            assert_eq!(
+                Action::calc_durability_cost(&waste_not_ii, base_cost),
+                halved_cost
+            );
+            assert_eq!(
+                Action::calc_durability_cost(&trained_perfection, base_cost),
+                0
+            );
+        }
+        assert_eq!(
+            waste_not.can_use(PrudentTouch),
+            Err(InvalidActionReason::WasteNotActive)
+        );
+    }
+
+    #[test]
+    fn combo_follow_ups_match_combo_starters() {
+        assert_eq!(
+            BasicTouch.combo_follow_ups(),
+            vec![StandardTouch, RefinedTouch]
+        );
+        assert_eq!(StandardTouch.combo_follow_ups(), vec![AdvancedTouch]);
+        assert_eq!(Observe.combo_follow_ups(), vec![AdvancedTouch]);
+        assert!(AdvancedTouch.combo_follow_ups().is_empty());
+
+        for action in Action::ACTIONS {
+            assert_eq!(
+                !action.combo_follow_ups().is_empty(),
+                Action::COMBO_STARTERS.contains(action),
+                "{action:?}"
+            );
+            assert_eq!(
+                action.metadata().combo_follow_ups,
+                action.combo_follow_ups()
+            );
+        }
+    }
+
+    #[test]
+    fn buff_effects_match_execution() {
+        let (context, _) = setup_1();
+        let buffs = Buffs {
+            inner_quiet: 5,
+            waste_not: 3,
+            waste_not_ii: 0,
+            manipulation: 3,
+            great_strides: 3,
+            innovation: 3,
+            veneration: 3,
+            muscle_memory: 3,
+        };
+        let start = CraftState::with_values(&context, 0, 0, 80, 500, buffs.clone());
+        let value = |buffs: &Buffs, kind: BuffKind| {
+            buffs
+                .active()
+                .into_iter()
+                .find(|&(active, _)| active == kind)
+                .map_or(0, |(_, value)| value)
+        };
+
+        // actions are executed even where they couldn't be used, e.g. Reflect
+        // after the first step, except Careful Observation which needs a
+        // specialist
+        for &action in Action::ACTIONS {
+            if action == CarefulObservation {
+                continue;
+            }
+            let effects = action.buff_effects();
+            let state = start.execute(&action);
+            for (kind, granted) in &effects.granted {
+                let expected = if *kind == BuffKind::InnerQuiet {
+                    buffs.inner_quiet + granted
+                } else {
+                    *granted
+                };
+                assert_eq!(value(&state.buffs, *kind), expected, "{action:?}");
+            }
+            for kind in &effects.consumed {
+                if effects.granted.iter().all(|(granted, _)| granted != kind) {
+                    assert_eq!(value(&state.buffs, *kind), 0, "{action:?} {kind:?}");
+                }
+            }
+        }
+
+        assert_eq!(
+            ByregotsBlessing.buff_effects().consumed,
+            vec![BuffKind::InnerQuiet, BuffKind::GreatStrides]
+        );
+        assert_eq!(
+            DelicateSynthesis.buff_effects().consumed,
+            vec![BuffKind::MuscleMemory, BuffKind::GreatStrides]
+        );
+        assert!(Observe.buff_effects().consumed.is_empty());
+        assert!(Observe.buff_effects().granted.is_empty());
+    }
+
+    #[test]
+    fn buff_metadata_matches_actions() {
+        for &kind in BuffKind::BUFFS {
+            let metadata = kind.metadata();
+            assert_eq!(metadata.is_stack, kind == BuffKind::InnerQuiet);
+            if metadata.is_stack {
+                assert_eq!(metadata.max_value, crate::MAX_INNER_QUIET);
+                continue;
+            }
+            // the longest timer any action grants
+            let longest = Action::ACTIONS
+                .iter()
+                .flat_map(|action| action.buff_effects().granted)
+                .filter(|&(granted, _)| granted == kind)
+                .map(|(_, duration)| duration)
+                .max();
+            assert_eq!(longest, Some(metadata.max_value), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn action_metadata_is_consistent() {
+        use crate::ActionCategory;
+
+        for action in Action::ACTIONS {
+            let metadata = action.metadata();
+            assert_eq!(metadata.label, action.label());
+            assert_eq!(metadata.level, action.attributes().level);
+            // actions with the same label are traited versions that share an id
+            for other in Action::ACTIONS {
+                assert_eq!(
+                    action.game_id() == other.game_id(),
+                    action.label() == other.label()
+                );
+            }
+        }
+
+        assert_eq!(BasicSynthesisTraited.game_id(), 100_001);
+        assert_eq!(DelicateSynthesis.category(), ActionCategory::Synthesis);
+        assert_eq!(ByregotsBlessing.category(), ActionCategory::Touch);
+        assert_eq!(ImmaculateMend.category(), ActionCategory::Repair);
+        assert_eq!(Manipulation.category(), ActionCategory::Buff);
+    }
+
+    #[test]
+    fn quick_checks_for_finishing() {
+        let (context, _) = setup_1();
+        assert!(Simulator::can_complete(&context));
+        assert!(Simulator::can_reach_quality(&context, 3_000));
+
+        let impossible = CraftContext {
+            progress_target: 100_000,
+            ..context.clone()
+        };
+        assert!(!Simulator::can_complete(&impossible));
+        assert!(!Simulator::can_reach_quality(&context, 100_000));
+    }
+
+    #[test]
+    fn progress_rotation_finishes_without_quality() {
+        let (context, _) = setup_1();
+        let actions = Simulator::progress_rotation(&context).unwrap();
+        assert_eq!(actions[..2], [MuscleMemory, Veneration]);
+        let (_, result) = Simulator::simulate(&context, actions.clone());
+        assert!(matches!(result, Some(CraftResult::Finished(_))));
+        assert!(actions.len() <= 8, "{actions:?}");
+        assert_eq!(Simulator::progress_rotation(&context), Some(actions));
+
+        let impossible = CraftContext {
+            progress_target: 100_000,
+            ..context
+        };
+        assert_eq!(Simulator::progress_rotation(&impossible), None);
+    }
+
+    #[test]
+    fn standard_rotation_finishes_with_quality() {
+        for (context, _) in [setup_1(), setup_2()] {
+            let actions = Simulator::standard_rotation(&context).unwrap();
+            let (state, result) = Simulator::simulate(&context, actions.clone());
+            assert!(
+                matches!(result, Some(CraftResult::Finished(_))),
+                "{actions:?}"
+            );
+            assert!(state.quality > 0, "{actions:?}");
+            assert!(actions.contains(&Innovation), "{actions:?}");
+        }
+
+        let (context, _) = setup_1();
+        let impossible = CraftContext {
+            progress_target: 100_000,
+            ..context
+        };
+        assert_eq!(Simulator::standard_rotation(&impossible), None);
+    }
+
+    #[test]
+    fn finishing_quality_floor_can_be_lowered() {
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(90, 3290, 3541, 649);
+        let context = |finishing_quality_percent| {
+            let craft_options = CraftOptions {
+                max_steps: 25,
+                finishing_quality_percent,
+                ..Default::default()
+            };
+            CraftContext::new(&player, &recipe, craft_options)
+        };
+        let finishing_rule = |context: &CraftContext| {
+            let progress = context.progress_target - 1;
+            CraftState::with_values(context, progress, 0, 40, 300, Buffs::new())
+                .pruning_rule(BasicSynthesisTraited)
+        };
+
+        let default = context(None);
+        assert_eq!(default.finishing_quality_floor, default.quality_target / 5);
+        assert_eq!(finishing_rule(&default), Some(PruningRule::EarlyFinish));
+        assert_eq!(finishing_rule(&context(Some(0))), None);
+        assert_eq!(context(Some(100)).finishing_quality_floor, recipe.quality);
+
+        let invalid = CraftOptions {
+            max_steps: 25,
+            finishing_quality_percent: Some(101),
+            ..Default::default()
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(CraftContextError::FinishingQualityOutOfRange(101))
+        );
+    }
+
+    #[test]
+    fn finishing_action_can_use_the_last_durability() {
+        let (context, _) = setup_1();
+        let finishing = CraftState::with_values(
+            &context,
+            context.progress_target - 1,
+            context.quality_target,
+            10,
+            100,
+            Buffs::new(),
+        );
+        assert_eq!(finishing.can_use(BasicSynthesisTraited), Ok(()));
+        assert!(finishing
+            .clone_strict()
+            .available_moves
+            .contains(BasicSynthesisTraited));
+
+        let finished = finishing.execute(&BasicSynthesisTraited);
+        assert_eq!(finished.durability, 0);
+        assert!(matches!(
+            finished.check_result(),
+            Some(CraftResult::Finished(metrics)) if metrics.durability == 0
+        ));
+
+        // the same action fails the craft if it doesn't finish it
+        let unfinished = CraftState::with_values(&context, 0, 0, 10, 100, Buffs::new())
+            .execute(&BasicSynthesisTraited);
+        assert_eq!(unfinished.durability, 0);
+        assert!(matches!(
+            unfinished.check_result(),
+            Some(CraftResult::DurabilityFailure)
+        ));
+    }
+
+    #[test]
+    fn maxed_starting_quality_skips_quality_moves() {
+        let (context, options) = setup_2();
+        let quality_moves = |actions: &[Action]| {
+            actions
+                .iter()
+                .filter(|action| {
+                    action.attributes().quality_efficiency.is_some()
+                        || matches!(action, Innovation | GreatStrides | QuickInnovation)
+                })
+                .count()
+        };
+
+        for starting_quality in [context.quality_target, context.quality_target + 500] {
+            let context = CraftContext {
+                starting_quality,
+                ..context.clone()
+            };
+            let (actions, state) = Simulator::search_oneshot(&context, vec![], options);
+            assert!(matches!(
+                state.check_result(),
+                Some(CraftResult::Finished(_))
+            ));
+            assert_eq!(quality_moves(&actions), 0, "{actions:?}");
+        }
+
+        // Trained Eye isn't forced as the opener when there's no quality to add
+        let player = Player::new(100, 3290, 3541, 649);
+        let recipe = crate::data::recipes(90)[0];
+        let craft_options = CraftOptions {
+            max_steps: 25,
+            starting_quality: Some(recipe.quality),
+            ..Default::default()
+        };
+        let context = CraftContext::new(&player, &recipe, craft_options);
+        assert!(context.action_pool.contains(TrainedEye));
+        let (actions, state) = Simulator::search_oneshot(&context, vec![], options);
+        assert!(matches!(
+            state.check_result(),
+            Some(CraftResult::Finished(_))
+        ));
+        assert_eq!(quality_moves(&actions), 0, "{actions:?}");
+    }
+
+    #[test]
+    fn buff_timers_count_down_after_the_cast_step() {
+        let (context, _) = setup_1();
+        let remaining = |actions: Vec<Action>, kind: BuffKind| {
+            let (state, _) = Simulator::simulate(&context, actions);
+            state
+                .buffs
+                .active()
+                .into_iter()
+                .find(|&(active, _)| active == kind)
+                .map_or(0, |(_, steps)| steps)
+        };
+
+        for (buff, kind, duration) in [
+            (WasteNot, BuffKind::WasteNot, WASTE_NOT_DURATION),
+            (WasteNotII, BuffKind::WasteNot, WASTE_NOT_II_DURATION),
+            (Manipulation, BuffKind::Manipulation, MANIPULATION_DURATION),
+            (GreatStrides, BuffKind::GreatStrides, GREAT_STRIDES_DURATION),
+            (Innovation, BuffKind::Innovation, INNOVATION_DURATION),
+            (Veneration, BuffKind::Veneration, VENERATION_DURATION),
+            (MuscleMemory, BuffKind::MuscleMemory, MUSCLE_MEMORY_DURATION),
+        ] {
+            // a buff is applied after timers count down, so it starts at its full duration
+            assert_eq!(remaining(vec![buff], kind), duration, "{buff:?}");
+            assert_eq!(
+                remaining(vec![buff, Observe], kind),
+                duration - 1,
+                "{buff:?}"
+            );
+            assert_eq!(
+                remaining(vec![buff, Observe, MastersMend], kind),
+                duration - 2,
+                "{buff:?}"
+            );
+        }
+
+        // buffs that were already active still count down on the step another is cast
+        let (state, _) = Simulator::simulate(&context, vec![Innovation, GreatStrides]);
+        assert_eq!(state.buffs.innovation, INNOVATION_DURATION - 1);
+        assert_eq!(state.buffs.great_strides, GREAT_STRIDES_DURATION);
+    }
+
+    #[test]
+    fn waste_not_buffs_are_reported_together() {
+        let buffs = Buffs {
+            waste_not: 2,
+            waste_not_ii: 5,
+            ..Buffs::new()
+        };
+        assert_eq!(buffs.waste_not_remaining(), 5);
+        assert_eq!(buffs.active(), vec![(BuffKind::WasteNot, 5)]);
+
+        let buffs = Buffs {
+            waste_not: 3,
+            ..Buffs::new()
+        };
+        assert_eq!(buffs.waste_not_remaining(), 3);
+        assert_eq!(Buffs::new().waste_not_remaining(), 0);
+    }
+
+    #[test]
+    fn recasting_buffs_refreshes_duration() {
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![Innovation, BasicTouch, BasicTouch]);
+        assert_eq!(state.buffs.innovation, 2);
+        assert!(state.can_use(Innovation).is_ok());
+        let (state, _) = Simulator::simulate(
+            &context,
+            vec![Innovation, BasicTouch, BasicTouch, Innovation],
+        );
+        assert_eq!(state.buffs.innovation, INNOVATION_DURATION);
+
+        let (state, _) = Simulator::simulate(
+            &context,
+            vec![Veneration, BasicSynthesisTraited, Veneration],
+        );
+        assert_eq!(state.buffs.veneration, VENERATION_DURATION);
+    }
+
+    #[test]
+    fn careful_observation_does_not_take_a_step() {
+        let (context, _) = setup_1();
+        let context = CraftContext {
+            player_is_specialist: true,
+            ..context
+        };
+
+        let (state, _) = Simulator::simulate(
+            &context,
+            vec![
+                Innovation,
+                CarefulObservation,
+                CarefulObservation,
+                CarefulObservation,
+            ],
+        );
+        assert_eq!(state.step, 2);
+        assert_eq!(state.buffs.innovation, INNOVATION_DURATION);
+        assert_eq!(state.careful_observations_remaining, 0);
+        assert_eq!(
+            state.can_use(CarefulObservation),
+            Err(InvalidActionReason::NoUsesRemaining)
+        );
+        // Reflect is still usable, since no step has been taken
+        let (state, _) = Simulator::simulate(&context, vec![CarefulObservation, Reflect]);
+        assert_eq!(state.step, 2);
+
+        // only specialists can use it
+        let (context, _) = setup_1();
+        let (state, _) = Simulator::simulate(&context, vec![]);
+        assert_eq!(
+            state.can_use(CarefulObservation),
+            Err(InvalidActionReason::NoUsesRemaining)
+        );
+    }
+
+    #[test]
+    fn oneshot_search_keeps_prefix() {
+        let (context, options) = setup_2();
+        let prefix = vec![MuscleMemory, Veneration, GroundworkTraited];
+
+        let (actions, state) = Simulator::search_oneshot(&context, prefix.clone(), options);
+        assert_eq!(actions[..prefix.len()], prefix);
+        let (simulated, _) = Simulator::simulate(&context, actions);
+        assert_eq!(simulated.step, state.step);
+        assert_eq!(simulated.progress, state.progress);
+
+        // a prefix that already ends the craft is returned as is
+        let (mut context, _) = setup_2();
+        context.step_max = 3;
+        let (actions, _) = Simulator::search_oneshot(&context, prefix.clone(), options);
+        assert_eq!(actions, prefix);
+    }
+
+    #[test]
+    fn preflight_checks_feasibility() {
+        let (context, _) = setup_1();
+        let recipe = Recipe {
+            recipe_level: 690,
+            job_level: 100,
+            stars: 0,
+            progress: 6600,
+            quality: 12000,
+            durability: 80,
+            progress_div: 170,
+            progress_mod: 90,
+            quality_div: 150,
+            quality_mod: 75,
+            is_expert: false,
+            conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
+        };
+        let player = Player::new(100, 4747, 4353, 577);
+        let options = CraftOptions {
+            max_steps: context.step_max,
+            use_manipulation: true,
+            player_is_specialist: true,
+            ..Default::default()
+        };
+
+        let preflight = Simulator::preflight(&player, &recipe, &options);
+        assert!(preflight.meets_level_requirement);
+        assert!(preflight.meets_craftsmanship_requirement);
+        assert!(preflight.meets_control_requirement);
+        assert!(preflight.can_complete);
+        assert!(preflight.recipe_modifiers_apply);
+        assert!(preflight.specialist_actions_available);
+
+        let preflight = Simulator::preflight(&player.with_job_level(50), &recipe, &options);
+        assert!(!preflight.meets_level_requirement);
+        assert!(!preflight.can_complete);
+        assert!(preflight.recipe_modifiers_apply);
+        assert!(!preflight.specialist_actions_available);
+
+        // the player's stats exactly meet these requirements
+        let required = Recipe {
+            required_craftsmanship: player.craftsmanship,
+            required_control: player.control,
+            ..recipe
+        };
+        let preflight = Simulator::preflight(&player, &required, &options);
+        assert!(preflight.meets_craftsmanship_requirement);
+        assert!(preflight.meets_control_requirement);
+        assert!(preflight.can_complete);
+        let preflight = Simulator::preflight(
+            &player.with_craftsmanship(player.craftsmanship - 1),
+            &required,
+            &options,
+        );
+        assert!(!preflight.meets_craftsmanship_requirement);
+        assert!(preflight.meets_control_requirement);
+        assert!(!preflight.can_complete && !preflight.can_reach_max_quality);
+        let preflight = Simulator::preflight(
+            &player.with_control(player.control - 1),
+            &required,
+            &options,
+        );
+        assert!(preflight.meets_craftsmanship_requirement);
+        assert!(!preflight.meets_control_requirement);
+        assert!(!preflight.can_complete);
+
+        let recipe = Recipe {
+            job_level: 90,
+            ..recipe
+        };
+        assert!(!Simulator::preflight(&player, &recipe, &options).recipe_modifiers_apply);
+    }
+
+    #[test]
+    fn required_complete_rotations_finish() {
+        let (context, options) = setup_1();
+        let options = SearchOptions {
+            iterations: 500,
+            require_complete: true,
+            ..options
+        };
+
+        let (actions, state) = Simulator::search_oneshot(&context, vec![], options);
+        assert_eq!(options.check_solution(&state), Ok(()));
+        let (_, result) = Simulator::simulate(&context, actions);
+        assert!(matches!(result, Some(CraftResult::Finished(_))));
+
+        // unfinished rotations are only rejected when the flag is set
+        let (unfinished, _) = Simulator::simulate(&context, vec![BasicTouch]);
+        assert_eq!(options.check_solution(&unfinished), Err(NoCompleteRotation));
+        let options = SearchOptions {
+            require_complete: false,
+            ..options
+        };
+        assert_eq!(options.check_solution(&unfinished), Ok(()));
+    }
+
+    #[test]
+    fn simulate_until_failure_reports_where() {
+        let (context, _) = setup_1();
+
+        let actions = vec![BasicTouch, ByregotsBlessing, Reflect, BasicTouch];
+        let (state, result, failure) = Simulator::simulate_until_failure(&context, &actions, &[]);
+        assert!(matches!(result, Some(CraftResult::InvalidActionFailure)));
+        assert_eq!(state.step, 3);
+        assert_eq!(
+            failure,
+            Some(FailurePoint {
+                action_index: 2,
+                invalid_reason: Some(InvalidActionReason::FirstStepOnly),
+            })
+        );
+
+        let actions = vec![BasicTouch; 10];
+        let (state, _, failure) = Simulator::simulate_until_failure(&context, &actions, &[]);
+        assert_eq!(state.durability, 0);
+        assert_eq!(failure.unwrap().action_index, 7);
+        assert_eq!(failure.unwrap().invalid_reason, None);
+
+        let (_, _, failure) = Simulator::simulate_until_failure(&context, &[BasicTouch], &[]);
+        assert_eq!(failure, None);
+    }
+
+    #[test]
+    fn reflect_opens_with_two_inner_quiet() {
+        use InvalidActionReason::*;
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![Reflect]);
+        assert_eq!(state.buffs.inner_quiet, 2);
+        assert!(state.quality > 0);
+        // any other action taken first means Reflect can't be used
+        for opener in [MuscleMemory, BasicTouch, Veneration] {
+            let (state, _) = Simulator::simulate(&context, vec![opener]);
+            assert_eq!(state.can_use(Reflect), Err(FirstStepOnly));
+        }
+        let (state, _) = Simulator::simulate(&context, vec![Reflect]);
+        assert_eq!(state.can_use(MuscleMemory), Err(FirstStepOnly));
+
+        // Trained Eye is also a first-step opener, when it's available
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(100, 4747, 4353, 577);
+        let context = CraftContext::new(
+            &player,
+            &recipe,
+            CraftOptions {
+                max_steps: 25,
+                ..Default::default()
+            },
+        );
+        let (state, _) = Simulator::simulate(&context, vec![]);
+        assert_eq!(state.can_use(Reflect), Ok(()));
+        assert_eq!(state.can_use(TrainedEye), Ok(()));
+        let (state, _) = Simulator::simulate(&context, vec![Reflect]);
+        assert_eq!(state.can_use(TrainedEye), Err(FirstStepOnly));
+        // Trained Eye maxes quality, so Reflect is ruled out for that first
+        let (state, _) = Simulator::simulate(&context, vec![TrainedEye]);
+        assert_eq!(state.can_use(Reflect), Err(QualityMaxed));
+        assert_eq!(state.can_use(MuscleMemory), Err(FirstStepOnly));
+    }
+
+    #[test]
+    fn trained_eye_opener_can_be_skipped() {
+        let recipe = crate::data::recipes(90)[0];
+        let player = Player::new(100, 4747, 4353, 577);
+        let context = |skip_trained_eye_opener| {
+            CraftContext::new(
+                &player,
+                &recipe,
+                CraftOptions {
+                    max_steps: 25,
+                    skip_trained_eye_opener,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let default_context = context(false);
+        let (state, _) = Simulator::simulate(&default_context, vec![]);
+        let moves = state.clone_strict().available_moves;
+        assert!(moves.contains(TrainedEye));
+        assert_eq!(moves.len(), 1);
+
+        let skip_context = context(true);
+        let (state, _) = Simulator::simulate(&skip_context, vec![]);
+        let moves = state.clone_strict().available_moves;
+        assert!(moves.contains(TrainedEye));
+        assert!(moves.contains(Reflect));
+        assert!(moves.contains(MuscleMemory));
+    }
+
+    #[test]
+    fn quality_under_muscle_memory_can_be_allowed() {
+        let (context, _) = setup_1();
+        let moves = |context: &CraftContext| {
+            let (state, _) = Simulator::simulate(context, vec![MuscleMemory]);
+            state.clone_strict().available_moves
+        };
+
+        assert!(!moves(&context).contains(DelicateSynthesisTraited));
+        assert!(!moves(&context).contains(BasicTouch));
+
+        let allowed_context = CraftContext {
+            allow_quality_under_muscle_memory: true,
+            ..context.clone()
+        };
+        assert!(moves(&allowed_context).contains(DelicateSynthesisTraited));
+        assert!(moves(&allowed_context).contains(BasicTouch));
+    }
+
+    #[test]
+    fn active_buffs_skip_expired_ones() {
+        use crate::BuffKind;
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![]);
+        assert!(state.buffs.active().is_empty());
+
+        let (state, _) = Simulator::simulate(
+            &context,
+            vec![Reflect, WasteNot, GreatStrides, PreparatoryTouch],
+        );
+        assert_eq!(
+            state.buffs.active(),
+            vec![(BuffKind::InnerQuiet, 4), (BuffKind::WasteNot, 2)]
+        );
+    }
+
+    #[test]
+    fn downgraded_groundwork_is_allowed_when_not_strict() {
+        let (context, _) = setup_1();
+
+        let (state, _) = Simulator::simulate(&context, vec![BasicTouch; 7]);
+        assert_eq!(state.durability, 10);
+        assert_eq!(state.can_use(GroundworkTraited), Ok(()));
+        assert!(state.available_moves.contains(GroundworkTraited));
+        assert!(!state
+            .clone_strict()
+            .available_moves
+            .contains(GroundworkTraited));
+
+        let mut actions = vec![BasicTouch; 7];
+        actions.push(GroundworkTraited);
+        let (state, _) = Simulator::simulate(&context, actions);
+        assert_eq!(state.progress, context.base_progress_factor * 180 / 100);
+    }
+
+    #[test]
+    fn finishing_on_the_last_durability_succeeds() {
+        let (mut context, _) = setup_1();
+        context.durability_max = 10;
+
+        let (state, result) = Simulator::simulate(&context, vec![BasicSynthesisTraited]);
+        assert_eq!(state.durability, 0);
+        assert!(matches!(result, Some(CraftResult::DurabilityFailure)));
+
+        // the same action succeeds if it finishes the craft
+        context.progress_target = state.progress;
+        let (state, result) = Simulator::simulate(&context, vec![BasicSynthesisTraited]);
+        assert_eq!(state.durability, 0);
+        assert!(matches!(result, Some(CraftResult::Finished(_))));
+    }
+
+    #[test]
+    fn replays_only_allocate_one_craft() {
+        let (context, _) = setup_1();
+        let mut sim = Simulator::for_replay(&context);
+        let capacity = sim.tree.nodes.capacity();
+        assert!(capacity <= usize::from(context.step_max) + 1, "{capacity}");
+
+        // even a rotation that's too long to finish fits
+        let actions = vec![BasicSynthesisTraited; usize::from(context.step_max) + 5];
+        let (_, result) = sim.execute_actions(0, actions, &[]);
+        assert!(result.is_some());
+        assert_eq!(sim.tree.nodes.capacity(), capacity);
+    }
+
+    #[test]
+    fn stepwise_defaults_keep_set_constants() {
+        let options = SearchOptions {
+            max_score_weighting_constant: None,
+            exploration_constant: Some(3.0),
+            ..Default::default()
+        }
+        .with_stepwise_defaults();
+        assert_eq!(options.max_score_weighting_constant, Some(0.2));
+        assert_eq!(options.exploration_constant, Some(3.0));
+
+        // the default options leave both constants for each mode to fill in
+        let options = SearchOptions::default().with_stepwise_defaults();
+        assert_eq!(options.max_score_weighting_constant, Some(0.2));
+        assert_eq!(options.exploration_constant, Some(1.0));
+        let (context, _) = setup_1();
+        let sim = Simulator::from_context(&context, SearchOptions::default());
+        assert_eq!(
+            (sim.max_score_weighting_constant, sim.exploration_constant),
+            (
+                SearchOptions::MAX_SCORE_WEIGHTING_CONSTANT,
+                SearchOptions::EXPLORATION_CONSTANT
+            )
+        );
+    }
+
+    #[test]
+    fn lint_flags_wasted_actions() {
+        let (context, _) = setup_1();
+        let kinds = |actions: &[Action]| -> Vec<(usize, RotationWarningKind)> {
+            Simulator::lint_rotation(&context, actions)
+                .iter()
+                .map(|warning| (warning.action_index, warning.kind))
+                .collect()
+        };
+
+        assert!(kinds(&[Reflect, Innovation, BasicTouch, Observe, AdvancedTouch]).is_empty());
+        assert_eq!(
+            kinds(&[
+                Innovation,
+                Veneration,
+                BasicSynthesisTraited,
+                Observe,
+                BasicSynthesisTraited,
+                WasteNot,
+            ]),
+            vec![
+                (0, RotationWarningKind::UnusedBuff),
+                (3, RotationWarningKind::ObserveNotFollowedUp),
+                (5, RotationWarningKind::UnusedBuff),
+            ]
+        );
+
+        // Basic Touch is too late for Innovation
+        let mut actions = vec![Innovation];
+        actions.extend([MastersMend; 4]);
+        actions.push(BasicTouch);
+        assert_eq!(kinds(&actions), vec![(0, RotationWarningKind::UnusedBuff)]);
+
+        // actions that don't cost durability don't benefit from Waste Not
+        assert_eq!(
+            kinds(&[WasteNot, Observe, MastersMend, Innovation]),
+            vec![
+                (0, RotationWarningKind::UnusedBuff),
+                (1, RotationWarningKind::ObserveNotFollowedUp),
+                (3, RotationWarningKind::UnusedBuff)
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_flags_actions_after_the_craft() {
+        let (mut context, _) = setup_1();
+        context.progress_target = 1;
+        let warnings = Simulator::lint_rotation(&context, &[BasicSynthesisTraited, BasicTouch]);
+        assert_eq!(
+            warnings,
+            vec![RotationWarning {
+                action_index: 1,
+                kind: RotationWarningKind::CraftAlreadyOver,
+                message: "Basic Touch is used after the craft is over".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn muscle_memory_doubles_the_next_synthesis() {
+        let (context, _) = setup_1();
+        let synthesis = context.base_progress_factor * 120 / 100;
+        let doubled = context.base_progress_factor * 240 / 100;
+
+        // Muscle Memory's own progress isn't doubled
+        let (opened, _) = Simulator::simulate(&context, vec![MuscleMemory]);
+        assert_eq!(opened.buffs.muscle_memory, MUSCLE_MEMORY_DURATION);
+
+        let (state, _) = Simulator::simulate(&context, vec![MuscleMemory, BasicSynthesisTraited]);
+        assert_eq!(state.progress - opened.progress, doubled);
+        assert_eq!(state.buffs.muscle_memory, 0);
+
+        let (state, _) = Simulator::simulate(
+            &context,
+            vec![MuscleMemory, BasicSynthesisTraited, BasicSynthesisTraited],
+        );
+        assert_eq!(state.progress - opened.progress, doubled + synthesis);
+    }
+
+    #[test]
+    fn muscle_memory_is_only_used_up_by_progress() {
+        let (context, _) = setup_1();
+
+        let (opened, _) = Simulator::simulate(&context, vec![MuscleMemory]);
+        let mut actions = vec![MuscleMemory, BasicTouch];
+        let (state, _) = Simulator::simulate(&context, actions.clone());
+        assert_eq!(state.buffs.muscle_memory, MUSCLE_MEMORY_DURATION - 1);
+
+        // still active on the last step it lasts
+        actions.extend([BasicTouch; 3]);
+        actions.push(BasicSynthesisTraited);
+        let (state, _) = Simulator::simulate(&context, actions.clone());
+        assert_eq!(
+            state.progress - opened.progress,
+            context.base_progress_factor * 240 / 100
+        );
+
+        // and expires if it isn't used
+        actions.pop();
+        actions.push(BasicTouch);
+        let (state, _) = Simulator::simulate(&context, actions);
+        assert_eq!(state.buffs.muscle_memory, 0);
+    }
+
+    #[test]
+    fn streaming_search_reports_improvements() {
+        let (context, options) = setup_1();
+        let options = SearchOptions {
+            iterations: 3_000,
+            ..options
+        };
+        let improvements = std::cell::RefCell::new(vec![]);
+        let (actions, state) = Simulator::search_streaming(
+            &context,
+            vec![MuscleMemory],
+            options,
+            500,
+            &|improvement| improvements.borrow_mut().push(improvement),
+        );
+
+        let improvements = improvements.into_inner();
+        assert!(!improvements.is_empty());
+        assert!(improvements.windows(2).all(|pair| {
+            pair[0].iterations < pair[1].iterations
+                && pair[0].state.max_score < pair[1].state.max_score
+        }));
+        assert!(improvements
+            .iter()
+            .all(|improvement| improvement.actions[0] == MuscleMemory));
+
+        // the best rotation reported is the one returned
+        let last = improvements.last().unwrap();
+        assert_eq!(last.actions, actions);
+        assert!((last.state.max_score - state.max_score).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn great_strides_is_used_up_by_quality_actions() {
+        let (context, _) = setup_1();
+        let great_strides_after = |actions: &[Action]| {
+            let (state, _) = Simulator::simulate(&context, actions.to_vec());
+            state.buffs.great_strides
+        };
+
+        assert_eq!(
+            great_strides_after(&[GreatStrides, DelicateSynthesisTraited]),
+            0
+        );
+        assert_eq!(
+            great_strides_after(&[Reflect, GreatStrides, ByregotsBlessing]),
+            0
+        );
+        for action in [
+            BasicSynthesisTraited,
+            GroundworkTraited,
+            Veneration,
+            WasteNot,
+        ] {
+            assert_eq!(
+                great_strides_after(&[GreatStrides, action]),
+                GREAT_STRIDES_DURATION - 1,
+                "{action:?} shouldn't use up Great Strides"
+            );
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{Action, CraftContext, CraftOptions, Player, Recipe, SearchOptions, Simulator};
-    use Action::*;
+    #[test]
+    fn quality_by_inner_quiet_adds_ten_percent_per_stack() {
+        let (context, _) = setup_1();
+        let state = CraftState::new(&context);
+        let increases = state.quality_by_inner_quiet(BasicTouch).unwrap();
 
-    fn setup_1() -> (CraftContext, SearchOptions) {
+        assert_eq!(increases.len(), 11);
+        assert_eq!(increases[0], state.execute(&BasicTouch).quality);
+        for (inner_quiet, &increase) in (0_u32..).zip(&increases) {
+            let expected = increases[0] * (10 + inner_quiet) / 10;
+            assert!(
+                increase.abs_diff(expected) <= 1,
+                "{inner_quiet}: {increase}"
+            );
+        }
+        // the state itself isn't changed
+        assert_eq!(state.buffs.inner_quiet, 0);
+        assert_eq!(state.quality_by_inner_quiet(BasicSynthesisTraited), None);
+    }
+
+    #[test]
+    fn great_strides_lasts_through_two_non_quality_actions() {
+        let (context, _) = setup_1();
+        let byregots_gain = |actions: &[Action]| {
+            let (before, _) = Simulator::simulate(&context, actions.to_vec());
+            before.execute(&ByregotsBlessing).quality - before.quality
+        };
+
+        // like in game, Great Strides lasts 3 steps and only a quality action
+        // uses it up, so it carries over a buff or progress action
+        let without = byregots_gain(&[Reflect, Veneration]);
+        assert_eq!(
+            byregots_gain(&[Reflect, GreatStrides, Veneration]),
+            without * 2
+        );
+        assert_eq!(
+            byregots_gain(&[Reflect, GreatStrides, Veneration, BasicSynthesisTraited]),
+            without * 2
+        );
+        // but expires if a third step passes without a quality action
+        assert_eq!(
+            byregots_gain(&[Reflect, GreatStrides, Veneration, WasteNot, Observe]),
+            without
+        );
+    }
+
+    #[test]
+    fn byregots_blessing_at_max_inner_quiet() {
+        let (context, _) = setup_1();
+        let buffs = Buffs {
+            inner_quiet: 10,
+            great_strides: 2,
+            ..Buffs::new()
+        };
+        let state = CraftState::with_values(&context, 0, 0, 40, 300, buffs);
+        assert_eq!(state.can_use(TrainedFinesse), Ok(()));
+        assert_eq!(
+            state.can_use(MuscleMemory),
+            Err(InvalidActionReason::FirstStepOnly)
+        );
+
+        let next = state.execute(&ByregotsBlessing);
+        // 300% efficiency, doubled by Inner Quiet and again by Great Strides
+        assert_eq!(next.quality, context.base_quality_factor * 12);
+        assert_eq!(next.buffs.inner_quiet, 0);
+        assert_eq!(next.buffs.great_strides, 0);
+    }
+
+    #[test]
+    fn score_explanation_lists_each_metric() {
+        let (context, _) = setup_1();
+        let state = CraftState::with_values(&context, 6600, 12000, 40, 0, Buffs::new());
+        assert_eq!(
+            state.explain_score(),
+            "Quality 100% (0.650), Progress 100% (0.200), Durability 40/80 (0.025), \
+             CP 0/577 (0.000), 1/25 steps (0.046)"
+        );
+
+        let context = CraftContext {
+            quality_relevant: false,
+            ..context
+        };
+        let state = CraftState::with_values(&context, 6600, 0, 40, 0, Buffs::new());
+        assert_eq!(state.explain_score(), "1/25 steps (0.920)");
+    }
+
+    #[test]
+    fn scores_stay_monotonic_for_large_targets() {
         let recipe = Recipe {
             recipe_level: 690,
             job_level: 100,
             stars: 0,
-            progress: 6600,
-            quality: 12000,
+            progress: 40_000_001,
+            quality: 80_000_001,
             durability: 80,
             progress_div: 170,
             progress_mod: 90,
@@ -369,196 +5262,321 @@ mod tests {
             quality_mod: 75,
             is_expert: false,
             conditions_flag: 15,
+            material_quality_factor: 0,
+            required_craftsmanship: 0,
+            required_control: 0,
+            item_id: 0,
         };
         let player = Player::new(100, 4747, 4353, 577);
-        let craft_options = CraftOptions {
+        let options = CraftOptions {
             max_steps: 25,
-            use_manipulation: true,
             ..Default::default()
         };
-        let context = CraftContext::new(&player, &recipe, craft_options);
-        let options = SearchOptions {
-            rng_seed: Some(0),
-            ..Default::default()
+        let context = CraftContext::new(&player, &recipe, options);
+        let score = |progress, quality| {
+            CraftState::with_values(&context, progress, quality, 40, 300, Buffs::new()).score()
         };
-        (context, options)
+
+        // both targets are past where f32 can represent every integer
+        let target = context.quality_target;
+        let qualities = [0, target / 2, target - target / 100, target, target + 1];
+        let scores: Vec<f32> = qualities
+            .iter()
+            .map(|&quality| score(context.progress_target, quality))
+            .collect();
+        assert!(
+            scores.windows(2).all(|pair| pair[0] <= pair[1]),
+            "{scores:?}"
+        );
+        assert!(scores[2] < scores[3]);
+        assert!((scores[3] - scores[4]).abs() < f32::EPSILON);
+
+        assert!(
+            score(context.progress_target - 1, target) <= score(context.progress_target, target)
+        );
+        assert!(
+            (score(context.progress_target, target) - score(u32::MAX, u32::MAX)).abs()
+                < f32::EPSILON
+        );
     }
 
-    fn setup_2() -> (CraftContext, SearchOptions) {
-        let recipe = Recipe {
-            recipe_level: 580,
-            job_level: 90,
-            stars: 2,
-            progress: 3900,
-            quality: 10920,
-            durability: 70,
-            progress_div: 130,
-            progress_mod: 80,
-            quality_div: 115,
-            quality_mod: 70,
-            is_expert: false,
-            conditions_flag: 15,
-        };
-        let player = Player::new(90, 3290, 3541, 649);
-        let craft_options = CraftOptions {
-            max_steps: 25,
-            use_manipulation: true,
-            ..Default::default()
-        };
-        let context = CraftContext::new(&player, &recipe, craft_options);
-        let options = SearchOptions {
-            rng_seed: Some(123),
-            ..Default::default()
+    #[test]
+    fn quality_weight_trades_quality_for_steps() {
+        let (context, _) = setup_1();
+        let score = |quality_weight, quality| {
+            let context = CraftContext {
+                quality_weight,
+                ..context.clone()
+            };
+            CraftState::with_values(&context, 6600, quality, 80, 577, Buffs::new()).score()
         };
-        (context, options)
+
+        // speed only: quality doesn't matter
+        assert!((score(Some(0.0), 0) - score(Some(0.0), 12000)).abs() < f32::EPSILON);
+        // quality only: a full quality craft scores 1 without any step bonus
+        assert!((score(Some(1.0), 12000) - 1.0).abs() < 1e-6);
+        assert!(score(Some(0.5), 12000) > score(Some(0.5), 6000));
+        assert!(score(Some(1.0), 6000) < score(Some(0.5), 6000));
+        // the default weights are kept when unset
+        assert!(score(None, 12000) < 1.0);
     }
 
-    fn assert_craft(
-        context: &CraftContext,
-        actions: Vec<Action>,
-        progress: u32,
-        quality: u32,
-        durability: i8,
-        cp: u32,
-    ) {
-        let (end_state, _) = Simulator::simulate(context, actions);
-        assert_eq!(end_state.progress, progress);
-        assert_eq!(end_state.quality, quality);
-        assert_eq!(end_state.durability, durability);
-        assert_eq!(end_state.cp, cp);
+    #[test]
+    fn available_moves_are_recorded_before_each_step() {
+        let (context, _) = setup_1();
+        let moves =
+            Simulator::available_moves_per_step(&context, &[Reflect, Observe, AdvancedTouch]);
+        assert_eq!(moves.len(), 3);
+        assert!(moves[0].contains(&MuscleMemory));
+        assert!(!moves[1].contains(&MuscleMemory));
+        assert!(!moves[2].contains(&Observe));
+
+        // Reflect can't follow Basic Touch, so the replay stops there
+        let moves =
+            Simulator::available_moves_per_step(&context, &[BasicTouch, Reflect, BasicTouch]);
+        assert_eq!(moves.len(), 1);
     }
 
     #[test]
-    fn basic_actions() {
-        let actions = vec![BasicTouch, BasicSynthesisTraited, MastersMend];
+    fn resource_timeline_tracks_each_step() {
         let (context, _) = setup_1();
-        assert_craft(&context, actions, 303, 243, 80, 471);
+        let actions = [MuscleMemory, Manipulation, BasicTouch];
+        let timeline = Simulator::resource_timeline(&context, &actions);
+        assert_eq!(timeline.len(), 4);
+        assert_eq!(timeline[0].cp, context.cp_max);
+        assert_eq!(timeline[0].durability, context.durability_max);
+        assert!(timeline.windows(2).all(|pair| pair[1].cp <= pair[0].cp));
+
+        let (state, _) = Simulator::simulate(&context, actions.to_vec());
+        let last = timeline.last().unwrap();
+        assert_eq!(
+            (last.progress, last.quality, last.durability, last.cp),
+            (state.progress, state.quality, state.durability, state.cp)
+        );
     }
 
     #[test]
-    fn basic_touch_combo() {
-        let actions = vec![
-            Innovation,
-            BasicTouch,
-            StandardTouch,
-            AdvancedTouch,
-            Observe,
-            AdvancedTouch,
-            StandardTouch,
-            AdvancedTouch,
-        ];
+    fn trimming_removes_wasted_actions() {
+        let (context, _) = setup_2();
+        let rotation = Simulator::progress_rotation(&context).unwrap();
+        let (state, _) = Simulator::simulate(&context, rotation.clone());
+
+        // buffs for touches that never come don't do anything
+        let (last, rest) = rotation.split_last().unwrap();
+        let wasteful = [rest, &[Innovation, GreatStrides, *last]].concat();
+        let trimmed = Simulator::trim_rotation(&context, &wasteful);
+        let (trimmed_state, result) = Simulator::simulate(&context, trimmed.clone());
+        assert!(matches!(result, Some(CraftResult::Finished(_))));
+        assert!(trimmed.len() <= rotation.len(), "{trimmed:?}");
+        assert!(trimmed_state.quality >= state.quality);
+        assert!(trimmed_state.cp >= state.cp);
+
+        // unfinished rotations are left alone
+        assert_eq!(
+            Simulator::trim_rotation(&context, &[BasicTouch]),
+            vec![BasicTouch]
+        );
+    }
+
+    #[test]
+    fn durability_recovery_keeps_the_item_intact() {
         let (context, _) = setup_1();
-        assert_craft(&context, actions, 0, 2965, 20, 402);
+        let touches = [BasicTouch, StandardTouch, AdvancedTouch].repeat(3);
+        let written = [
+            &touches[..],
+            &[Innovation, PreparatoryTouch, GreatStrides, ByregotsBlessing],
+            &[Veneration],
+            &[CarefulSynthesisTraited; 4],
+        ]
+        .concat();
+        let (_, result) = Simulator::simulate(&context, written.clone());
+        assert!(matches!(result, Some(CraftResult::DurabilityFailure)));
+
+        let planned = Simulator::insert_durability_recovery(&context, &written);
+        let (state, result) = Simulator::simulate(&context, planned.clone());
+        assert!(result.is_none() && state.durability > 0, "{planned:?}");
+        // the written actions are all kept in order, with only repairs added
+        let is_repair =
+            |action: &Action| matches!(action, Manipulation | MastersMend | ImmaculateMend);
+        let kept: Vec<Action> = planned.iter().copied().filter(|a| !is_repair(a)).collect();
+        assert_eq!(kept, written);
+        assert!(planned.len() > written.len());
+
+        // rotations that never break the item are left alone
+        let safe = [BasicTouch, BasicTouch, MastersMend, CarefulSynthesisTraited];
+        assert_eq!(Simulator::insert_durability_recovery(&context, &safe), safe);
     }
 
     #[test]
-    fn with_buffs_1() {
-        let actions = vec![
-            Reflect,
-            Manipulation,
-            WasteNotII,
-            PreparatoryTouch,
-            DelicateSynthesisTraited,
-        ];
+    fn rotation_difficulty_counts_tight_margins() {
         let (context, _) = setup_1();
-        assert_craft(&context, actions, 379, 1652, 70, 305);
+        let difficulty = |context: &CraftContext, actions: &[Action]| {
+            Simulator::rotation_difficulty(context, actions)
+        };
+
+        // two combos, with plenty of CP and durability left
+        let touches = [BasicTouch, StandardTouch, AdvancedTouch];
+        assert_eq!(difficulty(&context, &touches), Difficulty::Easy);
+
+        let combos = [&touches[..], &[Observe, AdvancedTouch]].concat();
+        assert_eq!(difficulty(&context, &combos), Difficulty::Medium);
+        // 10 durability is left before the last Basic Touch
+        assert_eq!(difficulty(&context, &[BasicTouch; 8]), Difficulty::Medium);
+
+        // only 5 of 120 CP is left
+        let low_cp_context = CraftContext {
+            cp_max: 120,
+            ..context.clone()
+        };
+        let cp_heavy = [&combos[..], &[BasicTouch, StandardTouch]].concat();
+        assert_eq!(difficulty(&low_cp_context, &cp_heavy), Difficulty::Hard);
     }
 
     #[test]
-    fn with_buffs_2() {
-        let actions = vec![
-            MuscleMemory,
-            GreatStrides,
-            PrudentTouch,
-            DelicateSynthesisTraited,
-        ];
+    fn min_durability_is_the_lowest_point() {
         let (context, _) = setup_1();
-        assert_craft(&context, actions, 1518, 753, 55, 482);
+        assert_eq!(
+            Simulator::min_durability_during(&context, &[]),
+            context.durability_max
+        );
+
+        let actions = [BasicTouch, BasicTouch, BasicTouch, MastersMend];
+        assert_eq!(Simulator::min_durability_during(&context, &actions), 50);
     }
 
     #[test]
-    fn with_buffs_3() {
-        let actions = vec![
-            MuscleMemory,
-            Manipulation,
-            MastersMend,
-            WasteNotII,
-            Innovation,
-            DelicateSynthesisTraited,
-            BasicTouch,
-            GreatStrides,
-            ByregotsBlessing,
-        ];
+    fn strict_immaculate_mend_needs_a_large_deficit() {
         let (context, _) = setup_1();
-        assert_craft(&context, actions, 1518, 1784, 80, 165);
+        let strict_moves = |actions: Vec<Action>| {
+            let (state, _) = Simulator::simulate(&context, actions);
+            state.clone_strict().available_moves
+        };
+
+        // Master's Mend is already worth it at 25 durability lost
+        let moves = strict_moves(vec![BasicTouch; 4]);
+        assert!(moves.contains(MastersMend));
+        assert!(!moves.contains(ImmaculateMend));
+
+        assert!(strict_moves(vec![BasicTouch; 5]).contains(ImmaculateMend));
+
+        // never while Manipulation is restoring durability
+        let mut actions = vec![Manipulation];
+        actions.extend([BasicTouch; 5]);
+        assert!(!strict_moves(actions).contains(ImmaculateMend));
     }
 
     #[test]
-    fn trained_finesse_procs() {
-        let actions = vec![
-            Reflect,
-            WasteNot,
-            PreparatoryTouch,
-            PreparatoryTouch,
-            BasicTouch,
-            StandardTouch,
-            PrudentTouch,
-            PreparatoryTouch,
-        ];
+    fn display_shows_variable_conditions() {
         let (context, _) = setup_1();
-        let (end_state, _) = Simulator::simulate(&context, actions);
-        // 10 stacks of IQ
-        assert_eq!(10, end_state.buffs.inner_quiet);
-        // should proc Trained Finesse
-        assert!(end_state.available_moves.contains(TrainedFinesse));
+        let state = CraftState::new(&context);
+        assert!(state.to_string().ends_with(" cp | Normal"));
+
+        let context = CraftContext {
+            conditions_flag: 1,
+            ..context
+        };
+        let state = CraftState::new(&context);
+        assert!(state.to_string().ends_with(" cp"));
     }
 
     #[test]
-    fn rotation_should_not_panic_1() {
-        let actions = vec![
-            Reflect,
-            Manipulation,
-            PreparatoryTouch,
-            WasteNotII,
-            PreparatoryTouch,
-            Innovation,
-            PreparatoryTouch,
-            PreparatoryTouch,
-            GreatStrides,
-            ByregotsBlessing,
-            Veneration,
-            GroundworkTraited,
-            GroundworkTraited,
-            GroundworkTraited,
-        ];
+    fn search_resumes_from_in_game_state() {
+        use crate::InGameState;
+        let (context, options) = setup_1();
+        let (replayed, _) =
+            Simulator::simulate(&context, vec![MuscleMemory, Veneration, BasicTouch]);
+        let in_game = InGameState {
+            step: replayed.step,
+            progress: replayed.progress,
+            quality: replayed.quality,
+            durability: replayed.durability,
+            cp: replayed.cp,
+            last_action: Some(BasicTouch),
+            inner_quiet: replayed.buffs.inner_quiet,
+            veneration: replayed.buffs.veneration,
+            muscle_memory: replayed.buffs.muscle_memory,
+            ..Default::default()
+        };
+
+        let state = CraftState::from_in_game(&context, &in_game);
+        assert_eq!(
+            state.available_moves.to_vec(),
+            replayed.available_moves.to_vec()
+        );
+        assert_eq!(state.can_use(StandardTouch), Ok(()));
+
+        let (actions, result_state) = Simulator::search_from_state(&state, options);
+        assert!(!actions.is_empty());
+        let (simulated, _) = Simulator::simulate(
+            &context,
+            [vec![MuscleMemory, Veneration, BasicTouch], actions].concat(),
+        );
+        assert_eq!(simulated.progress, result_state.progress);
+    }
+
+    #[test]
+    fn in_game_states_only_continue_real_combos() {
+        use crate::InGameState;
         let (context, _) = setup_1();
-        Simulator::simulate(&context, actions);
+        let in_game = |last_action, action_before_last| InGameState {
+            step: 3,
+            durability: context.durability_max,
+            cp: context.cp_max,
+            last_action: Some(last_action),
+            action_before_last,
+            ..Default::default()
+        };
+
+        // Standard Touch only leads into Advanced Touch after Basic Touch
+        let state = CraftState::from_in_game(&context, &in_game(StandardTouch, Some(BasicTouch)));
+        assert_eq!(state.previous_combo_action, Some(StandardTouch));
+        for before in [None, Some(Observe), Some(Veneration)] {
+            let state = CraftState::from_in_game(&context, &in_game(StandardTouch, before));
+            assert_eq!(state.previous_combo_action, None, "{before:?}");
+        }
+
+        // like after using them, Basic Touch and Observe always start a combo
+        for last_action in [BasicTouch, Observe] {
+            let state = CraftState::from_in_game(&context, &in_game(last_action, None));
+            assert_eq!(state.previous_combo_action, Some(last_action));
+        }
+        let (replayed, _) = Simulator::simulate(&context, vec![Veneration, StandardTouch]);
+        let state = CraftState::from_in_game(&context, &in_game(StandardTouch, Some(Veneration)));
+        assert_eq!(state.previous_combo_action, replayed.previous_combo_action);
     }
 
     #[test]
-    fn floating_point_error() {
-        let actions = vec![
-            MuscleMemory,
-            Manipulation,
-            Veneration,
-            WasteNotII,
-            GroundworkTraited,
-            GroundworkTraited,
-            StandardTouch,
-            Innovation,
-            PreparatoryTouch,
-            PreparatoryTouch,
-            PreparatoryTouch,
-            PreparatoryTouch,
-            GreatStrides,
-            Innovation,
-            PreparatoryTouch,
-            TrainedFinesse,
-            GreatStrides,
-            ByregotsBlessing,
-        ];
-        let (context, _) = setup_2();
-        assert_craft(&context, actions, 3549, 10932, 5, 7);
+    fn resumed_search_budgets_remaining_steps() {
+        use crate::InGameState;
+        let (context, options) = setup_2();
+        let in_game = InGameState {
+            step: 10,
+            durability: context.durability_max,
+            cp: context.cp_max,
+            ..Default::default()
+        };
+
+        // steps 10 through 24 are left before reaching the step limit of 25
+        let state = CraftState::from_in_game(&context, &in_game);
+        let (actions, result_state) = Simulator::search_from_state(&state, options);
+        assert!(actions.len() <= 15, "{actions:?}");
+        assert_eq!(usize::from(result_state.step), 10 + actions.len());
+
+        // finishing on a later step scores lower
+        let finish_from_step = |step| {
+            let in_game = InGameState {
+                step,
+                progress: context.progress_target - 1,
+                ..in_game
+            };
+            CraftState::from_in_game(&context, &in_game).execute(&BasicSynthesisTraited)
+        };
+        let (early, late) = (finish_from_step(2), finish_from_step(10));
+        assert!(matches!(
+            late.check_result(),
+            Some(CraftResult::Finished(_))
+        ));
+        assert!(late.score() < early.score());
     }
 
     #[test]
@@ -566,4 +5584,56 @@ mod tests {
         let (context, options) = setup_2();
         Simulator::search_oneshot(&context, vec![], options);
     }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn batch_solves_every_recipe_in_order() {
+        let recipes: Vec<&Recipe> = crate::data::recipes(90)
+            .iter()
+            .filter(|recipe| recipe.stars == 0)
+            .take(3)
+            .collect();
+        let player = Player::new(90, 3290, 3541, 649);
+        let options = CraftOptions {
+            max_steps: 25,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let search_options = SearchOptions {
+            iterations: 2_000,
+            rng_seed: Some(0),
+            ..Default::default()
+        };
+
+        let results = Simulator::solve_batch(&player, &recipes, &options, search_options);
+        assert_eq!(results.len(), recipes.len());
+        for ((recipe, actions, score), expected) in results.iter().zip(&recipes) {
+            assert_eq!(recipe.item_id, expected.item_id);
+            assert!(!actions.is_empty());
+            assert!(*score > 0.0);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn root_parallel_search_finishes_craft() {
+        let (context, options) = setup_2();
+        let options = SearchOptions {
+            iterations: 5_000,
+            score_storage_threshold: Some(0.0),
+            ..options
+        };
+        let (actions, state) = Simulator::search_root_parallel(&context, vec![], options, 4);
+        assert!(matches!(
+            state.check_result(),
+            Some(CraftResult::Finished(_))
+        ));
+
+        // simulating without a condition seed keeps every step Normal
+        let (simulated, _) = Simulator::simulate(&context, actions);
+        assert_eq!(
+            (simulated.progress, simulated.quality, simulated.cp),
+            (state.progress, state.quality, state.cp)
+        );
+    }
 }
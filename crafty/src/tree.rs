@@ -4,15 +4,32 @@ pub struct Arena<T> {
 }
 
 impl<T> Arena<T> {
+    #[allow(dead_code)]
     pub fn new(initial_state: T) -> Self {
-        let initial_node = Node {
+        Self::with_capacity(initial_state, 1)
+    }
+
+    /// Creates an arena with room for at least `capacity` nodes, including the
+    /// initial node, before reallocating.
+    pub fn with_capacity(initial_state: T, capacity: usize) -> Self {
+        let mut nodes = Vec::with_capacity(capacity.max(1));
+        nodes.push(Self::root(initial_state));
+        Arena { nodes }
+    }
+
+    /// Removes every node and starts over from a new initial node, keeping the
+    /// allocated memory for reuse.
+    pub fn clear(&mut self, initial_state: T) {
+        self.nodes.clear();
+        self.nodes.push(Self::root(initial_state));
+    }
+
+    fn root(initial_state: T) -> Node<T> {
+        Node {
             parent: None,
             index: 0,
             children: vec![],
             state: initial_state,
-        };
-        Arena {
-            nodes: vec![initial_node],
         }
     }
 
@@ -29,6 +46,40 @@ impl<T> Arena<T> {
         index
     }
 
+    /// Removes every node where `keep[index]` is false, along with all of its
+    /// descendants, and moves the remaining nodes together without
+    /// reallocating. The initial node is always kept. Returns the new index of
+    /// each node, or None if it was removed.
+    pub fn retain(&mut self, keep: &[bool]) -> Vec<Option<usize>> {
+        let mut new_indices = vec![None; self.nodes.len()];
+        let mut len = 0;
+        for old_index in 0..self.nodes.len() {
+            // parents always come before their children, so the parent's new
+            // index is already known
+            let parent = self.nodes[old_index].parent.map(|p| new_indices[p]);
+            let kept = match parent {
+                None => true,
+                Some(new_parent) => new_parent.is_some() && keep[old_index],
+            };
+            if !kept {
+                continue;
+            }
+
+            self.nodes.swap(len, old_index);
+            let node = &mut self.nodes[len];
+            node.parent = parent.flatten();
+            node.index = len;
+            node.children.clear();
+            if let Some(parent_index) = node.parent {
+                self.nodes[parent_index].children.push(len);
+            }
+            new_indices[old_index] = Some(len);
+            len += 1;
+        }
+        self.nodes.truncate(len);
+        new_indices
+    }
+
     pub fn get(&self, index: usize) -> &Node<T> {
         self.nodes.get(index).unwrap()
     }
@@ -59,6 +110,47 @@ mod tests {
         assert_eq!(arena.get(0).state, "a");
     }
 
+    #[test]
+    fn with_capacity_preallocates() {
+        let arena = Arena::with_capacity("a", 10);
+
+        assert_eq!(arena.nodes.len(), 1);
+        assert!(arena.nodes.capacity() >= 10);
+    }
+
+    #[test]
+    fn clear_resets_to_new_initial_node() {
+        let mut arena = Arena::with_capacity("a", 10);
+        arena.insert(0, "b");
+        arena.insert(1, "c");
+        let capacity = arena.nodes.capacity();
+
+        arena.clear("d");
+        assert_eq!(arena.nodes.len(), 1);
+        assert_eq!(arena.get(0).state, "d");
+        assert!(arena.get(0).children.is_empty());
+        assert_eq!(arena.nodes.capacity(), capacity);
+    }
+
+    #[test]
+    fn retain_removes_subtrees_and_compacts() {
+        let mut arena = Arena::new("a");
+        let b = arena.insert(0, "b");
+        let c = arena.insert(b, "c");
+        let d = arena.insert(0, "d");
+        arena.insert(c, "e");
+
+        let new_indices = arena.retain(&[true, false, true, true, true]);
+        assert_eq!(new_indices, vec![Some(0), None, None, Some(1), None]);
+        assert_eq!(arena.nodes.len(), 2);
+        assert_eq!(arena.get(0).children, vec![1]);
+        let node_d = arena.get(new_indices[d].unwrap());
+        assert_eq!(
+            (node_d.state, node_d.parent, node_d.index),
+            ("d", Some(0), 1)
+        );
+    }
+
     #[test]
     fn inserts_into_arena_and_parent() {
         let mut arena = Arena::new("a");
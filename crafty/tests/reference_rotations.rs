@@ -0,0 +1,162 @@
+//! Full rotations checked against pinned values, recorded from this
+//! simulator once its results were trusted. They haven't been checked against
+//! Teamcraft or the game, so they catch unintended changes rather than prove
+//! accuracy. New cases only need an entry in `CASES`.
+
+use crafty::{Action, CraftContext, CraftOptions, Player, Recipe, Simulator};
+use Action::*;
+
+struct ReferenceCase {
+    name: &'static str,
+    recipe: Recipe,
+    player: Player,
+    actions: &'static [Action],
+    progress: u32,
+    quality: u32,
+    durability: i8,
+    cp: u32,
+}
+
+const RLVL_690: Recipe = Recipe {
+    recipe_level: 690,
+    job_level: 100,
+    stars: 0,
+    progress: 6600,
+    quality: 12000,
+    durability: 80,
+    progress_div: 170,
+    progress_mod: 90,
+    quality_div: 150,
+    quality_mod: 75,
+    is_expert: false,
+    conditions_flag: 15,
+    material_quality_factor: 0,
+    required_craftsmanship: 0,
+    required_control: 0,
+    item_id: 0,
+};
+
+const RLVL_580: Recipe = Recipe {
+    recipe_level: 580,
+    job_level: 90,
+    stars: 2,
+    progress: 3900,
+    quality: 10920,
+    durability: 70,
+    progress_div: 130,
+    progress_mod: 80,
+    quality_div: 115,
+    quality_mod: 70,
+    is_expert: false,
+    conditions_flag: 15,
+    material_quality_factor: 0,
+    required_craftsmanship: 0,
+    required_control: 0,
+    item_id: 0,
+};
+
+const CASES: &[ReferenceCase] = &[
+    ReferenceCase {
+        name: "lv100 touch combos",
+        recipe: RLVL_690,
+        player: Player {
+            job_level: 100,
+            craftsmanship: 4747,
+            control: 4353,
+            cp: 577,
+        },
+        actions: &[
+            Innovation,
+            BasicTouch,
+            StandardTouch,
+            AdvancedTouch,
+            Observe,
+            AdvancedTouch,
+            StandardTouch,
+            AdvancedTouch,
+        ],
+        progress: 0,
+        quality: 2965,
+        durability: 20,
+        cp: 402,
+    },
+    ReferenceCase {
+        name: "lv100 buffs and Byregot's Blessing",
+        recipe: RLVL_690,
+        player: Player {
+            job_level: 100,
+            craftsmanship: 4747,
+            control: 4353,
+            cp: 577,
+        },
+        actions: &[
+            MuscleMemory,
+            Manipulation,
+            MastersMend,
+            WasteNotII,
+            Innovation,
+            DelicateSynthesisTraited,
+            BasicTouch,
+            GreatStrides,
+            ByregotsBlessing,
+        ],
+        progress: 1518,
+        quality: 1784,
+        durability: 80,
+        cp: 165,
+    },
+    ReferenceCase {
+        name: "lv90 2-star, rounding sensitive",
+        recipe: RLVL_580,
+        player: Player {
+            job_level: 90,
+            craftsmanship: 3290,
+            control: 3541,
+            cp: 649,
+        },
+        actions: &[
+            MuscleMemory,
+            Manipulation,
+            Veneration,
+            WasteNotII,
+            GroundworkTraited,
+            GroundworkTraited,
+            StandardTouch,
+            Innovation,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            PreparatoryTouch,
+            GreatStrides,
+            Innovation,
+            PreparatoryTouch,
+            TrainedFinesse,
+            GreatStrides,
+            ByregotsBlessing,
+        ],
+        progress: 3549,
+        quality: 10932,
+        durability: 5,
+        cp: 7,
+    },
+];
+
+#[test]
+fn rotations_match_pinned_values() {
+    for case in CASES {
+        let options = CraftOptions {
+            max_steps: 30,
+            use_manipulation: true,
+            ..Default::default()
+        };
+        let context = CraftContext::new(&case.player, &case.recipe, options);
+        let (state, _) = Simulator::simulate(&context, case.actions.to_vec());
+
+        assert_eq!(
+            (state.progress, state.quality, state.durability, state.cp),
+            (case.progress, case.quality, case.durability, case.cp),
+            "{}: (progress, quality, durability, cp) differs from the pinned values",
+            case.name
+        );
+    }
+}
@@ -12,6 +12,7 @@ pub trait TsType {
 mod tests {
     use super::*;
     use quote::quote;
+    use serde::Serialize;
 
     macro_rules! assert_ast_eq {
         ($enum_or_struct_name:ident, $quote_expression:expr) => {
@@ -30,7 +31,7 @@ mod tests {
     }
 
     #[allow(dead_code)]
-    #[derive(TsType)]
+    #[derive(Serialize, TsType)]
     enum Letter {
         A,
         B,
@@ -92,4 +93,26 @@ mod tests {
             }
         );
     }
+
+    #[allow(dead_code)]
+    #[derive(Serialize, TsType)]
+    struct Foo3<'a> {
+        #[serde(skip)]
+        first: u32,
+        second: &'a Letter,
+        third: &'static str,
+    }
+
+    #[test]
+    fn generic_structs_with_skipped_fields_work() {
+        assert_ast_eq!(
+            Foo3,
+            quote! {
+                export type Foo3 = {
+                    second: Letter;
+                    third: string;
+                };
+            }
+        );
+    }
 }
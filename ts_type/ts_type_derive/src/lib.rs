@@ -45,12 +45,13 @@ pub fn ts_type_derive(input: TokenStream) -> TokenStream {
     };
 
     let ts_string = ts_tokens.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
     let ident_upper = ["TS_TYPE_", &ident.to_string().to_uppercase()].join("");
     let const_ident = syn::Ident::new(&ident_upper, ident.span());
 
     let tokens = quote!(
-        impl TsType for #ident {
+        impl #impl_generics TsType for #ident #ty_generics #where_clause {
             fn ts_type() -> &'static str {
                 #ts_string
             }
@@ -81,6 +82,7 @@ fn process_enum(ident: &syn::Ident, variants: &[ast::Variant]) -> Option<QuoteTo
 fn process_struct(ident: &syn::Ident, fields: &[ast::Field]) -> QuoteTokens {
     let ts_fields: Vec<QuoteTokens> = fields
         .iter()
+        .filter(|field| !field.attrs.skip_serializing())
         .map(|field| {
             let name = field.attrs.name().serialize_name();
             let field_span = field
@@ -114,6 +116,8 @@ fn process_type(ty: &syn::Type) -> Option<QuoteTokens> {
             let ty_inner = process_type(&ty_slice.elem)?;
             Some(quote!(#ty_inner[]))
         }
+        // &T => T
+        syn::Type::Reference(ty_reference) => process_type(&ty_reference.elem),
         // (usize, String, bool) => [number, string, boolean]
         syn::Type::Tuple(ty_tuple) => {
             let ty_inner: Option<Vec<QuoteTokens>> =
@@ -146,7 +150,6 @@ fn process_type(ty: &syn::Type) -> Option<QuoteTokens> {
         | syn::Type::Never(_)
         | syn::Type::Paren(_)
         | syn::Type::Ptr(_)
-        | syn::Type::Reference(_)
         | syn::Type::TraitObject(_)
         | syn::Type::Verbatim(_) => None,
         _ => None,
@@ -160,7 +163,7 @@ fn process_path_segment(ident: &Ident) -> QuoteTokens {
         }
         "u64" | "i64" | "u128" | "i128" => quote!(BigInt),
         "bool" => quote!(boolean),
-        "char" | "Path" | "PathBuf" | "String" | "&'static str" => quote!(string),
+        "char" | "str" | "Path" | "PathBuf" | "String" => quote!(string),
         "()" => quote!(null),
         _ => quote!(#ident),
     }
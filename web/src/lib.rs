@@ -1,6 +1,8 @@
 use crafty::{
-    Action, CraftContext, CraftOptions, CraftResult, CraftState as InternalCraftState, Player,
-    Recipe, SearchOptions, Simulator,
+    macros::MacroOptions, materials::Material, Action, ActionMetadata, BuffKind, BuffMetadata,
+    Condition, CraftContext, CraftOptions, CraftResult, CraftState, FailurePoint, InGameState,
+    InvalidActionReason, Player, Recipe, SearchImprovement, SearchOptions, SearchSnapshot,
+    SerializedCraftState, Simulator,
 };
 use serde::Serialize;
 use serde_wasm_bindgen::{from_value as from_js_value, to_value as to_js_value};
@@ -10,7 +12,19 @@ use wasm_bindgen::{prelude::*, JsCast};
 
 // only present to generate Typescript types
 #[allow(unused_imports)]
-use crafty::Buffs;
+use crafty::{
+    BuffEffects, Buffs, Difficulty, EffectiveStats, Preflight, ResourcePoint, RotationWarning,
+    RotationWarningKind, StatChange, StatSensitivity,
+};
+
+fn craft_context(
+    player: &Player,
+    recipe: &Recipe,
+    craft_options: CraftOptions,
+) -> Result<CraftContext, JsError> {
+    CraftContext::try_new(player, recipe, craft_options)
+        .map_err(|err| JsError::new(&err.to_string()))
+}
 
 #[wasm_bindgen]
 extern "C" {
@@ -18,50 +32,93 @@ extern "C" {
     pub type Recipes;
 }
 
-#[wasm_bindgen(js_name = recipesByJobLevel)]
-pub fn recipes_by_job_level(player_job_level: u32) -> Recipes {
-    let recipes = crafty::data::recipes(player_job_level);
+/// Recipes whose job level is exactly `recipe_job_level`. This is the job
+/// level shown for a recipe in game, not its internal `recipe_level`, and
+/// recipes below the given level aren't included.
+#[wasm_bindgen(js_name = recipesByRecipeJobLevel)]
+pub fn recipes_by_recipe_job_level(recipe_job_level: u32) -> Recipes {
+    let recipes = crafty::data::recipes(recipe_job_level);
 
     to_js_value(&recipes).unwrap().unchecked_into()
 }
 
-#[derive(Serialize, TsType)]
-struct CraftState {
-    step: u8,
-    step_max: u8,
-    progress: u32,
-    progress_target: u32,
-    quality: u32,
-    quality_target: u32,
-    durability: i8,
-    durability_max: i8,
-    cp: u32,
-    cp_max: u32,
-    previous_combo_action: Option<Action>,
-    trained_perfection_active: Option<bool>,
-    buffs: Buffs,
-    available_moves: Vec<Action>,
-}
-
-impl CraftState {
-    fn from_internal(state: &InternalCraftState) -> Self {
-        Self {
-            step: state.step,
-            step_max: state.context.step_max,
-            progress: state.progress,
-            progress_target: state.context.progress_target,
-            quality: state.quality,
-            quality_target: state.context.quality_target,
-            durability: state.durability,
-            durability_max: state.context.durability_max,
-            cp: state.cp,
-            cp_max: state.context.cp_max,
-            previous_combo_action: state.previous_combo_action,
-            trained_perfection_active: state.trained_perfection_active,
-            buffs: state.buffs.clone(),
-            available_moves: state.available_moves.to_vec(),
-        }
-    }
+/// Every distinct recipe variant, sorted by job level, e.g. for comparing the
+/// derived values against other data sources
+#[wasm_bindgen(js_name = allRecipes)]
+pub fn all_recipes() -> Recipes {
+    let recipes = crafty::data::all_recipes();
+
+    to_js_value(&recipes).unwrap().unchecked_into()
+}
+
+#[wasm_bindgen(js_name = availableRecipeLevels)]
+pub fn available_recipe_levels() -> Vec<u32> {
+    crafty::data::available_recipe_levels()
+}
+
+#[wasm_bindgen(js_name = collectabilityFromQuality)]
+pub fn collectability_from_quality(quality: u32) -> u32 {
+    Recipe::collectability_from_quality(quality)
+}
+
+#[wasm_bindgen(js_name = qualityFromCollectability)]
+pub fn quality_from_collectability(collectability: u32) -> u32 {
+    Recipe::quality_from_collectability(collectability)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_ACTION_METADATA: &'static str = r#"
+export function actionMetadata(): ActionMetadata[];
+"#;
+
+#[wasm_bindgen(js_name = actionMetadata, skip_typescript)]
+pub fn action_metadata() -> JsValue {
+    let metadata: Vec<ActionMetadata> = Action::ACTIONS.iter().map(Action::metadata).collect();
+
+    to_js_value(&metadata).unwrap()
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_BUFF_METADATA: &'static str = r#"
+export function buffMetadata(): BuffMetadata[];
+"#;
+
+#[wasm_bindgen(js_name = buffMetadata, skip_typescript)]
+pub fn buff_metadata() -> JsValue {
+    let metadata: Vec<BuffMetadata> = BuffKind::BUFFS.iter().map(|kind| kind.metadata()).collect();
+
+    to_js_value(&metadata).unwrap()
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_STARTING_QUALITY: &'static str = r#"
+export function startingQuality(recipe: Recipe, materials: Material[], hq_amounts: number[]): number;
+"#;
+
+#[wasm_bindgen(js_name = startingQuality, skip_typescript)]
+pub fn starting_quality(recipe: JsValue, materials: JsValue, hq_amounts: Vec<u32>) -> u32 {
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let materials: Vec<Material> = from_js_value(materials).unwrap();
+
+    crafty::materials::starting_quality(&recipe, &materials, &hq_amounts)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_HQ_MATERIALS_NEEDED: &'static str = r#"
+export function hqMaterialsNeeded(
+    recipe: Recipe,
+    materials: Material[],
+    target_quality: number,
+): number[] | undefined;
+"#;
+
+#[wasm_bindgen(js_name = hqMaterialsNeeded, skip_typescript)]
+pub fn hq_materials_needed(recipe: JsValue, materials: JsValue, target_quality: u32) -> JsValue {
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let materials: Vec<Material> = from_js_value(materials).unwrap();
+    let hq_amounts = crafty::materials::hq_materials_needed(&recipe, &materials, target_quality);
+
+    to_js_value(&hq_amounts).unwrap()
 }
 
 #[derive(Serialize, TsType)]
@@ -85,10 +142,14 @@ impl CompletionReason {
 }
 
 #[derive(Serialize, TsType)]
-struct SimulatorResult {
-    craft_state: CraftState,
+struct SimulatorResult<'a> {
+    craft_state: CraftState<'a>,
     completion_reason: Option<CompletionReason>,
+    /// Where the rotation failed, if it did
+    failure: Option<FailurePoint>,
     score: f32,
+    /// A readable breakdown of `score`
+    score_explanation: String,
 }
 
 #[wasm_bindgen(typescript_custom_section)]
@@ -107,7 +168,7 @@ pub fn simulate_actions(
     player: JsValue,
     actions: JsValue,
     craft_options: JsValue,
-) -> JsValue {
+) -> Result<JsValue, JsError> {
     console_error_panic_hook::set_once();
 
     let recipe: Recipe = from_js_value(recipe).unwrap();
@@ -119,80 +180,1129 @@ pub fn simulate_actions(
         .collect();
     let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
 
-    let context = CraftContext::new(&player, &recipe, craft_options);
-    let (end_state, result) = Simulator::simulate(&context, actions);
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let conditions = Simulator::seeded_conditions(&context, actions.len());
+    let (end_state, result, failure) =
+        Simulator::simulate_until_failure(&context, &actions, &conditions);
 
     let sim_result = SimulatorResult {
-        craft_state: CraftState::from_internal(&end_state),
-        completion_reason: CompletionReason::from_craft_result(result),
         score: end_state.score(),
+        score_explanation: end_state.explain_score(),
+        craft_state: end_state,
+        completion_reason: CompletionReason::from_craft_result(result),
+        failure,
     };
 
-    to_js_value(&sim_result).unwrap().unchecked_into()
+    Ok(to_js_value(&sim_result).unwrap().unchecked_into())
 }
 
 #[wasm_bindgen(typescript_custom_section)]
-const TS_TYPE_SEARCH_STEPWISE: &'static str = r#"
-export function searchStepwise(
+const TS_TYPE_SIMULATE_ACTIONS_WITH_CONDITIONS: &'static str = r#"
+export function simulateActionsWithConditions(
     recipe: Recipe,
     player: Player,
-    action_history: Action[],
+    actions: Action[],
+    conditions: Condition[],
     craft_options: CraftOptions,
-    search_options: SearchOptions,
-    action_callback: (action: Action) => void,
-): Action[];
+): SimulatorResult;
 "#;
 
-#[wasm_bindgen(js_name = searchStepwise, skip_typescript)]
-pub fn search_stepwise(
+#[wasm_bindgen(js_name = simulateActionsWithConditions, skip_typescript)]
+pub fn simulate_actions_with_conditions(
     recipe: JsValue,
     player: JsValue,
-    action_history: JsValue,
+    actions: JsValue,
+    conditions: JsValue,
     craft_options: JsValue,
-    search_options: JsValue,
-    action_callback: js_sys::Function,
-) -> JsValue {
+) -> Result<JsValue, JsError> {
     console_error_panic_hook::set_once();
 
     let recipe: Recipe = from_js_value(recipe).unwrap();
     let player: Player = from_js_value(player).unwrap();
-    let action_history_str: Vec<String> = from_js_value(action_history).unwrap();
-    let action_history: Vec<Action> = action_history_str
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
         .iter()
         .map(|a| Action::from_str(a).unwrap())
         .collect();
+    let conditions: Vec<Condition> = from_js_value(conditions).unwrap();
     let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
-    let search_options: SearchOptions = from_js_value(search_options).unwrap();
 
-    let callback = |action: Action| {
-        let null = JsValue::null();
-        let action_str = JsValue::from(action.name());
-        action_callback.call1(&null, &action_str).unwrap();
-    };
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let (end_state, result, failure) =
+        Simulator::simulate_until_failure(&context, &actions, &conditions);
 
-    let (actions, _) = Simulator::search_stepwise(
-        &CraftContext::new(&player, &recipe, craft_options),
-        action_history,
-        search_options,
-        Some(&callback),
-    );
+    let sim_result = SimulatorResult {
+        score: end_state.score(),
+        score_explanation: end_state.explain_score(),
+        craft_state: end_state,
+        completion_reason: CompletionReason::from_craft_result(result),
+        failure,
+    };
 
-    let actions_str: Vec<&'static str> = actions.iter().map(|a| a.name()).collect();
-    to_js_value(&actions_str).unwrap().unchecked_into()
+    Ok(to_js_value(&sim_result).unwrap().unchecked_into())
 }
 
 #[wasm_bindgen(typescript_custom_section)]
-const TS_TYPE_GENERATE_MACRO_TEXT: &'static str = r#"
-export function generateMacroText(actions: Action[]): string[];
+const TS_TYPE_CAN_USE_ACTION: &'static str = r#"
+export function canUseAction(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    action: Action,
+    craft_options: CraftOptions,
+): InvalidActionReason | undefined;
 "#;
 
-#[wasm_bindgen(js_name = generateMacroText, skip_typescript)]
-pub fn generate_macro_text(actions: JsValue) -> JsValue {
+#[wasm_bindgen(js_name = canUseAction, skip_typescript)]
+pub fn can_use_action(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    action: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
     let actions_str: Vec<String> = from_js_value(actions).unwrap();
-    let macro_text: Vec<String> = actions_str
+    let actions: Vec<Action> = actions_str
         .iter()
-        .map(|a| Action::from_str(a).unwrap().macro_text())
+        .map(|a| Action::from_str(a).unwrap())
         .collect();
+    let action_str: String = from_js_value(action).unwrap();
+    let action = Action::from_str(&action_str).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
 
-    to_js_value(&macro_text).unwrap().unchecked_into()
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let (end_state, _) = Simulator::simulate(&context, actions);
+    let reason: Option<InvalidActionReason> = end_state.can_use(action).err();
+
+    Ok(to_js_value(&reason).unwrap())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_VALIDATE_CRAFT_OPTIONS: &'static str = r#"
+export function validateCraftOptions(craft_options: unknown): string | undefined;
+"#;
+
+#[wasm_bindgen(js_name = validateCraftOptions, skip_typescript)]
+pub fn validate_craft_options(craft_options: JsValue) -> Option<String> {
+    match from_js_value::<CraftOptions>(craft_options) {
+        Ok(craft_options) => craft_options.validate().err().map(|err| err.to_string()),
+        Err(err) => Some(err.to_string()),
+    }
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_CAN_COMPLETE: &'static str = r#"
+export function canComplete(recipe: Recipe, player: Player, craft_options: CraftOptions): boolean;
+"#;
+
+#[wasm_bindgen(js_name = canComplete, skip_typescript)]
+pub fn can_complete(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+) -> Result<bool, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    Ok(Simulator::can_complete(&context))
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_CAN_REACH_QUALITY: &'static str = r#"
+export function canReachQuality(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+    quality_target: number,
+): boolean;
+"#;
+
+#[wasm_bindgen(js_name = canReachQuality, skip_typescript)]
+pub fn can_reach_quality(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+    quality_target: u32,
+) -> Result<bool, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    Ok(Simulator::can_reach_quality(&context, quality_target))
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_MINIMUM_CP_FOR_HQ: &'static str = r#"
+export function minimumCpForHq(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+): number | undefined;
+"#;
+
+#[wasm_bindgen(js_name = minimumCpForHq, skip_typescript)]
+pub fn minimum_cp_for_hq(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+) -> Result<Option<u32>, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    // only to surface invalid options
+    craft_context(&player, &recipe, craft_options)?;
+    Ok(Simulator::minimum_cp_for_hq(
+        &player,
+        &recipe,
+        &craft_options,
+    ))
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_CRAFT_FINGERPRINT: &'static str = r#"
+export function craftFingerprint(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+): string;
+"#;
+
+/// A key for caching results of a craft, which stays the same across builds.
+/// It's a string since fingerprints don't fit in a JS number.
+#[wasm_bindgen(js_name = craftFingerprint, skip_typescript)]
+pub fn craft_fingerprint(recipe: JsValue, player: JsValue, craft_options: JsValue) -> String {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    format!(
+        "{:016x}{:016x}{:016x}",
+        recipe.fingerprint(),
+        player.fingerprint(),
+        craft_options.fingerprint()
+    )
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_PREFLIGHT: &'static str = r#"
+export function preflight(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+): Preflight;
+"#;
+
+#[wasm_bindgen(js_name = preflight, skip_typescript)]
+pub fn preflight(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    // only to surface invalid options; unmet stat requirements are reported
+    // in the preflight itself
+    craft_options
+        .validate()
+        .map_err(|err| JsError::new(&err.to_string()))?;
+    let preflight = Simulator::preflight(&player, &recipe, &craft_options);
+
+    Ok(to_js_value(&preflight).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_MAX_REACHABLE_QUALITY: &'static str = r#"
+export function maxReachableQuality(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+): number | undefined;
+"#;
+
+#[wasm_bindgen(js_name = maxReachableQuality, skip_typescript)]
+pub fn max_reachable_quality(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+    search_options: JsValue,
+) -> Result<Option<u32>, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+
+    // only to surface invalid options
+    craft_context(&player, &recipe, craft_options)?;
+    Ok(Simulator::max_reachable_quality(
+        &player,
+        &recipe,
+        &craft_options,
+        search_options,
+    ))
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_QUALITY_CP_CURVE: &'static str = r#"
+export function qualityCpCurve(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+    cps: number[],
+): [number, number | undefined][];
+"#;
+
+#[wasm_bindgen(js_name = qualityCpCurve, skip_typescript)]
+pub fn quality_cp_curve(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+    search_options: JsValue,
+    cps: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+    let cps: Vec<u32> = from_js_value(cps).unwrap();
+
+    // only to surface invalid options
+    craft_context(&player, &recipe, craft_options)?;
+    let curve = Simulator::quality_cp_curve(&player, &recipe, &craft_options, search_options, &cps);
+
+    Ok(to_js_value(&curve).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_SEARCH_COLLECTABLE: &'static str = r#"
+export function searchCollectable(
+    recipe: Recipe,
+    player: Player,
+    target_collectability: number,
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+    samples: number,
+): CollectableRotation | undefined;
+"#;
+
+#[wasm_bindgen(js_name = searchCollectable, skip_typescript)]
+pub fn search_collectable(
+    recipe: JsValue,
+    player: JsValue,
+    target_collectability: u32,
+    craft_options: JsValue,
+    search_options: JsValue,
+    samples: u32,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+
+    // only to surface invalid options
+    craft_context(&player, &recipe, craft_options)?;
+    let found = Simulator::search_collectable(
+        &player,
+        &recipe,
+        target_collectability,
+        &craft_options,
+        search_options,
+        samples,
+    );
+
+    Ok(to_js_value(&found).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_SENSITIVITY: &'static str = r#"
+export function sensitivity(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+    stat_delta: number,
+): StatSensitivity;
+"#;
+
+#[wasm_bindgen(js_name = sensitivity, skip_typescript)]
+pub fn sensitivity(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+    search_options: JsValue,
+    stat_delta: u32,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+
+    // only to surface invalid options
+    craft_context(&player, &recipe, craft_options)?;
+    let sensitivity =
+        Simulator::sensitivity(&player, &recipe, &craft_options, search_options, stat_delta);
+
+    Ok(to_js_value(&sensitivity).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_STANDARD_ROTATION: &'static str = r#"
+export function standardRotation(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+): Action[] | undefined;
+"#;
+
+/// A quick rotation to show until a search finishes
+#[wasm_bindgen(js_name = standardRotation, skip_typescript)]
+pub fn standard_rotation(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let actions = Simulator::standard_rotation(&context);
+
+    let actions_str: Option<Vec<&'static str>> =
+        actions.map(|actions| actions.iter().map(|a| a.name()).collect());
+    Ok(to_js_value(&actions_str).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_SHORTEST_ROTATION_FOR_HQ: &'static str = r#"
+export function shortestRotationForHq(
+    recipe: Recipe,
+    player: Player,
+    target_hq_percent: number,
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+): Action[] | undefined;
+"#;
+
+#[wasm_bindgen(js_name = shortestRotationForHq, skip_typescript)]
+pub fn shortest_rotation_for_hq(
+    recipe: JsValue,
+    player: JsValue,
+    target_hq_percent: u8,
+    craft_options: JsValue,
+    search_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+
+    // only to surface invalid options
+    craft_context(&player, &recipe, craft_options)?;
+    let actions = Simulator::shortest_rotation_for_hq(
+        &player,
+        &recipe,
+        target_hq_percent,
+        &craft_options,
+        search_options,
+    );
+
+    let actions_str: Option<Vec<&'static str>> =
+        actions.map(|actions| actions.iter().map(|a| a.name()).collect());
+    Ok(to_js_value(&actions_str).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_EVALUATE_ROTATION: &'static str = r#"
+export function evaluateRotation(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+    samples: number,
+): RotationEvaluation;
+"#;
+
+#[wasm_bindgen(js_name = evaluateRotation, skip_typescript)]
+pub fn evaluate_rotation(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+    samples: u32,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let evaluation = Simulator::evaluate(&context, &actions, samples);
+
+    Ok(to_js_value(&evaluation).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_SEARCH_FROM_IN_GAME_STATE: &'static str = r#"
+export function searchFromInGameState(
+    recipe: Recipe,
+    player: Player,
+    craft_options: CraftOptions,
+    in_game_state: InGameState,
+    search_options: SearchOptions,
+): Action[];
+"#;
+
+/// Suggests how to continue a craft from the values a plugin reads in game.
+/// Only the actions after the current state are returned.
+#[wasm_bindgen(js_name = searchFromInGameState, skip_typescript)]
+pub fn search_from_in_game_state(
+    recipe: JsValue,
+    player: JsValue,
+    craft_options: JsValue,
+    in_game_state: JsValue,
+    search_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let in_game_state: InGameState = from_js_value(in_game_state)?;
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let state = CraftState::from_in_game(&context, &in_game_state);
+    let (actions, state) = Simulator::search_from_state(&state, search_options);
+    search_options
+        .check_solution(&state)
+        .map_err(|err| JsError::new(&err.to_string()))?;
+
+    let actions_str: Vec<&'static str> = actions.iter().map(|a| a.name()).collect();
+    Ok(to_js_value(&actions_str).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_SEARCH_STREAMING: &'static str = r#"
+export function searchStreaming(
+    recipe: Recipe,
+    player: Player,
+    action_history: Action[],
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+    report_interval: number,
+    improvement_callback: (improvement: SearchImprovement) => void,
+): Action[];
+"#;
+
+#[wasm_bindgen(js_name = searchStreaming, skip_typescript)]
+#[allow(clippy::too_many_arguments)]
+pub fn search_streaming(
+    recipe: JsValue,
+    player: JsValue,
+    action_history: JsValue,
+    craft_options: JsValue,
+    search_options: JsValue,
+    report_interval: u32,
+    improvement_callback: js_sys::Function,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let action_history_str: Vec<String> = from_js_value(action_history).unwrap();
+    let action_history: Vec<Action> = action_history_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+
+    let callback = |improvement: SearchImprovement| {
+        let null = JsValue::null();
+        let improvement = to_js_value(&improvement).unwrap();
+        improvement_callback.call1(&null, &improvement).unwrap();
+    };
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let (actions, state) = Simulator::search_streaming(
+        &context,
+        action_history,
+        search_options,
+        report_interval,
+        &callback,
+    );
+    search_options
+        .check_solution(&state)
+        .map_err(|err| JsError::new(&err.to_string()))?;
+
+    let actions_str: Vec<&'static str> = actions.iter().map(|a| a.name()).collect();
+    Ok(to_js_value(&actions_str).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_STEP_CRAFT: &'static str = r#"
+export function stepCraft(
+    recipe: Recipe,
+    player: Player,
+    craft_state: CraftState,
+    action: Action,
+    craft_options: CraftOptions,
+): SimulatorResult;
+"#;
+
+/// Uses a single action on a `craft_state` from a previous result, so a
+/// rotation can be built one click at a time without replaying it. The
+/// condition isn't rolled; set `craft_state.condition` before each step to
+/// use a different one. An action that can't be used leaves the state as is
+/// and is reported as the failure, at index 0.
+#[wasm_bindgen(js_name = stepCraft, skip_typescript)]
+pub fn step_craft(
+    recipe: JsValue,
+    player: JsValue,
+    craft_state: JsValue,
+    action: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let craft_state: SerializedCraftState = from_js_value(craft_state)?;
+    let action_str: String = from_js_value(action).unwrap();
+    let action = Action::from_str(&action_str).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let state = CraftState::from_serialized(&context, &craft_state);
+    let (end_state, result, failure) = match state.can_use(action) {
+        Ok(()) => {
+            let next_state = state.execute(&action);
+            let result = next_state.check_result();
+            let failure = matches!(
+                result,
+                Some(CraftResult::DurabilityFailure | CraftResult::MaxStepsFailure)
+            )
+            .then_some(FailurePoint {
+                action_index: 0,
+                invalid_reason: None,
+            });
+            (next_state, result, failure)
+        }
+        Err(reason) => {
+            let failure = FailurePoint {
+                action_index: 0,
+                invalid_reason: Some(reason),
+            };
+            (
+                state,
+                Some(CraftResult::InvalidActionFailure),
+                Some(failure),
+            )
+        }
+    };
+
+    let sim_result = SimulatorResult {
+        score: end_state.score(),
+        score_explanation: end_state.explain_score(),
+        craft_state: end_state,
+        completion_reason: CompletionReason::from_craft_result(result),
+        failure,
+    };
+
+    Ok(to_js_value(&sim_result).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_QUALITY_BY_INNER_QUIET: &'static str = r#"
+export function qualityByInnerQuiet(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    action: Action,
+    craft_options: CraftOptions,
+): number[] | null;
+"#;
+
+/// How much quality `action` would add after `actions` at each Inner Quiet
+/// stack count, from 0 to 10. Null if the action doesn't add quality.
+#[wasm_bindgen(js_name = qualityByInnerQuiet, skip_typescript)]
+pub fn quality_by_inner_quiet(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    action: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let action_str: String = from_js_value(action).unwrap();
+    let action = Action::from_str(&action_str).unwrap();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let (state, _) = Simulator::simulate(&context, actions);
+    let increases = state.quality_by_inner_quiet(action);
+
+    Ok(to_js_value(&increases).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_RESOURCE_TIMELINE: &'static str = r#"
+export function resourceTimeline(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+): ResourcePoint[];
+"#;
+
+#[wasm_bindgen(js_name = resourceTimeline, skip_typescript)]
+pub fn resource_timeline(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let timeline = Simulator::resource_timeline(&context, &actions);
+
+    Ok(to_js_value(&timeline).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_TRIM_ROTATION: &'static str = r#"
+export function trimRotation(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+): Action[];
+"#;
+
+#[wasm_bindgen(js_name = trimRotation, skip_typescript)]
+pub fn trim_rotation(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let trimmed = Simulator::trim_rotation(&context, &actions);
+
+    let trimmed_str: Vec<&'static str> = trimmed.iter().map(|a| a.name()).collect();
+    Ok(to_js_value(&trimmed_str).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_INSERT_DURABILITY_RECOVERY: &'static str = r#"
+export function insertDurabilityRecovery(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+): Action[];
+"#;
+
+#[wasm_bindgen(js_name = insertDurabilityRecovery, skip_typescript)]
+pub fn insert_durability_recovery(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let planned = Simulator::insert_durability_recovery(&context, &actions);
+
+    let planned_str: Vec<&'static str> = planned.iter().map(|a| a.name()).collect();
+    Ok(to_js_value(&planned_str).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_ROTATION_DIFFICULTY: &'static str = r#"
+export function rotationDifficulty(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+): Difficulty;
+"#;
+
+#[wasm_bindgen(js_name = rotationDifficulty, skip_typescript)]
+pub fn rotation_difficulty(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let difficulty = Simulator::rotation_difficulty(&context, &actions);
+
+    Ok(to_js_value(&difficulty).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_MIN_DURABILITY_DURING: &'static str = r#"
+export function minDurabilityDuring(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+): number;
+"#;
+
+#[wasm_bindgen(js_name = minDurabilityDuring, skip_typescript)]
+pub fn min_durability_during(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+) -> Result<i8, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    Ok(Simulator::min_durability_during(&context, &actions))
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_AVAILABLE_MOVES_PER_STEP: &'static str = r#"
+export function availableMovesPerStep(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+): Action[][];
+"#;
+
+#[wasm_bindgen(js_name = availableMovesPerStep, skip_typescript)]
+pub fn available_moves_per_step(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let moves = Simulator::available_moves_per_step(&context, &actions);
+
+    Ok(to_js_value(&moves).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_LINT_ROTATION: &'static str = r#"
+export function lintRotation(
+    recipe: Recipe,
+    player: Player,
+    actions: Action[],
+    craft_options: CraftOptions,
+): RotationWarning[];
+"#;
+
+#[wasm_bindgen(js_name = lintRotation, skip_typescript)]
+pub fn lint_rotation(
+    recipe: JsValue,
+    player: JsValue,
+    actions: JsValue,
+    craft_options: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let warnings = Simulator::lint_rotation(&context, &actions);
+
+    Ok(to_js_value(&warnings).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_SEARCH_STEPWISE: &'static str = r#"
+export function searchStepwise(
+    recipe: Recipe,
+    player: Player,
+    action_history: Action[],
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+    action_callback: (action: Action) => void,
+): Action[];
+"#;
+
+#[wasm_bindgen(js_name = searchStepwise, skip_typescript)]
+pub fn search_stepwise(
+    recipe: JsValue,
+    player: JsValue,
+    action_history: JsValue,
+    craft_options: JsValue,
+    search_options: JsValue,
+    action_callback: js_sys::Function,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let action_history_str: Vec<String> = from_js_value(action_history).unwrap();
+    let action_history: Vec<Action> = action_history_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+
+    let callback = |action: Action| {
+        let null = JsValue::null();
+        let action_str = JsValue::from(action.name());
+        action_callback.call1(&null, &action_str).unwrap();
+    };
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let (actions, state) =
+        Simulator::search_stepwise(&context, action_history, search_options, Some(&callback));
+    search_options
+        .check_solution(&state)
+        .map_err(|err| JsError::new(&err.to_string()))?;
+
+    let actions_str: Vec<&'static str> = actions.iter().map(|a| a.name()).collect();
+    Ok(to_js_value(&actions_str).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_SUGGEST_NEXT_ACTION: &'static str = r#"
+export function suggestNextAction(
+    recipe: Recipe,
+    player: Player,
+    action_history: Action[],
+    craft_options: CraftOptions,
+    search_options: SearchOptions,
+    snapshot: SearchSnapshot | undefined,
+): ActionSuggestion | undefined;
+"#;
+
+#[wasm_bindgen(js_name = suggestNextAction, skip_typescript)]
+pub fn suggest_next_action(
+    recipe: JsValue,
+    player: JsValue,
+    action_history: JsValue,
+    craft_options: JsValue,
+    search_options: JsValue,
+    snapshot: JsValue,
+) -> Result<JsValue, JsError> {
+    console_error_panic_hook::set_once();
+
+    let recipe: Recipe = from_js_value(recipe).unwrap();
+    let player: Player = from_js_value(player).unwrap();
+    let action_history_str: Vec<String> = from_js_value(action_history).unwrap();
+    let action_history: Vec<Action> = action_history_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let craft_options: CraftOptions = from_js_value(craft_options).unwrap();
+    let search_options: SearchOptions = from_js_value(search_options).unwrap();
+    let snapshot: Option<SearchSnapshot> = from_js_value(snapshot).unwrap();
+
+    let context = craft_context(&player, &recipe, craft_options)?;
+    let suggestion = Simulator::suggest_next_action(
+        &context,
+        &action_history,
+        search_options,
+        snapshot.as_ref(),
+    );
+
+    Ok(to_js_value(&suggestion).unwrap())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_ENCODE_ROTATION: &'static str = r#"
+export function encodeRotation(actions: Action[]): string;
+"#;
+
+#[wasm_bindgen(js_name = encodeRotation, skip_typescript)]
+pub fn encode_rotation(actions: JsValue) -> String {
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+
+    crafty::rotation_code::encode_rotation(&actions)
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_DECODE_ROTATION: &'static str = r#"
+export function decodeRotation(code: string): Action[];
+"#;
+
+#[wasm_bindgen(js_name = decodeRotation, skip_typescript)]
+pub fn decode_rotation(code: &str) -> Result<JsValue, JsError> {
+    let actions = crafty::rotation_code::decode_rotation(code)
+        .map_err(|err| JsError::new(&err.to_string()))?;
+
+    Ok(to_js_value(&actions).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_GENERATE_MACRO_TEXT: &'static str = r#"
+export function generateMacroText(actions: Action[]): string[];
+"#;
+
+#[wasm_bindgen(js_name = generateMacroText, skip_typescript)]
+pub fn generate_macro_text(actions: JsValue) -> JsValue {
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let macro_text: Vec<String> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap().macro_text())
+        .collect();
+
+    to_js_value(&macro_text).unwrap().unchecked_into()
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_GENERATE_MACROS: &'static str = r#"
+export function generateMacros(actions: Action[], macro_options: MacroOptions): string[][];
+"#;
+
+#[wasm_bindgen(js_name = generateMacros, skip_typescript)]
+pub fn generate_macros(actions: JsValue, macro_options: JsValue) -> Result<JsValue, JsError> {
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let macro_options: MacroOptions = from_js_value(macro_options).unwrap();
+    let macros = crafty::macros::generate_macros(&actions, &macro_options)
+        .map_err(|err| JsError::new(&err.to_string()))?;
+
+    Ok(to_js_value(&macros).unwrap().unchecked_into())
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPE_MACRO_DURATION_SECS: &'static str = r#"
+export function macroDurationSecs(actions: Action[], macro_options: MacroOptions): number;
+"#;
+
+#[wasm_bindgen(js_name = macroDurationSecs, skip_typescript)]
+pub fn macro_duration_secs(actions: JsValue, macro_options: JsValue) -> u32 {
+    let actions_str: Vec<String> = from_js_value(actions).unwrap();
+    let actions: Vec<Action> = actions_str
+        .iter()
+        .map(|a| Action::from_str(a).unwrap())
+        .collect();
+    let macro_options: MacroOptions = from_js_value(macro_options).unwrap();
+
+    crafty::macros::duration_secs(&actions, &macro_options)
 }